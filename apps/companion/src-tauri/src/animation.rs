@@ -0,0 +1,180 @@
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, DynamicImage, Frames};
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+// frames looked at when picking a preview; later ones rarely differ much more
+const MAX_SAMPLED_FRAMES: usize = 12;
+// detail is compared on a downscaled copy, so big frames cost the same
+const DETAIL_EDGE: u32 = 64;
+
+fn read_array<const N: usize>(r: &mut impl Read) -> Option<[u8; N]> {
+    let mut buf = [0u8; N];
+    r.read_exact(&mut buf).ok()?;
+    Some(buf)
+}
+
+// Skips a run of GIF data sub-blocks, ending at the zero-length terminator.
+fn skip_sub_blocks(r: &mut (impl Read + Seek)) -> Option<()> {
+    loop {
+        let [len] = read_array::<1>(r)?;
+        if len == 0 {
+            return Some(());
+        }
+        r.seek(SeekFrom::Current(len as i64)).ok()?;
+    }
+}
+
+// Walks the block structure and stops at the second image descriptor; the
+// pixel data in between is skipped, never decompressed.
+fn gif_animated(r: &mut (impl Read + Seek)) -> Option<bool> {
+    r.seek(SeekFrom::Start(10)).ok()?;
+    let [flags, _, _] = read_array::<3>(r)?;
+    if flags & 0x80 != 0 {
+        let table = 3 * (1i64 << ((flags & 0x07) + 1));
+        r.seek(SeekFrom::Current(table)).ok()?;
+    }
+    let mut frames = 0;
+    loop {
+        match read_array::<1>(r)? {
+            [0x21] => {
+                read_array::<1>(r)?;
+                skip_sub_blocks(r)?;
+            }
+            [0x2C] => {
+                frames += 1;
+                if frames > 1 {
+                    return Some(true);
+                }
+                let descriptor = read_array::<9>(r)?;
+                if descriptor[8] & 0x80 != 0 {
+                    let table = 3 * (1i64 << ((descriptor[8] & 0x07) + 1));
+                    r.seek(SeekFrom::Current(table)).ok()?;
+                }
+                // LZW minimum code size, then the image data
+                read_array::<1>(r)?;
+                skip_sub_blocks(r)?;
+            }
+            // trailer, or a truncated file that still showed one frame
+            _ => return Some(false),
+        }
+    }
+}
+
+// APNG announces itself with an `acTL` chunk before the first `IDAT`.
+fn png_animated(r: &mut (impl Read + Seek)) -> Option<bool> {
+    r.seek(SeekFrom::Start(8)).ok()?;
+    loop {
+        let header = read_array::<8>(r)?;
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        match &header[4..] {
+            b"acTL" => {
+                let frames = u32::from_be_bytes(read_array::<4>(r)?);
+                return Some(frames > 1);
+            }
+            b"IDAT" | b"IEND" => return Some(false),
+            // chunk data plus its CRC
+            _ => r.seek(SeekFrom::Current(len as i64 + 4)).ok()?,
+        };
+    }
+}
+
+// AVIF image sequences carry the `avis` brand in their `ftyp` box.
+fn avif_animated(header: &[u8]) -> Option<bool> {
+    let size = u32::from_be_bytes(header.get(0..4)?.try_into().ok()?) as usize;
+    let brands = header.get(8..size.min(header.len()))?;
+    let mut is_avif = false;
+    for brand in brands.chunks_exact(4) {
+        match brand {
+            b"avis" => return Some(true),
+            b"avif" => is_avif = true,
+            _ => {}
+        }
+    }
+    is_avif.then_some(false)
+}
+
+fn detect(r: &mut (impl Read + Seek)) -> Option<bool> {
+    let mut header = [0u8; 64];
+    let n = r.read(&mut header).ok()?;
+    let header = &header[..n];
+    if header.starts_with(b"GIF87a") || header.starts_with(b"GIF89a") {
+        gif_animated(r)
+    } else if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_animated(r)
+    } else if header.starts_with(b"RIFF") && header.get(8..12) == Some(b"WEBP") {
+        // only the extended format can animate; its flags say whether it does
+        Some(header.get(12..16) == Some(b"VP8X") && header.get(20).is_some_and(|f| f & 0x02 != 0))
+    } else if header.get(4..8) == Some(b"ftyp") {
+        avif_animated(header)
+    } else {
+        None
+    }
+}
+
+/// Whether an image holds more than one frame, judged from its headers.
+/// None for formats that can't animate or files that can't be read.
+pub fn is_animated(path: &Path) -> Option<bool> {
+    let file = std::fs::File::open(path).ok()?;
+    detect(&mut BufReader::new(file))
+}
+
+/// Whether `representative_frame` can pick a frame: an animated GIF, WebP or
+/// APNG. AVIF sequences are left out, since there is no decoder for them.
+pub fn is_animated_bytes(bytes: &[u8]) -> bool {
+    bytes.get(4..8) != Some(b"ftyp") && detect(&mut Cursor::new(bytes)) == Some(true)
+}
+
+// Edge energy of a small grayscale copy: blank or fading-in frames score low.
+fn detail(frame: &DynamicImage) -> u64 {
+    let small = frame.thumbnail(DETAIL_EDGE, DETAIL_EDGE).to_luma8();
+    small
+        .rows()
+        .map(|row| {
+            let row: Vec<u8> = row.map(|p| p.0[0]).collect();
+            row.windows(2)
+                .map(|w| w[0].abs_diff(w[1]) as u64)
+                .sum::<u64>()
+        })
+        .sum()
+}
+
+fn frames(bytes: &[u8]) -> Result<Frames<'_>, String> {
+    let cursor = Cursor::new(bytes);
+    let frames = if bytes.starts_with(b"GIF") {
+        GifDecoder::new(cursor)
+            .map_err(|e| e.to_string())?
+            .into_frames()
+    } else if bytes.starts_with(b"RIFF") {
+        WebPDecoder::new(cursor)
+            .map_err(|e| e.to_string())?
+            .into_frames()
+    } else if bytes.starts_with(b"\x89PNG") {
+        PngDecoder::new(cursor)
+            .and_then(|d| d.apng())
+            .map_err(|e| e.to_string())?
+            .into_frames()
+    } else {
+        return Err("not a GIF, WebP or PNG animation".into());
+    };
+    Ok(frames)
+}
+
+/// The most detailed of the first few frames of an animated GIF, WebP or
+/// APNG, so previews don't show a blank intro frame. Only the sampled frames
+/// are decoded.
+pub fn representative_frame(bytes: &[u8]) -> Result<DynamicImage, String> {
+    let mut best: Option<(u64, DynamicImage)> = None;
+    for frame in frames(bytes)?.take(MAX_SAMPLED_FRAMES) {
+        // frames arrive composited onto the full canvas
+        let frame = DynamicImage::ImageRgba8(frame.map_err(|e| e.to_string())?.into_buffer());
+        let score = detail(&frame);
+        if best.as_ref().map_or(true, |(top, _)| score > *top) {
+            best = Some((score, frame));
+        }
+    }
+    best.map(|(_, frame)| frame)
+        .ok_or_else(|| "animation has no frames".to_string())
+}
@@ -0,0 +1,87 @@
+use crate::settings;
+use bytes::Bytes;
+use futures_util::{stream, Stream, StreamExt};
+use once_cell::sync::Lazy;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Bytes per second for all uploads together; 0 means uncapped.
+static LIMIT: AtomicU64 = AtomicU64::new(0);
+// When the bytes granted so far will have gone out at the capped rate.
+static NEXT_SEND: Lazy<Mutex<Option<Instant>>> = Lazy::new(|| Mutex::new(None));
+// Lines carrying inlined files can be megabytes; pacing smaller pieces keeps
+// the rate even instead of bursty.
+const PIECE: usize = 64 * 1024;
+// Quiet time earns at most this much of a burst.
+const MAX_BURST: Duration = Duration::from_secs(1);
+// Below this the cap would starve every request into a timeout.
+const MIN_LIMIT: u64 = 16 * 1024;
+
+pub fn set_limit(bytes_per_sec: Option<u64>) {
+    let limit = bytes_per_sec
+        .filter(|b| *b > 0)
+        .map_or(0, |b| b.max(MIN_LIMIT));
+    LIMIT.store(limit, Ordering::SeqCst);
+}
+
+// Waits until `bytes` more may go out under the cap. Concurrent uploads share
+// one schedule, so the cap holds for their sum.
+async fn pace(bytes: usize) {
+    let limit = LIMIT.load(Ordering::SeqCst);
+    if limit == 0 {
+        return;
+    }
+    let wait = {
+        let mut next = NEXT_SEND.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let start = next.map_or(now, |t| t.max(now));
+        let end = start + Duration::from_secs_f64(bytes as f64 / limit as f64);
+        *next = Some(end);
+        // the schedule may run ahead of the clock by one burst, no further
+        end.saturating_duration_since(now).saturating_sub(MAX_BURST)
+    };
+    if !wait.is_zero() {
+        tokio::time::sleep(wait).await;
+    }
+}
+
+fn pieces(chunk: io::Result<Bytes>) -> Vec<io::Result<Bytes>> {
+    let Ok(bytes) = chunk else {
+        return vec![chunk];
+    };
+    (0..bytes.len())
+        .step_by(PIECE)
+        .map(|at| Ok(bytes.slice(at..(at + PIECE).min(bytes.len()))))
+        .collect()
+}
+
+/// Holds back an upload body so uploads stay within the saved
+/// `max_bytes_per_sec`. Apply it to what goes on the wire, i.e. after
+/// compression.
+pub fn limit<S>(body: S) -> impl Stream<Item = io::Result<Bytes>> + Send + 'static
+where
+    S: Stream<Item = io::Result<Bytes>> + Send + 'static,
+{
+    body.flat_map(|chunk| stream::iter(pieces(chunk)))
+        .then(|piece| async move {
+            if let Ok(bytes) = &piece {
+                pace(bytes.len()).await;
+            }
+            piece
+        })
+}
+
+/// Caps upload speed for every sync, in bytes per second, and saves it.
+/// None or 0 lifts the cap; anything below 16 KB/s is raised to that.
+#[tauri::command]
+pub async fn set_upload_rate_limit(
+    bytes_per_sec: Option<u64>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    set_limit(bytes_per_sec);
+    settings::update(&app, |s| {
+        s.upload_limits.max_bytes_per_sec = bytes_per_sec.filter(|b| *b > 0)
+    })
+}
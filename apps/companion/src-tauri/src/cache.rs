@@ -0,0 +1,127 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+pub const THUMBNAILS: &str = "thumbnails";
+pub const MERGED_PDFS: &str = "merged_pdfs";
+// Only these are ours to clear: the webview keeps its own cache under the
+// same app cache dir on some platforms.
+const SUBDIRS: &[&str] = &[THUMBNAILS, MERGED_PDFS];
+
+/// A directory of derived data under the app cache dir; everything in it
+/// can be regenerated.
+pub fn subdir(app: &tauri::AppHandle, name: &str) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| e.to_string())?
+        .join(name))
+}
+
+#[derive(Serialize, Default)]
+pub struct CacheDirStats {
+    pub name: String,
+    pub bytes: u64,
+    pub files: usize,
+}
+
+#[derive(Serialize, Default)]
+pub struct CacheStats {
+    pub total_bytes: u64,
+    pub file_count: usize,
+    pub dirs: Vec<CacheDirStats>,
+}
+
+#[derive(Serialize, Default)]
+pub struct ClearCacheResult {
+    pub freed_bytes: u64,
+    pub removed_files: usize,
+    /// Files that couldn't be removed (e.g. open elsewhere); they stay counted
+    /// in the next `get_cache_stats`.
+    pub errors: Vec<String>,
+}
+
+fn dir_stats(name: &str, dir: &Path) -> CacheDirStats {
+    let mut stats = CacheDirStats {
+        name: name.to_string(),
+        ..Default::default()
+    };
+    // a missing dir just means nothing has been cached there yet
+    for entry in walkdir::WalkDir::new(dir).into_iter().flatten() {
+        if entry.file_type().is_file() {
+            stats.bytes += entry.metadata().map_or(0, |md| md.len());
+            stats.files += 1;
+        }
+    }
+    stats
+}
+
+fn clear_dir(dir: &Path, result: &mut ClearCacheResult) {
+    for entry in walkdir::WalkDir::new(dir).contents_first(true).into_iter() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                // vanished under us, e.g. a second clear running at once
+                if err.io_error().map(|e| e.kind()) != Some(std::io::ErrorKind::NotFound) {
+                    result.errors.push(err.to_string());
+                }
+                continue;
+            }
+        };
+        let path = entry.path();
+        if entry.file_type().is_dir() {
+            // the cache dir itself goes too; the next writer recreates it
+            let _ = std::fs::remove_dir(path);
+            continue;
+        }
+        let size = entry.metadata().map_or(0, |md| md.len());
+        match std::fs::remove_file(path) {
+            Ok(()) => {
+                result.freed_bytes += size;
+                result.removed_files += 1;
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => result.errors.push(format!("{}: {}", path.display(), err)),
+        }
+    }
+}
+
+/// Size of the thumbnail and merged-PDF caches.
+#[tauri::command]
+pub async fn get_cache_stats(app: tauri::AppHandle) -> Result<CacheStats, String> {
+    let dirs = SUBDIRS
+        .iter()
+        .map(|name| subdir(&app, name).map(|dir| (*name, dir)))
+        .collect::<Result<Vec<_>, _>>()?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut stats = CacheStats::default();
+        for (name, dir) in dirs {
+            let dir = dir_stats(name, &dir);
+            stats.total_bytes += dir.bytes;
+            stats.file_count += dir.files;
+            stats.dirs.push(dir);
+        }
+        stats
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Deletes the thumbnail and merged-PDF caches. Sessions, settings and the
+/// sync queue live elsewhere and are never touched.
+#[tauri::command]
+pub async fn clear_cache(app: tauri::AppHandle) -> Result<ClearCacheResult, String> {
+    let dirs = SUBDIRS
+        .iter()
+        .map(|name| subdir(&app, name))
+        .collect::<Result<Vec<_>, _>>()?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut result = ClearCacheResult::default();
+        for dir in dirs {
+            clear_dir(&dir, &mut result);
+        }
+        result
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
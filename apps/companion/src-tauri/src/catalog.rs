@@ -0,0 +1,406 @@
+use crate::manifest::ScanManifest;
+use crate::MediaMeta;
+use rusqlite::{params, params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::Manager;
+
+const CATALOG_FILE: &str = "catalog.sqlite3";
+const DEFAULT_QUERY_LIMIT: usize = 200;
+const MAX_QUERY_LIMIT: usize = 5000;
+
+// `hash` is filled in when a scan hashes the file; a changed size or mtime
+// clears it along with the sync status.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS media (
+    path        TEXT PRIMARY KEY,
+    size        INTEGER NOT NULL,
+    mtime       TEXT,
+    modality    TEXT NOT NULL,
+    hash        TEXT,
+    sync_status TEXT NOT NULL DEFAULT 'pending',
+    synced_at   TEXT,
+    scanned_at  TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS media_sync_status ON media (sync_status);
+CREATE INDEX IF NOT EXISTS media_hash ON media (hash);
+";
+
+/// Where an indexed file stands with the server.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStatus {
+    Pending,
+    Synced,
+    Failed,
+}
+
+impl SyncStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            SyncStatus::Pending => "pending",
+            SyncStatus::Synced => "synced",
+            SyncStatus::Failed => "failed",
+        }
+    }
+}
+
+fn catalog_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(CATALOG_FILE))
+        .map_err(|e| e.to_string())
+}
+
+fn open(app: &tauri::AppHandle) -> Result<Connection, String> {
+    let path = catalog_path(app)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let conn = Connection::open(&path).map_err(|e| e.to_string())?;
+    // a scan and a sync may write at the same time
+    conn.busy_timeout(Duration::from_secs(5))
+        .map_err(|e| e.to_string())?;
+    conn.execute_batch(SCHEMA).map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+// Catalog work is plain file I/O; keep it off the async runtime.
+async fn with_catalog<T: Send + 'static>(
+    app: &tauri::AppHandle,
+    work: impl FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+) -> Result<T, String> {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut conn = open(&app)?;
+        work(&mut conn).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Records the files a scan matched and drops the ones it found removed.
+pub async fn record_scan(
+    app: &tauri::AppHandle,
+    items: Vec<MediaMeta>,
+    removed: Vec<String>,
+) -> Result<(), String> {
+    let now = chrono::Utc::now().to_rfc3339();
+    with_catalog(app, move |conn| {
+        let tx = conn.transaction()?;
+        {
+            let mut upsert = tx.prepare(
+                "INSERT INTO media (path, size, mtime, modality, hash, scanned_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT (path) DO UPDATE SET
+                     hash = COALESCE(excluded.hash,
+                                     CASE WHEN size = excluded.size AND mtime IS excluded.mtime
+                                          THEN hash END),
+                     sync_status = CASE WHEN size = excluded.size AND mtime IS excluded.mtime
+                                        THEN sync_status ELSE 'pending' END,
+                     size = excluded.size,
+                     mtime = excluded.mtime,
+                     modality = excluded.modality,
+                     scanned_at = excluded.scanned_at",
+            )?;
+            for item in &items {
+                upsert.execute(params![
+                    item.path,
+                    item.size as i64,
+                    item.modified,
+                    item.modality,
+                    item.hash,
+                    now
+                ])?;
+            }
+            let mut delete = tx.prepare("DELETE FROM media WHERE path = ?1")?;
+            for path in &removed {
+                delete.execute([path])?;
+            }
+        }
+        tx.commit()
+    })
+    .await
+}
+
+// Matches `path` itself and everything below it; the separator keeps
+// /photos from matching /photos-old. Binds ?1.
+const UNDER_PATH: &str =
+    "(path = ?1 OR substr(path, 1, length(?1) + 1) IN (?1 || '/', ?1 || '\\'))";
+
+fn folder_key(prefix: &str) -> String {
+    prefix.trim_end_matches(['/', '\\']).to_string()
+}
+
+/// Size and mtime of every catalogued file under `folder`, as a scan
+/// manifest.
+pub async fn manifest_under(app: &tauri::AppHandle, folder: &str) -> Result<ScanManifest, String> {
+    let dir = folder_key(folder);
+    with_catalog(app, move |conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT path, size, mtime FROM media WHERE {UNDER_PATH}"
+        ))?;
+        let rows = stmt.query_map([dir], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                (
+                    row.get::<_, i64>(1)? as u64,
+                    row.get::<_, Option<String>>(2)?,
+                ),
+            ))
+        })?;
+        rows.collect()
+    })
+    .await
+}
+
+/// Path -> (size, mtime) fingerprint and the hash taken at it.
+pub type KnownHashes = HashMap<String, ((u64, Option<String>), String)>;
+
+/// Content hashes already recorded for files under `folder`, with the size
+/// and mtime they were taken at, so a scan only hashes what changed.
+pub async fn hashes_under(app: &tauri::AppHandle, folder: &str) -> Result<KnownHashes, String> {
+    let dir = folder_key(folder);
+    with_catalog(app, move |conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT path, size, mtime, hash FROM media WHERE hash IS NOT NULL AND {UNDER_PATH}"
+        ))?;
+        let rows = stmt.query_map([dir], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                (
+                    (
+                        row.get::<_, i64>(1)? as u64,
+                        row.get::<_, Option<String>>(2)?,
+                    ),
+                    row.get::<_, String>(3)?,
+                ),
+            ))
+        })?;
+        rows.collect()
+    })
+    .await
+}
+
+/// Sets the sync status of indexed files; paths not in the catalog are
+/// ignored.
+pub async fn mark_sync_status(
+    app: &tauri::AppHandle,
+    paths: Vec<String>,
+    status: SyncStatus,
+) -> Result<(), String> {
+    let synced_at = (status == SyncStatus::Synced).then(|| chrono::Utc::now().to_rfc3339());
+    with_catalog(app, move |conn| {
+        let tx = conn.transaction()?;
+        {
+            let mut update = tx.prepare(
+                "UPDATE media SET sync_status = ?2, synced_at = COALESCE(?3, synced_at)
+                 WHERE path = ?1",
+            )?;
+            for path in &paths {
+                update.execute(params![path, status.as_str(), synced_at])?;
+            }
+        }
+        tx.commit()
+    })
+    .await
+}
+
+#[derive(Serialize, Default)]
+pub struct IndexStats {
+    pub total: usize,
+    pub total_bytes: u64,
+    pub by_modality: HashMap<String, usize>,
+    pub by_status: HashMap<String, usize>,
+    pub last_scanned_at: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct IndexEntry {
+    pub path: String,
+    pub size: u64,
+    pub mtime: Option<String>,
+    pub modality: String,
+    pub hash: Option<String>,
+    pub sync_status: SyncStatus,
+    pub synced_at: Option<String>,
+    pub scanned_at: String,
+}
+
+/// Filters for `query_index`; all optional, combined with AND.
+#[derive(Deserialize, Default)]
+pub struct IndexQuery {
+    /// Only files under this folder.
+    pub path_prefix: Option<String>,
+    pub modality: Option<String>,
+    pub sync_status: Option<SyncStatus>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct IndexPage {
+    pub items: Vec<IndexEntry>,
+    /// Matches before `limit`/`offset`.
+    pub total: usize,
+}
+
+fn count_by(conn: &Connection, column: &str) -> rusqlite::Result<HashMap<String, usize>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {column}, COUNT(*) FROM media GROUP BY {column}"
+    ))?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
+    })?;
+    rows.collect()
+}
+
+/// Counts and sizes of everything in the local catalog.
+#[tauri::command]
+pub async fn get_index_stats(app: tauri::AppHandle) -> Result<IndexStats, String> {
+    with_catalog(&app, |conn| {
+        let (total, total_bytes, last_scanned_at) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(size), 0), MAX(scanned_at) FROM media",
+            [],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)? as usize,
+                    row.get::<_, i64>(1)? as u64,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            },
+        )?;
+        Ok(IndexStats {
+            total,
+            total_bytes,
+            by_modality: count_by(conn, "modality")?,
+            by_status: count_by(conn, "sync_status")?,
+            last_scanned_at,
+        })
+    })
+    .await
+}
+
+/// One page of catalog entries, ordered by path.
+#[tauri::command]
+pub async fn query_index(
+    query: Option<IndexQuery>,
+    app: tauri::AppHandle,
+) -> Result<IndexPage, String> {
+    let query = query.unwrap_or_default();
+    let mut clauses: Vec<&str> = Vec::new();
+    let mut args: Vec<String> = Vec::new();
+    if let Some(prefix) = query.path_prefix.filter(|p| !p.trim().is_empty()) {
+        clauses.push(UNDER_PATH);
+        args.push(folder_key(&prefix));
+    }
+    if let Some(modality) = query.modality {
+        clauses.push("modality = ?");
+        args.push(modality);
+    }
+    if let Some(status) = query.sync_status {
+        clauses.push("sync_status = ?");
+        args.push(status.as_str().to_string());
+    }
+    let filter = if clauses.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", clauses.join(" AND "))
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_QUERY_LIMIT)
+        .clamp(1, MAX_QUERY_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+    with_catalog(&app, move |conn| {
+        let total = conn.query_row(
+            &format!("SELECT COUNT(*) FROM media {filter}"),
+            params_from_iter(&args),
+            |row| row.get::<_, i64>(0),
+        )? as usize;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT path, size, mtime, modality, hash, sync_status, synced_at, scanned_at
+             FROM media {filter} ORDER BY path LIMIT {limit} OFFSET {offset}"
+        ))?;
+        let items = stmt
+            .query_map(params_from_iter(&args), |row| {
+                let status: String = row.get(5)?;
+                Ok(IndexEntry {
+                    path: row.get(0)?,
+                    size: row.get::<_, i64>(1)? as u64,
+                    mtime: row.get(2)?,
+                    modality: row.get(3)?,
+                    hash: row.get(4)?,
+                    sync_status: match status.as_str() {
+                        "synced" => SyncStatus::Synced,
+                        "failed" => SyncStatus::Failed,
+                        _ => SyncStatus::Pending,
+                    },
+                    synced_at: row.get(6)?,
+                    scanned_at: row.get(7)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(IndexPage { items, total })
+    })
+    .await
+}
+
+/// Empties the local catalog and returns how many entries were dropped. The
+/// server's index is not touched; the next scan fills the catalog again.
+#[tauri::command]
+pub async fn clear_index(app: tauri::AppHandle) -> Result<usize, String> {
+    with_catalog(&app, |conn| conn.execute("DELETE FROM media", [])).await
+}
+
+/// Files in the catalog that share one content hash.
+#[derive(Serialize)]
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub size: u64,
+    pub paths: Vec<String>,
+    /// Bytes freed by keeping only one copy.
+    pub reclaimable_bytes: u64,
+}
+
+/// Groups of catalogued files with identical contents, largest savings
+/// first. Only files hashed by a scan with `hash_content` are considered.
+#[tauri::command]
+pub async fn find_duplicates(app: tauri::AppHandle) -> Result<Vec<DuplicateGroup>, String> {
+    with_catalog(&app, |conn| {
+        let mut stmt = conn.prepare(
+            "SELECT hash, size, path FROM media
+             WHERE hash IN (SELECT hash FROM media WHERE hash IS NOT NULL
+                            GROUP BY hash HAVING COUNT(*) > 1)
+             ORDER BY hash, path",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)? as u64,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        let mut groups: Vec<DuplicateGroup> = Vec::new();
+        for row in rows {
+            let (hash, size, path) = row?;
+            match groups.last_mut() {
+                Some(group) if group.hash == hash => {
+                    group.paths.push(path);
+                    group.reclaimable_bytes += size;
+                }
+                _ => groups.push(DuplicateGroup {
+                    hash,
+                    size,
+                    paths: vec![path],
+                    reclaimable_bytes: 0,
+                }),
+            }
+        }
+        groups.sort_by_key(|g| std::cmp::Reverse(g.reclaimable_bytes));
+        Ok(groups)
+    })
+    .await
+}
@@ -0,0 +1,295 @@
+use crate::thumbnail::decode_thumbnail;
+use image::{imageops, ImageFormat, Rgb, RgbImage};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::Emitter;
+
+static CANCEL_CONTACT_SHEET: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(false));
+
+const PADDING: u32 = 8;
+const LABEL_HEIGHT: u32 = 10;
+// 600 MB of RGB; past this, ask for fewer files or smaller thumbnails.
+const MAX_SHEET_PIXELS: u64 = 200_000_000;
+const BACKGROUND: Rgb<u8> = Rgb([24, 24, 27]);
+const PLACEHOLDER: Rgb<u8> = Rgb([63, 63, 70]);
+const LABEL_COLOR: Rgb<u8> = Rgb([228, 228, 231]);
+
+// Classic 5x7 column-major bitmap font for printable ASCII (0x20..=0x7E);
+// bit 0 of each column byte is the top row.
+const FONT_5X7: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x5F, 0x00, 0x00],
+    [0x00, 0x07, 0x00, 0x07, 0x00],
+    [0x14, 0x7F, 0x14, 0x7F, 0x14],
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12],
+    [0x23, 0x13, 0x08, 0x64, 0x62],
+    [0x36, 0x49, 0x56, 0x20, 0x50],
+    [0x00, 0x08, 0x07, 0x03, 0x00],
+    [0x00, 0x1C, 0x22, 0x41, 0x00],
+    [0x00, 0x41, 0x22, 0x1C, 0x00],
+    [0x2A, 0x1C, 0x7F, 0x1C, 0x2A],
+    [0x08, 0x08, 0x3E, 0x08, 0x08],
+    [0x00, 0x80, 0x70, 0x30, 0x00],
+    [0x08, 0x08, 0x08, 0x08, 0x08],
+    [0x00, 0x00, 0x60, 0x60, 0x00],
+    [0x20, 0x10, 0x08, 0x04, 0x02],
+    [0x3E, 0x51, 0x49, 0x45, 0x3E],
+    [0x00, 0x42, 0x7F, 0x40, 0x00],
+    [0x72, 0x49, 0x49, 0x49, 0x46],
+    [0x21, 0x41, 0x49, 0x4D, 0x33],
+    [0x18, 0x14, 0x12, 0x7F, 0x10],
+    [0x27, 0x45, 0x45, 0x45, 0x39],
+    [0x3C, 0x4A, 0x49, 0x49, 0x31],
+    [0x41, 0x21, 0x11, 0x09, 0x07],
+    [0x36, 0x49, 0x49, 0x49, 0x36],
+    [0x46, 0x49, 0x49, 0x29, 0x1E],
+    [0x00, 0x00, 0x14, 0x00, 0x00],
+    [0x00, 0x40, 0x34, 0x00, 0x00],
+    [0x00, 0x08, 0x14, 0x22, 0x41],
+    [0x14, 0x14, 0x14, 0x14, 0x14],
+    [0x00, 0x41, 0x22, 0x14, 0x08],
+    [0x02, 0x01, 0x59, 0x09, 0x06],
+    [0x3E, 0x41, 0x5D, 0x59, 0x4E],
+    [0x7C, 0x12, 0x11, 0x12, 0x7C],
+    [0x7F, 0x49, 0x49, 0x49, 0x36],
+    [0x3E, 0x41, 0x41, 0x41, 0x22],
+    [0x7F, 0x41, 0x41, 0x41, 0x3E],
+    [0x7F, 0x49, 0x49, 0x49, 0x41],
+    [0x7F, 0x09, 0x09, 0x09, 0x01],
+    [0x3E, 0x41, 0x41, 0x51, 0x73],
+    [0x7F, 0x08, 0x08, 0x08, 0x7F],
+    [0x00, 0x41, 0x7F, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3F, 0x01],
+    [0x7F, 0x08, 0x14, 0x22, 0x41],
+    [0x7F, 0x40, 0x40, 0x40, 0x40],
+    [0x7F, 0x02, 0x1C, 0x02, 0x7F],
+    [0x7F, 0x04, 0x08, 0x10, 0x7F],
+    [0x3E, 0x41, 0x41, 0x41, 0x3E],
+    [0x7F, 0x09, 0x09, 0x09, 0x06],
+    [0x3E, 0x41, 0x51, 0x21, 0x5E],
+    [0x7F, 0x09, 0x19, 0x29, 0x46],
+    [0x26, 0x49, 0x49, 0x49, 0x32],
+    [0x03, 0x01, 0x7F, 0x01, 0x03],
+    [0x3F, 0x40, 0x40, 0x40, 0x3F],
+    [0x1F, 0x20, 0x40, 0x20, 0x1F],
+    [0x3F, 0x40, 0x38, 0x40, 0x3F],
+    [0x63, 0x14, 0x08, 0x14, 0x63],
+    [0x03, 0x04, 0x78, 0x04, 0x03],
+    [0x61, 0x59, 0x49, 0x4D, 0x43],
+    [0x00, 0x7F, 0x41, 0x41, 0x41],
+    [0x02, 0x04, 0x08, 0x10, 0x20],
+    [0x00, 0x41, 0x41, 0x41, 0x7F],
+    [0x04, 0x02, 0x01, 0x02, 0x04],
+    [0x40, 0x40, 0x40, 0x40, 0x40],
+    [0x00, 0x03, 0x07, 0x08, 0x00],
+    [0x20, 0x54, 0x54, 0x78, 0x40],
+    [0x7F, 0x28, 0x44, 0x44, 0x38],
+    [0x38, 0x44, 0x44, 0x44, 0x28],
+    [0x38, 0x44, 0x44, 0x28, 0x7F],
+    [0x38, 0x54, 0x54, 0x54, 0x18],
+    [0x00, 0x08, 0x7E, 0x09, 0x02],
+    [0x18, 0xA4, 0xA4, 0x9C, 0x78],
+    [0x7F, 0x08, 0x04, 0x04, 0x78],
+    [0x00, 0x44, 0x7D, 0x40, 0x00],
+    [0x20, 0x40, 0x40, 0x3D, 0x00],
+    [0x7F, 0x10, 0x28, 0x44, 0x00],
+    [0x00, 0x41, 0x7F, 0x40, 0x00],
+    [0x7C, 0x04, 0x78, 0x04, 0x78],
+    [0x7C, 0x08, 0x04, 0x04, 0x78],
+    [0x38, 0x44, 0x44, 0x44, 0x38],
+    [0xFC, 0x18, 0x24, 0x24, 0x18],
+    [0x18, 0x24, 0x24, 0x18, 0xFC],
+    [0x7C, 0x08, 0x04, 0x04, 0x08],
+    [0x48, 0x54, 0x54, 0x54, 0x24],
+    [0x04, 0x04, 0x3F, 0x44, 0x24],
+    [0x3C, 0x40, 0x40, 0x20, 0x7C],
+    [0x1C, 0x20, 0x40, 0x20, 0x1C],
+    [0x3C, 0x40, 0x30, 0x40, 0x3C],
+    [0x44, 0x28, 0x10, 0x28, 0x44],
+    [0x4C, 0x90, 0x90, 0x90, 0x7C],
+    [0x44, 0x64, 0x54, 0x4C, 0x44],
+    [0x00, 0x08, 0x36, 0x41, 0x00],
+    [0x00, 0x00, 0x77, 0x00, 0x00],
+    [0x00, 0x41, 0x36, 0x08, 0x00],
+    [0x02, 0x01, 0x02, 0x04, 0x02],
+];
+
+#[derive(Serialize)]
+pub struct ContactSheetResult {
+    pub out_path: String,
+    pub placed: usize,
+    pub failed: Vec<String>,
+    pub cancelled: bool,
+}
+
+// Draws `text` with the built-in bitmap font, clipped to `max_width`.
+fn draw_label(canvas: &mut RgbImage, x: u32, y: u32, max_width: u32, text: &str) {
+    let max_chars = (max_width / 6) as usize;
+    for (i, ch) in text.chars().take(max_chars).enumerate() {
+        let idx = match ch {
+            ' '..='~' => ch as usize - 0x20,
+            _ => '?' as usize - 0x20,
+        };
+        for (col, bits) in FONT_5X7[idx].iter().enumerate() {
+            for row in 0..8u32 {
+                if bits & (1 << row) != 0 {
+                    let px = x + i as u32 * 6 + col as u32;
+                    let py = y + row;
+                    if px < canvas.width() && py < canvas.height() {
+                        canvas.put_pixel(px, py, LABEL_COLOR);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn sheet_format(out_path: &Path) -> Result<ImageFormat, String> {
+    match out_path
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase())
+    {
+        Some(ext) if ext == "png" => Ok(ImageFormat::Png),
+        Some(ext) if ext == "jpg" || ext == "jpeg" => Ok(ImageFormat::Jpeg),
+        _ => Err("out_path must end in .png, .jpg or .jpeg".into()),
+    }
+}
+
+fn build_sheet(
+    app: &tauri::AppHandle,
+    uris: &[String],
+    out_path: &Path,
+    format: ImageFormat,
+    cols: u32,
+    thumb_size: u32,
+    labels: bool,
+) -> Result<ContactSheetResult, String> {
+    let total = uris.len();
+    let too_large = || format!("a sheet of {} files is too large", total);
+    let rows = u32::try_from(total)
+        .map_err(|_| too_large())?
+        .div_ceil(cols)
+        .max(1);
+    let label_h = if labels { LABEL_HEIGHT } else { 0 };
+    let cell_w = thumb_size + PADDING;
+    let cell_h = thumb_size + label_h + PADDING;
+    let width = cols
+        .checked_mul(cell_w)
+        .and_then(|w| w.checked_add(PADDING))
+        .ok_or_else(too_large)?;
+    let height = rows
+        .checked_mul(cell_h)
+        .and_then(|h| h.checked_add(PADDING))
+        .ok_or_else(too_large)?;
+    if u64::from(width) * u64::from(height) > MAX_SHEET_PIXELS {
+        return Err(format!(
+            "{}; use fewer files or a smaller thumb_size",
+            too_large()
+        ));
+    }
+    let mut canvas = RgbImage::from_pixel(width, height, BACKGROUND);
+
+    let mut placed = 0usize;
+    let mut failed = Vec::new();
+    let mut cancelled = false;
+    for (i, uri) in uris.iter().enumerate() {
+        if CANCEL_CONTACT_SHEET.load(Ordering::SeqCst) {
+            cancelled = true;
+            break;
+        }
+        let x0 = PADDING + (i as u32 % cols) * cell_w;
+        let y0 = PADDING + (i as u32 / cols) * cell_h;
+        let path = Path::new(uri);
+        match decode_thumbnail(path, thumb_size) {
+            Ok(thumb) => {
+                let thumb = thumb.to_rgb8();
+                // center inside the square cell
+                let dx = (thumb_size - thumb.width()) / 2;
+                let dy = (thumb_size - thumb.height()) / 2;
+                imageops::overlay(&mut canvas, &thumb, (x0 + dx) as i64, (y0 + dy) as i64);
+                placed += 1;
+            }
+            Err(err) => {
+                for y in y0..y0 + thumb_size {
+                    for x in x0..x0 + thumb_size {
+                        canvas.put_pixel(x, y, PLACEHOLDER);
+                    }
+                }
+                failed.push(format!("{}: {}", uri, err));
+            }
+        }
+        if labels {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| uri.clone());
+            draw_label(&mut canvas, x0, y0 + thumb_size + 1, thumb_size, &name);
+        }
+        let _ = app.emit(
+            "contact_sheet_progress",
+            serde_json::json!({
+              "done": i + 1,
+              "total": total
+            }),
+        );
+    }
+
+    if !cancelled {
+        if let Some(parent) = out_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        canvas
+            .save_with_format(out_path, format)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(ContactSheetResult {
+        out_path: out_path.to_string_lossy().to_string(),
+        placed,
+        failed,
+        cancelled,
+    })
+}
+
+#[tauri::command]
+pub async fn generate_contact_sheet(
+    uris: Vec<String>,
+    out_path: String,
+    cols: Option<u32>,
+    thumb_size: Option<u32>,
+    labels: Option<bool>,
+    app: tauri::AppHandle,
+) -> Result<ContactSheetResult, String> {
+    if uris.is_empty() {
+        return Err("no uris".into());
+    }
+    if out_path.is_empty() {
+        return Err("out_path empty".into());
+    }
+    let cols = cols.unwrap_or(6).clamp(1, 64);
+    let thumb_size = thumb_size.unwrap_or(200).clamp(32, 1024);
+    let labels = labels.unwrap_or(true);
+    let format = sheet_format(Path::new(&out_path))?;
+    CANCEL_CONTACT_SHEET.store(false, Ordering::SeqCst);
+    tauri::async_runtime::spawn_blocking(move || {
+        build_sheet(
+            &app,
+            &uris,
+            Path::new(&out_path),
+            format,
+            cols,
+            thumb_size,
+            labels,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn cancel_contact_sheet() -> Result<(), String> {
+    CANCEL_CONTACT_SHEET.store(true, Ordering::SeqCst);
+    Ok(())
+}
@@ -0,0 +1,10 @@
+use std::io;
+use std::path::Path;
+
+/// blake3 of a file's contents as lowercase hex. The file is streamed, so a
+/// large video is never held in memory whole.
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(std::fs::File::open(path)?)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
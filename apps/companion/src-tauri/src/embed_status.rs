@@ -0,0 +1,137 @@
+use crate::error::TauraError;
+use crate::{http, oauth, operation, send_with_retry, SyncErrorItem};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::Emitter;
+
+const DEFAULT_POLL_SECS: u64 = 5;
+const MIN_POLL_SECS: u64 = 1;
+// a gateway that stays unreachable this many polls in a row ends the watch
+const MAX_CONSECUTIVE_FAILURES: usize = 5;
+
+/// The server's asynchronous embedding backlog for one user.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EmbedStatus {
+    /// Items still waiting to be embedded, across the server's whole queue.
+    #[serde(default)]
+    pub queue_depth: usize,
+    #[serde(default)]
+    pub embedded: Option<usize>,
+    /// Recent failures, newest first, as far back as the server keeps them.
+    #[serde(default)]
+    pub failed: Vec<SyncErrorItem>,
+}
+
+async fn fetch(
+    app: &tauri::AppHandle,
+    url: &str,
+    user_id: &str,
+    opts: &http::RequestOptions,
+) -> Result<EmbedStatus, TauraError> {
+    opts.run(async {
+        let sent = send_with_retry(app, None, |client, token| {
+            opts.apply(client.get(url))
+                .bearer_auth(token)
+                .query(&[("user_id", user_id)])
+        })
+        .await?;
+        let status = sent.resp.status();
+        if !status.is_success() {
+            return Err(TauraError::server(
+                status,
+                format!("embed status failed: {}", status),
+            ));
+        }
+        Ok(sent.resp.json::<EmbedStatus>().await?)
+    })
+    .await
+}
+
+fn target(
+    server_url: &str,
+    user_id: Option<String>,
+    app: &tauri::AppHandle,
+) -> Result<(String, String), TauraError> {
+    if server_url.trim().is_empty() {
+        return Err(TauraError::invalid_input("server_url empty"));
+    }
+    let user_id = user_id
+        .filter(|u| !u.trim().is_empty())
+        .or_else(|| oauth::active_user_id(app))
+        .ok_or_else(|| TauraError::NotAuthenticated {
+            message: "not authenticated".into(),
+        })?;
+    let url = format!("{}/embed/status", server_url.trim().trim_end_matches('/'));
+    Ok((url, user_id))
+}
+
+/// Remaining embedding work on the server for `user_id` (default: the
+/// active account).
+#[tauri::command]
+pub async fn get_embed_status(
+    server_url: String,
+    user_id: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<EmbedStatus, TauraError> {
+    let (url, user_id) = target(&server_url, user_id, &app)?;
+    let opts = http::RequestOptions::with_defaults(http::PROBE_TIMEOUT);
+    fetch(&app, &url, &user_id, &opts).await
+}
+
+/// Polls the embed status every `interval_secs` in the background, emitting
+/// `embed_status` after each poll until the queue drains. Pass `op_id` to
+/// stop it early with `cancel_operation`.
+#[tauri::command]
+pub async fn watch_embed_status(
+    server_url: String,
+    user_id: Option<String>,
+    interval_secs: Option<u64>,
+    op_id: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<(), TauraError> {
+    let (url, user_id) = target(&server_url, user_id, &app)?;
+    let interval = Duration::from_secs(
+        interval_secs
+            .unwrap_or(DEFAULT_POLL_SECS)
+            .max(MIN_POLL_SECS),
+    );
+    let op = operation::Operation::register(op_id)?;
+    let opts = http::RequestOptions::with_defaults(http::PROBE_TIMEOUT).cancellable(op.token());
+    tauri::async_runtime::spawn(async move {
+        // held for the whole watch so the op_id stays cancellable
+        let _op = op;
+        let mut failures = 0;
+        loop {
+            match fetch(&app, &url, &user_id, &opts).await {
+                Ok(status) => {
+                    failures = 0;
+                    let drained = status.queue_depth == 0;
+                    let _ = app.emit(
+                        "embed_status",
+                        serde_json::json!({ "user_id": user_id, "status": status, "done": drained }),
+                    );
+                    if drained {
+                        break;
+                    }
+                }
+                Err(TauraError::Cancelled { .. }) => break,
+                Err(err) => {
+                    failures += 1;
+                    log::warn!("embed status poll {} failed: {}", failures, err);
+                    if failures >= MAX_CONSECUTIVE_FAILURES {
+                        let _ = app.emit(
+                            "embed_status",
+                            serde_json::json!({ "user_id": user_id, "error": err, "done": true }),
+                        );
+                        break;
+                    }
+                }
+            }
+            tokio::select! {
+                _ = opts.cancel_token().cancelled() => break,
+                _ = tokio::time::sleep(interval) => {}
+            }
+        }
+    });
+    Ok(())
+}
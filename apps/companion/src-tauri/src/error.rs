@@ -0,0 +1,154 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Error returned by commands the UI needs to tell apart. Serialized as
+///
+/// ```json
+/// { "kind": "server", "message": "sync failed: 502 Bad Gateway", "status": 502 }
+/// ```
+///
+/// `kind` is one of `not_authenticated`, `network`, `server`, `invalid_input`,
+/// `cancelled`, `io` or `other`; `status` is only present for `server`.
+/// `message` is for logs and fallback display, not for matching.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TauraError {
+    /// No session, or its grant is gone; signing in again fixes it.
+    NotAuthenticated {
+        message: String,
+    },
+    /// The request never got a response (DNS, connect, TLS, timeout).
+    Network {
+        message: String,
+    },
+    /// The server answered with a non-success status.
+    Server {
+        status: u16,
+        message: String,
+    },
+    InvalidInput {
+        message: String,
+    },
+    Cancelled {
+        message: String,
+    },
+    Io {
+        message: String,
+    },
+    Other {
+        message: String,
+    },
+}
+
+impl TauraError {
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::InvalidInput {
+            message: message.into(),
+        }
+    }
+
+    pub fn server(status: reqwest::StatusCode, message: impl Into<String>) -> Self {
+        Self::Server {
+            status: status.as_u16(),
+            message: message.into(),
+        }
+    }
+
+    pub fn network(message: impl Into<String>) -> Self {
+        Self::Network {
+            message: message.into(),
+        }
+    }
+
+    pub fn cancelled(message: impl Into<String>) -> Self {
+        Self::Cancelled {
+            message: message.into(),
+        }
+    }
+
+    pub fn not_authenticated(message: impl Into<String>) -> Self {
+        Self::NotAuthenticated {
+            message: message.into(),
+        }
+    }
+
+    /// Whether the same request may go through later: no response at all,
+    /// or a 429 or 5xx.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Network { .. } => true,
+            Self::Server { status, .. } => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::NotAuthenticated { message }
+            | Self::Network { message }
+            | Self::Server { message, .. }
+            | Self::InvalidInput { message }
+            | Self::Cancelled { message }
+            | Self::Io { message }
+            | Self::Other { message } => message,
+        }
+    }
+
+    /// Same kind, with `context` prepended to the message.
+    pub fn context(mut self, context: &str) -> Self {
+        match &mut self {
+            Self::NotAuthenticated { message }
+            | Self::Network { message }
+            | Self::Server { message, .. }
+            | Self::InvalidInput { message }
+            | Self::Cancelled { message }
+            | Self::Io { message }
+            | Self::Other { message } => *message = format!("{}: {}", context, message),
+        }
+        self
+    }
+}
+
+impl fmt::Display for TauraError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for TauraError {}
+
+impl From<String> for TauraError {
+    fn from(message: String) -> Self {
+        Self::Other { message }
+    }
+}
+
+impl From<std::io::Error> for TauraError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io {
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<reqwest::Error> for TauraError {
+    fn from(err: reqwest::Error) -> Self {
+        match err.status() {
+            Some(status) => Self::server(status, err.to_string()),
+            // no response, or it broke off before the body was read
+            None if err.is_connect() || err.is_timeout() || err.is_request() || err.is_body() => {
+                Self::network(err.to_string())
+            }
+            None => Self::Other {
+                message: err.to_string(),
+            },
+        }
+    }
+}
+
+// Commands that still report plain strings can `?` these.
+impl From<TauraError> for String {
+    fn from(err: TauraError) -> Self {
+        err.to_string()
+    }
+}
@@ -0,0 +1,267 @@
+use crate::fs_access::canonical_dir;
+use crate::settings::{self, Settings};
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const IGNORE_FILE: &str = ".tauraignore";
+
+/// Compiles exclusion globs; an invalid one is reported rather than skipped
+/// so a typo doesn't silently index what it was meant to hide.
+pub fn compile(globs: &[String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(Glob::new(glob).map_err(|e| format!("invalid glob \"{}\": {}", glob, e))?);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// Whether `path` (somewhere under `root`) matches, by its relative path or
+/// its bare name, so `node_modules` and `*.tmp` work at any depth.
+pub fn is_excluded(set: &GlobSet, root: &Path, path: &Path) -> bool {
+    if set.is_empty() {
+        return false;
+    }
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    set.is_match(relative) || path.file_name().is_some_and(|name| set.is_match(name))
+}
+
+/// The saved exclusions, compiled: `excluded_globs` for every scan and
+/// `folder_exclusions` for anything under their folder.
+pub struct Rules {
+    global: GlobSet,
+    folders: Vec<(PathBuf, GlobSet)>,
+}
+
+pub fn rules(config: &Settings) -> Result<Rules, String> {
+    let folders = config
+        .folder_exclusions
+        .iter()
+        // keys saved before they were canonicalized may still be relative
+        .map(|(folder, globs)| Ok((folder_path(folder), compile(globs)?)))
+        .collect::<Result<_, String>>()?;
+    Ok(Rules {
+        global: compile(&config.excluded_globs)?,
+        folders,
+    })
+}
+
+impl Rules {
+    /// Exclusions for one walk from `root`.
+    pub fn walk(&self, root: &Path) -> Exclusions {
+        let canonical_root = std::fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        Exclusions {
+            global: self.global.clone(),
+            // rules for folders beside the walk can never match
+            folders: self
+                .folders
+                .iter()
+                .filter(|(folder, _)| {
+                    folder.starts_with(&canonical_root) || canonical_root.starts_with(folder)
+                })
+                .cloned()
+                .collect(),
+            root: root.to_path_buf(),
+            canonical_root,
+            ignore_files: HashMap::new(),
+        }
+    }
+}
+
+// Patterns from one `.tauraignore`, matched against paths relative to the
+// folder holding it.
+struct IgnoreFile {
+    any: GlobSet,
+    dirs_only: GlobSet,
+}
+
+// A gitignore subset: `#` comments, `*`/`**`/`?`/`[...]`, a leading or inner
+// `/` anchoring to the file's folder and a trailing `/` for folders only.
+// Lines that can't be honoured are logged and skipped; a stray line in a file
+// on disk shouldn't fail the whole scan.
+fn parse_ignore_file(path: &Path, text: &str) -> IgnoreFile {
+    let mut any = GlobSetBuilder::new();
+    let mut dirs_only = GlobSetBuilder::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('!') {
+            log::warn!(
+                "{}: negated pattern {:?} is not supported",
+                path.display(),
+                line
+            );
+            continue;
+        }
+        let (pattern, dir_only) = match line.strip_suffix('/') {
+            Some(pattern) => (pattern, true),
+            None => (line, false),
+        };
+        let glob = if pattern.contains('/') {
+            pattern.trim_start_matches('/').to_string()
+        } else {
+            format!("**/{}", pattern)
+        };
+        match GlobBuilder::new(&glob).literal_separator(true).build() {
+            Ok(glob) if dir_only => {
+                dirs_only.add(glob);
+            }
+            Ok(glob) => {
+                any.add(glob);
+            }
+            Err(err) => log::warn!("{}: invalid pattern {:?}: {}", path.display(), line, err),
+        }
+    }
+    IgnoreFile {
+        any: any.build().unwrap_or_else(|_| GlobSet::empty()),
+        dirs_only: dirs_only.build().unwrap_or_else(|_| GlobSet::empty()),
+    }
+}
+
+fn load_ignore_file(dir: &Path) -> Option<IgnoreFile> {
+    let path = dir.join(IGNORE_FILE);
+    let text = std::fs::read_to_string(&path).ok()?;
+    Some(parse_ignore_file(&path, &text))
+}
+
+/// Decides what one walk prunes: the saved rules plus any `.tauraignore`
+/// found in the walked folders, which applies to everything below it.
+pub struct Exclusions {
+    global: GlobSet,
+    folders: Vec<(PathBuf, GlobSet)>,
+    root: PathBuf,
+    // folder rules are keyed by canonical path; entries are matched as if
+    // the walk had started here
+    canonical_root: PathBuf,
+    // per folder, read the first time an entry below it comes up
+    ignore_files: HashMap<PathBuf, Option<IgnoreFile>>,
+}
+
+impl Exclusions {
+    pub fn excludes(&mut self, entry: &walkdir::DirEntry) -> bool {
+        let path = entry.path();
+        let canonical = match path.strip_prefix(&self.root) {
+            Ok(relative) => self.canonical_root.join(relative),
+            Err(_) => path.to_path_buf(),
+        };
+        if is_excluded(&self.global, &self.root, path)
+            || self.folders.iter().any(|(folder, set)| {
+                canonical.starts_with(folder) && is_excluded(set, folder, &canonical)
+            })
+        {
+            return true;
+        }
+        let is_dir = entry.file_type().is_dir();
+        for dir in path.ancestors().skip(1) {
+            if !dir.starts_with(&self.root) {
+                break;
+            }
+            let ignore = self
+                .ignore_files
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| load_ignore_file(dir));
+            let Some(ignore) = ignore else {
+                continue;
+            };
+            let relative = path.strip_prefix(dir).unwrap_or(path);
+            if ignore.any.is_match(relative) || (is_dir && ignore.dirs_only.is_match(relative)) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+// Folders are keyed by their canonical path, so `~/Photos/` and a link to it
+// share one set of rules.
+fn folder_key(path: &str) -> String {
+    folder_path(path).to_string_lossy().into_owned()
+}
+
+fn folder_path(path: &str) -> PathBuf {
+    canonical_dir(path).unwrap_or_else(|_| PathBuf::from(path.trim()))
+}
+
+/// Exclusion globs for scans of `path` and anything inside it, or the ones
+/// for every scan when `path` is None.
+#[tauri::command]
+pub async fn get_exclusions(
+    path: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<Vec<String>, String> {
+    let mut config = settings::load(&app);
+    Ok(match path {
+        Some(path) => config
+            .folder_exclusions
+            .remove(&folder_key(&path))
+            .or_else(|| config.folder_exclusions.remove(path.trim()))
+            .unwrap_or_default(),
+        None => config.excluded_globs,
+    })
+}
+
+/// Replaces the exclusion globs for `path` (e.g. `node_modules`, `.cache`,
+/// `Screenshots/**`), or the ones for every scan when `path` is None. Globs
+/// match the path relative to that folder or the bare name. An empty list
+/// drops the folder's rules. `.tauraignore` files in scanned folders are
+/// honoured on top of these.
+#[tauri::command]
+pub async fn set_exclusions(
+    path: Option<String>,
+    globs: Vec<String>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    if path.as_deref().is_some_and(|p| p.trim().is_empty()) {
+        return Err("path empty".into());
+    }
+    compile(&globs)?;
+    let key = match path.as_deref() {
+        // a folder that is gone can still have its rules dropped
+        Some(path) if globs.is_empty() => Some(folder_key(path)),
+        Some(path) => Some(canonical_dir(path)?.to_string_lossy().into_owned()),
+        None => None,
+    };
+    settings::update(&app, |s| match key {
+        Some(key) if globs.is_empty() => {
+            s.folder_exclusions.remove(&key);
+            if let Some(path) = path.as_deref() {
+                s.folder_exclusions.remove(path.trim());
+            }
+        }
+        Some(key) => {
+            s.folder_exclusions.insert(key, globs);
+        }
+        None => s.excluded_globs = globs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folder_rules_match_a_non_canonical_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let photos = dir.path().join("photos");
+        std::fs::create_dir_all(photos.join("cache")).unwrap();
+        std::fs::write(photos.join("cache").join("a.jpg"), b"").unwrap();
+        std::fs::create_dir(dir.path().join("other")).unwrap();
+
+        let roundabout = dir.path().join("other").join("..").join("photos");
+        let key = folder_key(&format!("{}/", roundabout.display()));
+        assert_eq!(key, folder_key(&photos.to_string_lossy()));
+
+        let mut config = Settings::default();
+        config.folder_exclusions.insert(key, vec!["cache".into()]);
+        let rules = rules(&config).unwrap();
+        let mut exclusions = rules.walk(&roundabout);
+        let excluded: Vec<_> = walkdir::WalkDir::new(&roundabout)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e| exclusions.excludes(e))
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(excluded, ["cache"]);
+    }
+}
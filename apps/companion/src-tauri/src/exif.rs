@@ -0,0 +1,458 @@
+use crate::{heic, raw};
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Utc};
+use serde::Serialize;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+const TAG_MAKE: u16 = 0x010F;
+const TAG_MODEL: u16 = 0x0110;
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_DATETIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_GPS_IFD: u16 = 0x8825;
+const TAG_DATETIME_ORIGINAL: u16 = 0x9003;
+const TAG_DATETIME_DIGITIZED: u16 = 0x9004;
+const TAG_OFFSET_TIME_ORIGINAL: u16 = 0x9011;
+const TAG_GPS_LATITUDE_REF: u16 = 0x0001;
+const TAG_GPS_LATITUDE: u16 = 0x0002;
+const TAG_GPS_LONGITUDE_REF: u16 = 0x0003;
+const TAG_GPS_LONGITUDE: u16 = 0x0004;
+const TYPE_ASCII: u16 = 2;
+const TYPE_SHORT: u16 = 3;
+const TYPE_RATIONAL: u16 = 5;
+const EXIF_DATETIME_FMT: &str = "%Y:%m:%d %H:%M:%S";
+const EXIF_DATETIME_LEN: usize = 19;
+// JPEG keeps Exif in an early APP1 segment and TIFF writers put IFD0 near the
+// start, so the tag can be read without loading the whole file.
+const HEADER_READ_LIMIT: u64 = 256 * 1024;
+
+/// What the index keeps from a photo's EXIF block.
+#[derive(Debug, Default, Clone)]
+pub struct ExifMeta {
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    /// Capture time as RFC 3339. Cameras that don't record their UTC offset
+    /// get their local clock reading taken as UTC.
+    pub timestamp: Option<String>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub orientation: Option<u16>,
+}
+
+/// Reader over a TIFF structure embedded somewhere inside a larger buffer.
+pub struct Tiff<'a> {
+    buf: &'a [u8],
+    base: usize,
+    little_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    pub fn new(buf: &'a [u8], base: usize) -> Option<Self> {
+        let header = buf.get(base..base + 4)?;
+        let little_endian = match header {
+            [b'I', b'I', 42, 0] => true,
+            [b'M', b'M', 0, 42] => false,
+            _ => return None,
+        };
+        Some(Self {
+            buf,
+            base,
+            little_endian,
+        })
+    }
+
+    pub fn u16_at(&self, off: usize) -> Option<u16> {
+        let b = self.buf.get(self.base + off..self.base + off + 2)?;
+        Some(if self.little_endian {
+            u16::from_le_bytes([b[0], b[1]])
+        } else {
+            u16::from_be_bytes([b[0], b[1]])
+        })
+    }
+
+    pub fn u32_at(&self, off: usize) -> Option<u32> {
+        let b = self.buf.get(self.base + off..self.base + off + 4)?;
+        Some(if self.little_endian {
+            u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+        } else {
+            u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+        })
+    }
+
+    /// Entries of the IFD at `off` as (tag, type, count, value_or_offset field position).
+    pub fn entries(&self, off: usize) -> Vec<(u16, u16, u32, usize)> {
+        let mut out = Vec::new();
+        let Some(n) = self.u16_at(off) else {
+            return out;
+        };
+        for i in 0..n as usize {
+            let e = off + 2 + i * 12;
+            match (self.u16_at(e), self.u16_at(e + 2), self.u32_at(e + 4)) {
+                (Some(tag), Some(typ), Some(count)) => out.push((tag, typ, count, e + 8)),
+                _ => break,
+            }
+        }
+        out
+    }
+
+    // Values of up to four bytes sit in the entry itself, longer ones at an offset.
+    fn ascii(&self, count: u32, value_pos: usize) -> Option<String> {
+        let count = count as usize;
+        let start = if count <= 4 {
+            self.base + value_pos
+        } else {
+            self.base + self.u32_at(value_pos)? as usize
+        };
+        let bytes = self.buf.get(start..start + count)?;
+        let text = String::from_utf8_lossy(bytes);
+        let text = text.trim_end_matches('\0').trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+
+    // Degrees, minutes and seconds as three RATIONALs, folded into degrees.
+    fn gps_coordinate(&self, typ: u16, count: u32, value_pos: usize) -> Option<f64> {
+        if typ != TYPE_RATIONAL || count != 3 {
+            return None;
+        }
+        let at = self.u32_at(value_pos)? as usize;
+        let mut parts = [0f64; 3];
+        for (i, part) in parts.iter_mut().enumerate() {
+            let num = self.u32_at(at + i * 8)?;
+            let den = self.u32_at(at + i * 8 + 4)?;
+            // 0/0 is how some cameras write "no fix"
+            if den == 0 {
+                return None;
+            }
+            *part = num as f64 / den as f64;
+        }
+        Some(parts[0] + parts[1] / 60.0 + parts[2] / 3600.0)
+    }
+
+    fn gps(&self, ifd: usize) -> Option<(f64, f64)> {
+        let (mut lat, mut lon, mut lat_ref, mut lon_ref) = (None, None, None, None);
+        for (tag, typ, count, value_pos) in self.entries(ifd) {
+            match tag {
+                TAG_GPS_LATITUDE_REF if typ == TYPE_ASCII => lat_ref = self.ascii(count, value_pos),
+                TAG_GPS_LONGITUDE_REF if typ == TYPE_ASCII => {
+                    lon_ref = self.ascii(count, value_pos)
+                }
+                TAG_GPS_LATITUDE => lat = self.gps_coordinate(typ, count, value_pos),
+                TAG_GPS_LONGITUDE => lon = self.gps_coordinate(typ, count, value_pos),
+                _ => {}
+            }
+        }
+        let lat = if lat_ref.as_deref() == Some("S") {
+            -lat?
+        } else {
+            lat?
+        };
+        let lon = if lon_ref.as_deref() == Some("W") {
+            -lon?
+        } else {
+            lon?
+        };
+        ((-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)).then_some((lat, lon))
+    }
+
+    /// GPS position, capture time, camera and orientation from IFD0 and the
+    /// Exif and GPS IFDs it points to.
+    fn meta(&self) -> ExifMeta {
+        let mut meta = ExifMeta::default();
+        let Some(ifd0) = self.u32_at(4) else {
+            return meta;
+        };
+        let (mut exif_ifd, mut gps_ifd, mut modified) = (None, None, None);
+        for (tag, typ, count, value_pos) in self.entries(ifd0 as usize) {
+            match tag {
+                TAG_MAKE if typ == TYPE_ASCII => meta.make = self.ascii(count, value_pos),
+                TAG_MODEL if typ == TYPE_ASCII => meta.model = self.ascii(count, value_pos),
+                TAG_DATETIME if typ == TYPE_ASCII => modified = self.ascii(count, value_pos),
+                TAG_EXIF_IFD => exif_ifd = self.u32_at(value_pos),
+                TAG_GPS_IFD => gps_ifd = self.u32_at(value_pos),
+                _ => {}
+            }
+        }
+        meta.orientation = self.orientation();
+        let (mut original, mut digitized, mut offset) = (None, None, None);
+        for (tag, typ, count, value_pos) in
+            exif_ifd.map_or_else(Vec::new, |ifd| self.entries(ifd as usize))
+        {
+            match tag {
+                TAG_DATETIME_ORIGINAL if typ == TYPE_ASCII => {
+                    original = self.ascii(count, value_pos)
+                }
+                TAG_DATETIME_DIGITIZED if typ == TYPE_ASCII => {
+                    digitized = self.ascii(count, value_pos)
+                }
+                TAG_OFFSET_TIME_ORIGINAL if typ == TYPE_ASCII => {
+                    offset = self.ascii(count, value_pos)
+                }
+                _ => {}
+            }
+        }
+        // IFD0's DateTime is when the file was last edited, so it comes last
+        meta.timestamp = [original, digitized, modified]
+            .into_iter()
+            .flatten()
+            .find_map(|raw| capture_time(&raw, offset.as_deref()));
+        if let Some((lat, lon)) = gps_ifd.and_then(|ifd| self.gps(ifd as usize)) {
+            meta.lat = Some(lat);
+            meta.lon = Some(lon);
+        }
+        meta
+    }
+
+    /// Orientation from IFD0; SHORT values sit in the first two bytes of the field.
+    fn orientation(&self) -> Option<u16> {
+        let ifd0 = self.u32_at(4)? as usize;
+        self.entries(ifd0)
+            .into_iter()
+            .find(|(tag, typ, _, _)| *tag == TAG_ORIENTATION && *typ == TYPE_SHORT)
+            .and_then(|(_, _, _, value_pos)| self.u16_at(value_pos))
+            .filter(|o| (1..=8).contains(o))
+    }
+
+    /// Absolute buffer offsets of every datetime string in IFD0 and the Exif IFD.
+    fn datetime_offsets(&self) -> Vec<(u16, usize)> {
+        let mut found = Vec::new();
+        let Some(ifd0) = self.u32_at(4) else {
+            return found;
+        };
+        let mut ifds = vec![ifd0 as usize];
+        let mut visited = Vec::new();
+        while let Some(ifd) = ifds.pop() {
+            if visited.contains(&ifd) {
+                continue;
+            }
+            visited.push(ifd);
+            for (tag, typ, count, value_pos) in self.entries(ifd) {
+                match tag {
+                    TAG_EXIF_IFD => {
+                        if let Some(ptr) = self.u32_at(value_pos) {
+                            ifds.push(ptr as usize);
+                        }
+                    }
+                    TAG_DATETIME | TAG_DATETIME_ORIGINAL | TAG_DATETIME_DIGITIZED
+                        if typ == 2 && count as usize >= EXIF_DATETIME_LEN =>
+                    {
+                        if let Some(ptr) = self.u32_at(value_pos) {
+                            let abs = self.base + ptr as usize;
+                            if abs + EXIF_DATETIME_LEN <= self.buf.len() {
+                                found.push((tag, abs));
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        found
+    }
+}
+
+fn capture_time(raw: &str, offset: Option<&str>) -> Option<String> {
+    let local =
+        NaiveDateTime::parse_from_str(raw.get(..EXIF_DATETIME_LEN)?, EXIF_DATETIME_FMT).ok()?;
+    let offset = offset.and_then(|o| o.parse::<FixedOffset>().ok());
+    Some(
+        match offset.and_then(|o| local.and_local_timezone(o).single()) {
+            Some(dt) => dt.to_rfc3339(),
+            None => DateTime::<Utc>::from_naive_utc_and_offset(local, Utc).to_rfc3339(),
+        },
+    )
+}
+
+/// Offset of the TIFF header inside a JPEG's Exif APP1 segment.
+fn jpeg_tiff_base(buf: &[u8]) -> Option<usize> {
+    if buf.get(0..2)? != [0xFF, 0xD8] {
+        return None;
+    }
+    let mut pos = 2;
+    while pos + 4 <= buf.len() {
+        if buf[pos] != 0xFF {
+            return None;
+        }
+        let marker = buf[pos + 1];
+        // start of scan: no more metadata segments follow
+        if marker == 0xDA {
+            return None;
+        }
+        let len = u16::from_be_bytes([buf[pos + 2], buf[pos + 3]]) as usize;
+        let payload = pos + 4;
+        if marker == 0xE1 && buf.get(payload..payload + 6) == Some(b"Exif\0\0") {
+            return Some(payload + 6);
+        }
+        pos += 2 + len;
+    }
+    None
+}
+
+fn tiff_for(buf: &[u8]) -> Option<Tiff<'_>> {
+    // RAF keeps its Exif in the embedded JPEG; other RAW formats are TIFFs
+    if let Some((jpeg, _)) = raw::raf_jpeg(buf) {
+        let base = jpeg + jpeg_tiff_base(buf.get(jpeg..)?)?;
+        return Tiff::new(buf, base);
+    }
+    match jpeg_tiff_base(buf) {
+        Some(base) => Tiff::new(buf, base),
+        None => Tiff::new(buf, 0),
+    }
+}
+
+/// Orientation of an image already in memory.
+pub fn orientation_in(buf: &[u8]) -> Option<u16> {
+    tiff_for(buf)?.orientation()
+}
+
+/// EXIF metadata of a JPEG, TIFF, RAW or HEIC image; empty when it has none.
+pub fn read_meta(path: &Path) -> ExifMeta {
+    if heic::is_heif(path) {
+        let exif = fs::read(path).ok().and_then(|buf| heic::exif(&buf));
+        return exif
+            .as_deref()
+            .and_then(|exif| Tiff::new(exif, 0))
+            .map(|tiff| tiff.meta())
+            .unwrap_or_default();
+    }
+    let mut buf = Vec::new();
+    let read = fs::File::open(path).and_then(|f| f.take(HEADER_READ_LIMIT).read_to_end(&mut buf));
+    if read.is_err() {
+        return ExifMeta::default();
+    }
+    tiff_for(&buf).map(|tiff| tiff.meta()).unwrap_or_default()
+}
+
+/// Orientations 5-8 rotate by a quarter turn, so width and height swap.
+pub fn swaps_dimensions(orientation: Option<u16>) -> bool {
+    matches!(orientation, Some(5..=8))
+}
+
+#[derive(Serialize)]
+pub struct TimestampShiftResult {
+    pub uri: String,
+    pub ok: bool,
+    pub original: Option<String>,
+    pub shifted: Option<String>,
+    pub backup: Option<String>,
+    pub error: Option<String>,
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+/// Rewrites the datetime tags in place. The EXIF datetime format is fixed
+/// width, so no offsets move and any XMP or maker-note blocks stay intact.
+fn shift_file(
+    path: &Path,
+    offset_secs: i64,
+    adjust_mtime: bool,
+) -> Result<(String, String, String), String> {
+    let mut buf = fs::read(path).map_err(|e| e.to_string())?;
+    let offsets = tiff_for(&buf)
+        .map(|t| t.datetime_offsets())
+        .unwrap_or_default();
+    if !offsets.iter().any(|(tag, _)| *tag == TAG_DATETIME_ORIGINAL) {
+        return Err("no EXIF DateTimeOriginal".into());
+    }
+    let offset = chrono::Duration::try_seconds(offset_secs)
+        .ok_or_else(|| format!("offset {}s out of range", offset_secs))?;
+    let mut original = String::new();
+    let mut shifted = String::new();
+    for (tag, abs) in offsets {
+        let raw = String::from_utf8_lossy(&buf[abs..abs + EXIF_DATETIME_LEN]).to_string();
+        let Ok(parsed) = NaiveDateTime::parse_from_str(&raw, EXIF_DATETIME_FMT) else {
+            // zeroed or blank dates are common; leave them alone
+            continue;
+        };
+        let updated = parsed
+            .checked_add_signed(offset)
+            .ok_or_else(|| format!("shifting {} by {}s is out of range", raw, offset_secs))?
+            .format(EXIF_DATETIME_FMT)
+            .to_string();
+        if updated.len() != EXIF_DATETIME_LEN {
+            return Err(format!("shifted date {} out of range", updated));
+        }
+        buf[abs..abs + EXIF_DATETIME_LEN].copy_from_slice(updated.as_bytes());
+        if tag == TAG_DATETIME_ORIGINAL {
+            original = raw;
+            shifted = updated;
+        }
+    }
+    if shifted.is_empty() {
+        return Err("EXIF DateTimeOriginal unreadable".into());
+    }
+
+    let previous_mtime = fs::metadata(path).and_then(|m| m.modified()).ok();
+    // worked out before anything is written, so an overflow leaves the file as is
+    let new_mtime = match previous_mtime {
+        Some(t) if adjust_mtime => {
+            let delta = std::time::Duration::from_secs(offset_secs.unsigned_abs());
+            let moved = if offset_secs >= 0 {
+                t.checked_add(delta)
+            } else {
+                t.checked_sub(delta)
+            };
+            Some(moved.ok_or("shifted modified time out of range")?)
+        }
+        other => other,
+    };
+
+    let backup = backup_path(path);
+    if !backup.exists() {
+        fs::copy(path, &backup).map_err(|e| format!("backup failed: {}", e))?;
+    }
+    // a fresh name beside the original, so the rename stays on one volume
+    // and two shifts can't write the same temp file
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty());
+    let mut tmp = tempfile::NamedTempFile::new_in(parent.unwrap_or(Path::new(".")))
+        .map_err(|e| e.to_string())?;
+    tmp.write_all(&buf).map_err(|e| e.to_string())?;
+    if let Some(t) = new_mtime {
+        tmp.as_file().set_modified(t).map_err(|e| e.to_string())?;
+    }
+    let permissions = fs::metadata(path).map_err(|e| e.to_string())?.permissions();
+    tmp.as_file()
+        .set_permissions(permissions)
+        .map_err(|e| e.to_string())?;
+    tmp.persist(path).map_err(|e| e.error.to_string())?;
+    Ok((original, shifted, backup.to_string_lossy().to_string()))
+}
+
+#[tauri::command]
+pub async fn shift_timestamps(
+    uris: Vec<String>,
+    offset_secs: i64,
+    adjust_mtime: Option<bool>,
+) -> Result<Vec<TimestampShiftResult>, String> {
+    let adjust_mtime = adjust_mtime.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || {
+        uris.into_iter()
+            .map(
+                |uri| match shift_file(Path::new(&uri), offset_secs, adjust_mtime) {
+                    Ok((original, shifted, backup)) => TimestampShiftResult {
+                        uri,
+                        ok: true,
+                        original: Some(original),
+                        shifted: Some(shifted),
+                        backup: Some(backup),
+                        error: None,
+                    },
+                    Err(error) => TimestampShiftResult {
+                        uri,
+                        ok: false,
+                        original: None,
+                        shifted: None,
+                        backup: None,
+                        error: Some(error),
+                    },
+                },
+            )
+            .collect()
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
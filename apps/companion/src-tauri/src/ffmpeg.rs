@@ -0,0 +1,149 @@
+use crate::mp4;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageFormat};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The ffmpeg binary: `TAURA_FFMPEG` when set, otherwise whatever `ffmpeg`
+/// resolves to on PATH.
+fn binary() -> PathBuf {
+    std::env::var_os("TAURA_FFMPEG")
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("ffmpeg"))
+}
+
+// An ffmpeg run that doesn't flash a console window on Windows.
+fn command() -> Command {
+    #[allow(unused_mut)]
+    let mut cmd = Command::new(binary());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+    cmd
+}
+
+/// Decodes the keyframe at or just before `at_secs`, scaled so its longest
+/// edge fits `max_edge`. ffmpeg applies the rotation tag, so the frame comes
+/// back upright.
+pub fn keyframe(path: &Path, at_secs: f64, max_edge: u32) -> Result<DynamicImage, String> {
+    let scale = format!(
+        "scale=w={e}:h={e}:force_original_aspect_ratio=decrease",
+        e = max_edge
+    );
+    let out = command()
+        .args(["-v", "error", "-nostdin", "-skip_frame", "nokey"])
+        // seeking before the input jumps straight to the nearest keyframe
+        .args(["-ss", &format!("{:.3}", at_secs.max(0.0))])
+        .arg("-i")
+        .arg(path)
+        .args(["-frames:v", "1", "-vf", &scale])
+        .args(["-f", "image2pipe", "-c:v", "png", "-"])
+        .output()
+        .map_err(|e| format!("could not run ffmpeg: {}", e))?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        return Err(format!("ffmpeg failed: {}", stderr.trim()));
+    }
+    if out.stdout.is_empty() {
+        return Err("ffmpeg found no frame".into());
+    }
+    image::load_from_memory_with_format(&out.stdout, ImageFormat::Png).map_err(|e| e.to_string())
+}
+
+const DEFAULT_FRAME_INTERVAL_SECS: f64 = 10.0;
+const DEFAULT_MAX_FRAMES: usize = 8;
+const MAX_FRAMES: usize = 32;
+const DEFAULT_FRAME_EDGE: u32 = 512;
+const FRAME_QUALITY: u8 = 80;
+
+/// How `sync_index` samples videos for the server to embed. Every field is
+/// optional.
+#[derive(Deserialize, Serialize, Clone, Copy, Default)]
+pub struct FrameSampling {
+    /// Seconds between frames; default 10.
+    pub every_secs: Option<f64>,
+    /// Cap per video, default 8 and at most 32. Long videos are sampled
+    /// evenly instead of every `every_secs`.
+    pub max_frames: Option<usize>,
+    /// Longest edge of each frame; default 512.
+    pub max_edge: Option<u32>,
+}
+
+// Interval midpoints, so a fade-in at the start or the credits at the end
+// are not what represents the video.
+fn sample_times(duration: f64, every: f64, max_frames: usize) -> Vec<f64> {
+    let wanted = ((duration / every).floor() as usize).clamp(1, max_frames);
+    let step = duration / wanted as f64;
+    (0..wanted).map(|i| (i as f64 + 0.5) * step).collect()
+}
+
+/// Duration as ffmpeg reads it from the container header, for formats
+/// mp4.rs doesn't parse.
+pub fn duration(path: &Path) -> Option<f64> {
+    // with no output file ffmpeg exits with an error after printing the header
+    let out = command()
+        .args(["-hide_banner", "-nostdin", "-i"])
+        .arg(path)
+        .output()
+        .ok()?;
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    let rest = stderr.split("Duration: ").nth(1)?;
+    let stamp = rest.split(',').next()?.trim();
+    let mut secs = 0.0;
+    for part in stamp.split(':') {
+        secs = secs * 60.0 + part.parse::<f64>().ok()?;
+    }
+    (secs > 0.0).then_some(secs)
+}
+
+/// Representative frames of a video as JPEG bytes, in playback order.
+/// Frames that land on the same keyframe are kept once.
+pub fn sample_frames(path: &Path, sampling: &FrameSampling) -> Result<Vec<Vec<u8>>, String> {
+    let every = sampling
+        .every_secs
+        .filter(|s| *s > 0.0)
+        .unwrap_or(DEFAULT_FRAME_INTERVAL_SECS);
+    let max_frames = sampling
+        .max_frames
+        .unwrap_or(DEFAULT_MAX_FRAMES)
+        .clamp(1, MAX_FRAMES);
+    let max_edge = sampling
+        .max_edge
+        .unwrap_or(DEFAULT_FRAME_EDGE)
+        .clamp(16, 2048);
+    let duration = mp4::read_video_meta(path)
+        .and_then(|m| m.duration_secs)
+        .or_else(|| duration(path));
+    let times = match duration {
+        Some(d) => sample_times(d, every, max_frames),
+        None => vec![0.0],
+    };
+    let mut frames: Vec<Vec<u8>> = Vec::new();
+    let mut last_error = None;
+    for at in times {
+        let frame = match keyframe(path, at, max_edge) {
+            Ok(frame) => frame,
+            Err(err) => {
+                last_error = Some(err);
+                continue;
+            }
+        };
+        let mut jpeg = Vec::new();
+        frame
+            .into_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, FRAME_QUALITY))
+            .map_err(|e| e.to_string())?;
+        if frames.last() != Some(&jpeg) {
+            frames.push(jpeg);
+        }
+    }
+    if frames.is_empty() {
+        return Err(last_error.unwrap_or_else(|| "no frames sampled".into()));
+    }
+    Ok(frames)
+}
@@ -0,0 +1,83 @@
+use crate::settings;
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use tauri_plugin_fs::FsExt;
+
+// Folders revoked since launch. The fs scope can forbid a path but never
+// drop an allow, so these stay forbidden until the next launch.
+static REVOKED: Lazy<std::sync::Mutex<HashSet<PathBuf>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashSet::new()));
+
+pub fn canonical_dir(path: &str) -> Result<PathBuf, String> {
+    let dir = std::fs::canonicalize(path.trim()).map_err(|e| format!("{}: {}", path, e))?;
+    if !dir.is_dir() {
+        return Err(format!("{} is not a folder", path));
+    }
+    Ok(dir)
+}
+
+/// Re-applies the persisted grants; called once at startup.
+pub fn restore(app: &tauri::AppHandle) {
+    let scope = app.fs_scope();
+    for folder in settings::load(app).fs_grants {
+        if let Err(err) = scope.allow_directory(&folder, true) {
+            log::warn!("could not restore access to {}: {}", folder, err);
+        }
+    }
+}
+
+/// Lets the webview read `path` and everything under it through the fs
+/// plugin, now and on later launches. Returns the granted folders.
+#[tauri::command]
+pub async fn grant_folder_access(
+    path: String,
+    app: tauri::AppHandle,
+) -> Result<Vec<String>, String> {
+    let dir = canonical_dir(&path)?;
+    let key = dir.to_string_lossy().to_string();
+    let mut granted = Vec::new();
+    settings::update(&app, |s| {
+        if !s.fs_grants.contains(&key) {
+            s.fs_grants.push(key.clone());
+        }
+        granted = s.fs_grants.clone();
+    })?;
+    if REVOKED.lock().map_err(|_| "lock poisoned")?.contains(&dir) {
+        return Err(format!(
+            "access to {} was revoked since launch; the grant takes effect after a restart",
+            key
+        ));
+    }
+    app.fs_scope()
+        .allow_directory(&dir, true)
+        .map_err(|e| e.to_string())?;
+    Ok(granted)
+}
+
+/// Withdraws a grant made by `grant_folder_access`. The folder is forbidden
+/// for the rest of this launch and not granted again on the next one.
+#[tauri::command]
+pub async fn revoke_folder_access(
+    path: String,
+    app: tauri::AppHandle,
+) -> Result<Vec<String>, String> {
+    // a folder that has since been deleted can still be revoked by its old path
+    let dir = canonical_dir(&path).unwrap_or_else(|_| PathBuf::from(path.trim()));
+    let key = dir.to_string_lossy().to_string();
+    let mut granted = Vec::new();
+    settings::update(&app, |s| {
+        s.fs_grants.retain(|g| *g != key);
+        granted = s.fs_grants.clone();
+    })?;
+    app.fs_scope()
+        .forbid_directory(&dir, true)
+        .map_err(|e| e.to_string())?;
+    REVOKED.lock().map_err(|_| "lock poisoned")?.insert(dir);
+    Ok(granted)
+}
+
+#[tauri::command]
+pub async fn list_folder_access(app: tauri::AppHandle) -> Result<Vec<String>, String> {
+    Ok(settings::load(&app).fs_grants)
+}
@@ -0,0 +1,76 @@
+use std::path::Path;
+
+// libheif is a native library, so HEIC/HEIF decoding is only compiled in with
+// the `heic` feature. Without it these files still scan as images but get no
+// thumbnail or EXIF.
+pub const SUPPORTED: bool = cfg!(feature = "heic");
+
+pub fn is_heif(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .is_some_and(|e| e == "heic" || e == "heif")
+}
+
+/// Decodes the primary image, scaled so its longest edge fits `max_edge`.
+/// libheif applies the container's rotation and mirroring, so the result is
+/// already upright.
+#[cfg(feature = "heic")]
+pub fn decode(bytes: &[u8], max_edge: u32) -> Result<image::DynamicImage, String> {
+    use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+    let lib = LibHeif::new();
+    let ctx = HeifContext::read_from_bytes(bytes).map_err(|e| e.to_string())?;
+    let handle = ctx.primary_image_handle().map_err(|e| e.to_string())?;
+    let mut decoded = lib
+        .decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None)
+        .map_err(|e| e.to_string())?;
+    let (w, h) = (decoded.width(), decoded.height());
+    if w > max_edge || h > max_edge {
+        // scale inside libheif; copying a full 12MP frame out first is wasteful
+        let scale = max_edge as f64 / w.max(h) as f64;
+        let tw = ((w as f64 * scale).round() as u32).max(1);
+        let th = ((h as f64 * scale).round() as u32).max(1);
+        decoded = decoded.scale(tw, th, None).map_err(|e| e.to_string())?;
+    }
+    let planes = decoded.planes();
+    let plane = planes
+        .interleaved
+        .ok_or_else(|| "HEIC decode produced no RGB plane".to_string())?;
+    let row = plane.width as usize * 3;
+    let mut pixels = Vec::with_capacity(row * plane.height as usize);
+    // rows are padded to `stride`
+    for y in 0..plane.height as usize {
+        pixels.extend_from_slice(&plane.data[y * plane.stride..y * plane.stride + row]);
+    }
+    image::RgbImage::from_raw(plane.width, plane.height, pixels)
+        .map(image::DynamicImage::ImageRgb8)
+        .ok_or_else(|| "HEIC plane size mismatch".to_string())
+}
+
+#[cfg(not(feature = "heic"))]
+pub fn decode(_bytes: &[u8], _max_edge: u32) -> Result<image::DynamicImage, String> {
+    Err("HEIC support not built in".into())
+}
+
+/// Raw EXIF block of the primary image, starting at its TIFF header.
+#[cfg(feature = "heic")]
+pub fn exif(bytes: &[u8]) -> Option<Vec<u8>> {
+    use libheif_rs::{HeifContext, ItemId};
+
+    let ctx = HeifContext::read_from_bytes(bytes).ok()?;
+    let handle = ctx.primary_image_handle().ok()?;
+    let mut ids: Vec<ItemId> = vec![0; 1];
+    if handle.metadata_block_ids(&mut ids, b"Exif") == 0 {
+        return None;
+    }
+    let block = handle.metadata(ids[0]).ok()?;
+    // HEIF prefixes the block with a big-endian offset to the TIFF header
+    let offset = u32::from_be_bytes(block.get(0..4)?.try_into().ok()?) as usize;
+    block.get(4 + offset..).map(|b| b.to_vec())
+}
+
+#[cfg(not(feature = "heic"))]
+pub fn exif(_bytes: &[u8]) -> Option<Vec<u8>> {
+    None
+}
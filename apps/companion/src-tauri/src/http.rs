@@ -0,0 +1,349 @@
+use crate::error::TauraError;
+use crate::{oauth, session_store, settings};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+// Proxy value that forces direct connections, ignoring environment proxies.
+pub const NO_PROXY: &str = "none";
+
+// Shared client so every call reuses pooled connections and TLS sessions.
+// Swapped out wholesale when the proxy setting changes.
+static HTTP_CLIENT: Lazy<RwLock<reqwest::Client>> = Lazy::new(|| {
+    let client = build_client(None).unwrap_or_else(|err| {
+        log::warn!("falling back to default HTTP client: {}", err);
+        reqwest::Client::new()
+    });
+    RwLock::new(client)
+});
+
+/// The shared client. No client-wide timeout: sync uploads can legitimately
+/// stream for minutes, so requests set their own via [`RequestOptions`].
+pub fn client() -> reqwest::Client {
+    match HTTP_CLIENT.read() {
+        Ok(client) => client.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    }
+}
+
+// `proxy` is an http(s) URL, optionally with `user:pass@`; None keeps
+// reqwest's environment detection and NO_PROXY disables proxies entirely.
+fn build_client(proxy: Option<&str>) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(10))
+        .pool_idle_timeout(Duration::from_secs(90))
+        .pool_max_idle_per_host(8)
+        .tcp_keepalive(Duration::from_secs(60))
+        .user_agent(concat!("taura-companion/", env!("CARGO_PKG_VERSION")));
+    match proxy.map(str::trim).filter(|p| !p.is_empty()) {
+        None => {}
+        Some(p) if p.eq_ignore_ascii_case(NO_PROXY) => builder = builder.no_proxy(),
+        Some(p) => {
+            let url = reqwest::Url::parse(p).map_err(|e| format!("invalid proxy url: {}", e))?;
+            if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+                return Err("proxy url must be http:// or https:// with a host".into());
+            }
+            // reqwest sends any userinfo in the URL as Proxy-Authorization
+            let proxy =
+                reqwest::Proxy::all(url).map_err(|e| format!("invalid proxy url: {}", e))?;
+            builder = builder.proxy(proxy);
+        }
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// Checks that `proxy` is a URL `apply_proxy` would accept, without applying it.
+pub fn check_proxy(proxy: &str) -> Result<(), String> {
+    build_client(Some(proxy)).map(|_| ())
+}
+
+/// Rebuilds the shared client for `proxy`. In-flight requests finish on the
+/// client they started with.
+pub fn apply_proxy(proxy: Option<&str>) -> Result<(), String> {
+    let client = build_client(proxy)?;
+    let mut guard = HTTP_CLIENT.write().map_err(|_| "lock poisoned")?;
+    *guard = client;
+    Ok(())
+}
+
+// Splits `user:pass@` off a proxy URL; anything that isn't one is kept whole.
+fn split_credentials(proxy: &str) -> (String, Option<String>) {
+    match reqwest::Url::parse(proxy) {
+        Ok(mut url) if !url.username().is_empty() || url.password().is_some() => {
+            let credentials = format!("{}:{}", url.username(), url.password().unwrap_or(""));
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+            (url.to_string(), Some(credentials))
+        }
+        _ => (proxy.to_string(), None),
+    }
+}
+
+/// Moves any credentials in `proxy` to the keychain and returns the URL to
+/// keep in settings.
+pub fn store_proxy(app: &tauri::AppHandle, proxy: Option<&str>) -> Result<Option<String>, String> {
+    let Some(proxy) = proxy.map(str::trim).filter(|p| !p.is_empty()) else {
+        session_store::save_proxy_credentials(app, None)?;
+        return Ok(None);
+    };
+    let (bare, credentials) = split_credentials(proxy);
+    session_store::save_proxy_credentials(app, credentials.as_deref())?;
+    Ok(Some(bare))
+}
+
+/// The saved proxy with its credentials put back, for `apply_proxy`.
+pub fn saved_proxy(app: &tauri::AppHandle) -> Option<String> {
+    let proxy = settings::load(app).proxy_url?;
+    // saved in plaintext before credentials moved to the keychain
+    if split_credentials(&proxy).1.is_some() {
+        match store_proxy(app, Some(&proxy)) {
+            Ok(bare) => {
+                let _ = settings::update(app, |s| s.proxy_url = bare);
+            }
+            Err(err) => log::warn!("proxy credentials not moved to the keychain: {}", err),
+        }
+        return Some(proxy);
+    }
+    let Some(credentials) = session_store::load_proxy_credentials(app) else {
+        return Some(proxy);
+    };
+    let Ok(mut url) = reqwest::Url::parse(&proxy) else {
+        return Some(proxy);
+    };
+    let (user, pass) = credentials.split_once(':').unwrap_or((&credentials, ""));
+    let _ = url.set_username(user);
+    let _ = url.set_password((!pass.is_empty()).then_some(pass));
+    Some(url.to_string())
+}
+
+/// Sets the proxy for all outbound requests and remembers it, with any
+/// `user:pass@` kept in the keychain rather than settings. `None` or an empty
+/// string goes back to the environment's proxy; "none" disables it.
+#[tauri::command]
+pub async fn set_proxy(url: Option<String>, app: tauri::AppHandle) -> Result<(), String> {
+    let url = url.map(|u| u.trim().to_string()).filter(|u| !u.is_empty());
+    apply_proxy(url.as_deref())?;
+    let saved = store_proxy(&app, url.as_deref())?;
+    settings::update(&app, |s| s.proxy_url = saved)
+}
+
+pub const STREAM_TIMEOUT: Duration = Duration::from_secs(300);
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+// Set by the request itself; a caller's value would corrupt or hijack it.
+const RESERVED_HEADERS: &[&str] = &[
+    "authorization",
+    "content-type",
+    "content-encoding",
+    "content-length",
+    "transfer-encoding",
+    "host",
+];
+
+/// Caller-supplied headers and timeout for requests to a sync gateway
+/// (e.g. an API key required by a reverse proxy), plus the token that
+/// abandons them.
+#[derive(Debug, Clone)]
+pub struct RequestOptions {
+    headers: reqwest::header::HeaderMap,
+    timeout: Duration,
+    cancel: CancellationToken,
+}
+
+impl RequestOptions {
+    pub fn new(
+        headers: Option<HashMap<String, String>>,
+        timeout_secs: Option<u64>,
+        default_timeout: Duration,
+    ) -> Result<Self, TauraError> {
+        use reqwest::header::{HeaderName, HeaderValue};
+        let mut map = reqwest::header::HeaderMap::new();
+        for (name, value) in headers.unwrap_or_default() {
+            let name = HeaderName::from_bytes(name.trim().as_bytes()).map_err(|_| {
+                TauraError::invalid_input(format!("invalid header name \"{}\"", name))
+            })?;
+            if RESERVED_HEADERS.contains(&name.as_str()) {
+                return Err(TauraError::invalid_input(format!(
+                    "header {} can't be overridden",
+                    name
+                )));
+            }
+            let value = HeaderValue::from_str(&value).map_err(|_| {
+                TauraError::invalid_input(format!("invalid value for header {}", name))
+            })?;
+            map.insert(name, value);
+        }
+        let timeout = match timeout_secs {
+            Some(0) => return Err(TauraError::invalid_input("timeout_secs must be positive")),
+            Some(secs) => Duration::from_secs(secs),
+            None => default_timeout,
+        };
+        Ok(Self {
+            headers: map,
+            timeout,
+            cancel: CancellationToken::new(),
+        })
+    }
+
+    pub fn with_defaults(default_timeout: Duration) -> Self {
+        Self {
+            headers: reqwest::header::HeaderMap::new(),
+            timeout: default_timeout,
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    pub fn cancellable(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    pub fn cancel_token(&self) -> &CancellationToken {
+        &self.cancel
+    }
+
+    /// Runs `fut` until it finishes or the token fires. Cancelling drops the
+    /// future, which aborts its request and closes the connection.
+    pub async fn run<T, F>(&self, fut: F) -> Result<T, TauraError>
+    where
+        F: std::future::Future<Output = Result<T, TauraError>>,
+    {
+        tokio::select! {
+            biased;
+            _ = self.cancel.cancelled() => Err(TauraError::cancelled("operation cancelled")),
+            res = fut => res,
+        }
+    }
+
+    pub fn apply(&self, req: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        req.headers(self.headers.clone()).timeout(self.timeout)
+    }
+}
+
+const DEFAULT_MAX_RETRIES: usize = 3;
+const BACKOFF_BASE: Duration = Duration::from_millis(500);
+const BACKOFF_CAP: Duration = Duration::from_secs(30);
+const RETRY_AFTER_CAP: Duration = Duration::from_secs(120);
+
+/// Sends a bearer-authenticated request, refreshing the session and retrying
+/// once if the server answers 401.
+pub async fn send_authed<F>(
+    app: &tauri::AppHandle,
+    build: F,
+) -> Result<reqwest::Response, TauraError>
+where
+    F: Fn(&reqwest::Client, &str) -> reqwest::RequestBuilder,
+{
+    send_with_retry(app, Some(0), build)
+        .await
+        .map(|sent| sent.resp)
+}
+
+pub struct Sent {
+    pub resp: reqwest::Response,
+    /// 1-based attempt that produced `resp`.
+    pub attempt: usize,
+    pub max_attempts: usize,
+}
+
+fn retryable_status(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+// Retry-After is either delta-seconds or an HTTP date.
+fn retry_after(resp: &reqwest::Response) -> Option<Duration> {
+    let raw = resp
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    let wait = match raw.parse::<u64>() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => {
+            let at = chrono::DateTime::parse_from_rfc2822(raw).ok()?;
+            (at.with_timezone(&chrono::Utc) - chrono::Utc::now())
+                .to_std()
+                .unwrap_or_default()
+        }
+    };
+    Some(wait.min(RETRY_AFTER_CAP))
+}
+
+// Somewhere in the upper half of the exponential step, so clients that
+// failed together don't all come back at the same moment.
+fn backoff(attempt: usize) -> Duration {
+    use rand::Rng;
+    let exp = attempt.saturating_sub(1).min(16) as u32;
+    let step = BACKOFF_BASE.saturating_mul(1 << exp).min(BACKOFF_CAP);
+    step.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+}
+
+/// Like [`send_authed`], but also retries 429/5xx responses and connect or
+/// timeout errors up to `max_retries` times (default 3) with jittered
+/// exponential backoff, honoring `Retry-After`. The final response is returned whatever
+/// its status so callers can report it together with the attempt number.
+pub async fn send_with_retry<F>(
+    app: &tauri::AppHandle,
+    max_retries: Option<usize>,
+    build: F,
+) -> Result<Sent, TauraError>
+where
+    F: Fn(&reqwest::Client, &str) -> reqwest::RequestBuilder,
+{
+    let max_attempts = max_retries.unwrap_or(DEFAULT_MAX_RETRIES) + 1;
+    let mut token = oauth::access_token(app).await?;
+    let mut refreshed = false;
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let wait = match build(&client(), &token).send().await {
+            Ok(resp) if resp.status() == reqwest::StatusCode::UNAUTHORIZED && !refreshed => {
+                // a stale token isn't the server's fault, so it doesn't use up a retry
+                token = oauth::refreshed_access_token(app).await?;
+                refreshed = true;
+                attempt -= 1;
+                continue;
+            }
+            Ok(resp) if retryable_status(resp.status()) && attempt < max_attempts => {
+                let wait = retry_after(&resp).unwrap_or_else(|| backoff(attempt));
+                log::warn!(
+                    "attempt {}/{} got {}, retrying in {:?}",
+                    attempt,
+                    max_attempts,
+                    resp.status(),
+                    wait
+                );
+                wait
+            }
+            Ok(resp) => {
+                return Ok(Sent {
+                    resp,
+                    attempt,
+                    max_attempts,
+                })
+            }
+            Err(err) if (err.is_connect() || err.is_timeout()) && attempt < max_attempts => {
+                log::warn!(
+                    "attempt {}/{} failed: {}, retrying",
+                    attempt,
+                    max_attempts,
+                    err
+                );
+                backoff(attempt)
+            }
+            Err(err) => {
+                return Err(TauraError::from(err).context(&format!(
+                    "request failed on attempt {}/{}",
+                    attempt, max_attempts
+                )))
+            }
+        };
+        tokio::time::sleep(wait).await;
+    }
+}
@@ -0,0 +1,142 @@
+use crate::http;
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+// Providers rotate signing keys every few days and publish the next key ahead
+// of time, so an hour-old set is fine; an unknown `kid` forces a refetch.
+const JWKS_TTL: Duration = Duration::from_secs(3600);
+// Microsoft's multi-tenant issuer is published with this placeholder.
+const TENANT_PLACEHOLDER: &str = "{tenantid}";
+
+static JWKS_CACHE: Lazy<Mutex<HashMap<String, (Instant, JwkSet)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Claims of an id_token whose signature, issuer, audience and expiry checked out.
+#[derive(Debug, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: Option<String>,
+    pub email: Option<String>,
+    pub name: Option<String>,
+    pub picture: Option<String>,
+    /// Microsoft tenant id, used to resolve its templated issuer.
+    tid: Option<String>,
+    nonce: Option<String>,
+}
+
+async fn fetch_jwks(jwks_uri: &str) -> Result<JwkSet, String> {
+    http::client()
+        .get(jwks_uri)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| format!("jwks fetch failed: {e}"))?
+        .json::<JwkSet>()
+        .await
+        .map_err(|e| format!("jwks decode failed: {e}"))
+}
+
+// The provider's key set, from cache unless it is stale or lacks `kid`.
+async fn jwks_for(jwks_uri: &str, kid: &str) -> Result<JwkSet, String> {
+    let mut cache = JWKS_CACHE.lock().await;
+    if let Some((fetched, set)) = cache.get(jwks_uri) {
+        if fetched.elapsed() < JWKS_TTL && set.find(kid).is_some() {
+            return Ok(set.clone());
+        }
+    }
+    let set = fetch_jwks(jwks_uri).await?;
+    cache.insert(jwks_uri.to_string(), (Instant::now(), set.clone()));
+    Ok(set)
+}
+
+// Algorithms `jwk` can verify. The token header names its own algorithm, so
+// it is only accepted when it is one of these; a key that states its
+// algorithm allows that one alone.
+fn key_algorithms(jwk: &Jwk) -> Vec<Algorithm> {
+    use Algorithm::*;
+    let by_type = match &jwk.algorithm {
+        AlgorithmParameters::RSA(_) => vec![RS256, RS384, RS512, PS256, PS384, PS512],
+        AlgorithmParameters::EllipticCurve(ec) => match ec.curve {
+            EllipticCurve::P256 => vec![ES256],
+            EllipticCurve::P384 => vec![ES384],
+            _ => Vec::new(),
+        },
+        AlgorithmParameters::OctetKeyPair(okp) if okp.curve == EllipticCurve::Ed25519 => {
+            vec![EdDSA]
+        }
+        // a symmetric key would let anyone holding the client secret mint tokens
+        _ => Vec::new(),
+    };
+    match jwk
+        .common
+        .key_algorithm
+        .and_then(|alg| Algorithm::from_str(&alg.to_string()).ok())
+    {
+        Some(stated) => by_type.into_iter().filter(|alg| *alg == stated).collect(),
+        None => by_type,
+    }
+}
+
+fn issuer_matches(expected: &str, claims: &IdTokenClaims) -> bool {
+    match (expected.contains(TENANT_PLACEHOLDER), claims.tid.as_deref()) {
+        (true, Some(tid)) => expected.replace(TENANT_PLACEHOLDER, tid) == claims.iss,
+        (true, None) => false,
+        (false, _) => expected == claims.iss,
+    }
+}
+
+/// Verifies `token` against the provider's published keys: the signature,
+/// `iss` against `issuers`, `aud` against `client_id`, `exp`, and that
+/// `nonce` is the one sent with the authorization request.
+pub async fn verify(
+    token: &str,
+    client_id: &str,
+    issuers: &[String],
+    jwks_uri: &str,
+    nonce: &str,
+) -> Result<IdTokenClaims, String> {
+    let header =
+        jsonwebtoken::decode_header(token).map_err(|e| format!("id_token malformed: {e}"))?;
+    let kid = header
+        .kid
+        .ok_or_else(|| "id_token has no key id".to_string())?;
+    let jwks = jwks_for(jwks_uri, &kid).await?;
+    let jwk = jwks
+        .find(&kid)
+        .ok_or_else(|| format!("id_token signed with unknown key {}", kid))?;
+    let key = DecodingKey::from_jwk(jwk).map_err(|e| format!("id_token key unusable: {e}"))?;
+    let algorithms = key_algorithms(jwk);
+    if !algorithms.contains(&header.alg) {
+        return Err(format!(
+            "id_token alg {:?} doesn't fit key {}",
+            header.alg, kid
+        ));
+    }
+
+    let mut validation = Validation::new(header.alg);
+    validation.algorithms = algorithms;
+    validation.set_audience(&[client_id]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+    let claims = jsonwebtoken::decode::<IdTokenClaims>(token, &key, &validation)
+        .map_err(|e| format!("id_token rejected: {e}"))?
+        .claims;
+    // checked here rather than by the library to allow templated issuers
+    if !issuers.iter().any(|iss| issuer_matches(iss, &claims)) {
+        return Err(format!(
+            "id_token rejected: unexpected issuer {}",
+            claims.iss
+        ));
+    }
+    // a token replayed from another sign-in carries that sign-in's nonce
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err("id_token rejected: nonce mismatch".into());
+    }
+    Ok(claims)
+}
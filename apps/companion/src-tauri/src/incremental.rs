@@ -0,0 +1,138 @@
+use crate::error::TauraError;
+use crate::manifest::save_manifest;
+use crate::{cache, catalog, scan_folder_with, MediaMeta, ScanOptions};
+use serde::Serialize;
+use tauri::Emitter;
+
+const DELTA_DIR: &str = "incremental";
+// changes per `scan_delta` event, so a first run over a big library doesn't
+// arrive as one enormous message
+const DELTA_EVENT_CHUNK: usize = 500;
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum DeltaKind {
+    Added,
+    Changed,
+    Removed,
+}
+
+#[derive(Serialize)]
+struct DeltaChange<'a> {
+    uri: &'a str,
+    kind: DeltaKind,
+}
+
+/// What changed under a folder since the catalog last saw it.
+#[derive(Serialize)]
+pub struct ScanDelta {
+    pub scan_id: String,
+    /// Files the catalog has never seen.
+    pub added: Vec<MediaMeta>,
+    /// Catalogued files whose size or mtime moved.
+    pub changed: Vec<MediaMeta>,
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+    /// False when the walk was cancelled or timed out; deletions are only
+    /// known after a full walk, so `removed` is empty then.
+    pub complete: bool,
+    pub errors: Vec<String>,
+}
+
+fn emit_changes(app: &tauri::AppHandle, scan_id: &str, path: &str, changes: &[DeltaChange]) {
+    for chunk in changes.chunks(DELTA_EVENT_CHUNK) {
+        let _ = app.emit(
+            "scan_delta",
+            serde_json::json!({ "scan_id": scan_id, "path": path, "changes": chunk }),
+        );
+    }
+}
+
+/// Walks `path` and reports only what differs from the local catalog by
+/// size or mtime: new, changed and deleted media. Unchanged files cost a
+/// stat each and nothing more. The catalog is updated as by `scan_folder`,
+/// and the differences are announced as `scan_delta` events. Walk options
+/// should match the scans that filled the catalog, or files they reached
+/// (e.g. below `max_depth`) count as removed.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn incremental_scan(
+    path: String,
+    max_depth: Option<usize>,
+    throttle_ms: Option<u64>,
+    max_runtime_secs: Option<u64>,
+    skip_hidden: Option<bool>,
+    hash_content: Option<bool>,
+    concurrency: Option<usize>,
+    scan_id: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<ScanDelta, TauraError> {
+    let opts = ScanOptions {
+        max_depth,
+        throttle_ms,
+        max_runtime_secs,
+        skip_hidden,
+        hash_content,
+        concurrency,
+        scan_id,
+        ..Default::default()
+    };
+    scan_changes(path, opts, app).await
+}
+
+/// `incremental_scan` with the walk options in `opts`; its sampling,
+/// manifest and thumbnail fields are set here.
+pub async fn scan_changes(
+    path: String,
+    opts: ScanOptions,
+    app: tauri::AppHandle,
+) -> Result<ScanDelta, TauraError> {
+    if path.trim().is_empty() {
+        return Err(TauraError::invalid_input("path empty"));
+    }
+    let known = catalog::manifest_under(&app, &path).await?;
+    // scan_folder diffs against a manifest file; this one is only for the run
+    let manifest_path =
+        cache::subdir(&app, DELTA_DIR)?.join(format!("{}.json", uuid::Uuid::new_v4()));
+    save_manifest(&manifest_path, &known)?;
+    let opts = ScanOptions {
+        max_samples: Some(0),
+        manifest_path: Some(manifest_path.to_string_lossy().to_string()),
+        thumbnails: Some(false),
+        ..opts
+    };
+    let scanned = scan_folder_with(path.clone(), opts, app.clone()).await;
+    let _ = std::fs::remove_file(&manifest_path);
+    let scanned = scanned?;
+
+    let (changed, added): (Vec<MediaMeta>, Vec<MediaMeta>) = scanned
+        .items
+        .into_iter()
+        .partition(|item| known.contains_key(&item.path));
+    let changes: Vec<DeltaChange> = added
+        .iter()
+        .map(|m| (m.path.as_str(), DeltaKind::Added))
+        .chain(
+            changed
+                .iter()
+                .map(|m| (m.path.as_str(), DeltaKind::Changed)),
+        )
+        .chain(
+            scanned
+                .removed
+                .iter()
+                .map(|p| (p.as_str(), DeltaKind::Removed)),
+        )
+        .map(|(uri, kind)| DeltaChange { uri, kind })
+        .collect();
+    emit_changes(&app, &scanned.scan_id, &path, &changes);
+    Ok(ScanDelta {
+        scan_id: scanned.scan_id,
+        added,
+        changed,
+        removed: scanned.removed,
+        unchanged: scanned.unchanged,
+        complete: !scanned.cancelled && !scanned.timed_out,
+        errors: scanned.errors,
+    })
+}
@@ -1,25 +1,103 @@
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
-use futures_util::stream;
+use futures_util::{stream, StreamExt};
 use once_cell::sync::Lazy;
 use std::collections::{HashMap, HashSet};
 use std::io;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use tauri::{Emitter, Manager};
 use tokio::time::sleep; // for throttled scan yielding
 
+mod error;
+use error::TauraError;
+mod http;
+use http::{send_authed, send_with_retry, set_proxy};
+mod bandwidth;
+mod exclude;
+mod id_token;
 mod oauth;
-use oauth::{get_session, google_auth_start, logout, refresh_session, ensure_fresh_session};
+mod redact;
+mod session_store;
+mod settings;
+mod throttle;
+use bandwidth::set_upload_rate_limit;
+use exclude::{get_exclusions, set_exclusions};
+mod mp4;
+mod sync_queue;
+use sync_queue::{flush_sync_queue, pause_sync, queue_sync, resume_sync, sync_queue_status};
+mod overlay;
+use overlay::{reset_overlay_position, save_overlay_position};
+mod search;
+use search::search;
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+mod shortcut;
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+mod tray;
+mod watcher;
+use watcher::{unwatch_folder, watch_folder};
+mod scheduler;
+use oauth::{
+    auth_start, build_auth_url, complete_auth, describe_session, ensure_fresh_session,
+    get_scope_status, get_session, google_auth_start, list_sessions, logout, refresh_session,
+    set_auto_refresh, switch_active_session,
+};
+use scheduler::{get_next_sync_time, set_sync_schedule};
+mod tls;
+use tls::inspect_tls;
+mod network;
+use network::network_status;
+mod server;
+use server::ping_server;
+mod embed_status;
+use embed_status::{get_embed_status, watch_embed_status};
+mod exif;
+use exif::shift_timestamps;
+mod manifest;
+use manifest::{load_manifest, save_manifest, ScanManifest};
+mod cache;
+use cache::{clear_cache, get_cache_stats};
+mod catalog;
+mod fs_access;
+use catalog::{clear_index, find_duplicates, get_index_stats, query_index, SyncStatus};
+mod incremental;
+use incremental::incremental_scan;
+mod library;
+use library::{add_library_folder, list_library_folders, remove_library_folder, scan_all};
+mod content_hash;
+mod scan_pool;
+use fs_access::{grant_folder_access, list_folder_access, revoke_folder_access};
+mod thumbnail;
+mod upload_item;
+use upload_item::prepare_upload_item;
+mod animation;
+mod contact_sheet;
+mod ffmpeg;
+mod heic;
+mod operation;
+mod pdf;
+mod pdf_pages;
+mod raw;
+mod sampling;
+use contact_sheet::{cancel_contact_sheet, generate_contact_sheet};
+use thumbnail::{generate_thumbnail, generate_thumbnails};
 
 // Cancellation + config state
-static CANCEL_SCAN: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(false));
+static SCANS: Lazy<std::sync::Mutex<HashMap<String, Arc<ScanControl>>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
 static DEFAULT_THROTTLE_VALUE: Lazy<std::sync::Mutex<u64>> =
     Lazy::new(|| std::sync::Mutex::new(40)); // 40ms gentle by default
+static OVERLAY_HIDE_ON_BLUR: AtomicBool = AtomicBool::new(true);
+static CLOSE_TO_TRAY: AtomicBool = AtomicBool::new(false);
+const DEFAULT_TARGET_FPS: f64 = 500.0;
+// how long scan_folder waits on its workers before re-checking cancel/pause
+const OUTCOME_POLL: std::time::Duration = std::time::Duration::from_millis(200);
+const MAX_SCAN_ERRORS: usize = 500; // keep error list bounded on huge broken trees
 use std::process::Command;
 use walkdir::WalkDir;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, Clone)]
 struct MediaMeta {
     path: String,
     size: u64,
@@ -28,45 +106,110 @@ struct MediaMeta {
     lat: Option<f64>,
     lon: Option<f64>,
     timestamp: Option<String>,
+    merged_from: Option<Vec<String>>,
+    duration_secs: Option<f64>,
+    width: Option<u32>,
+    height: Option<u32>,
+    page_count: Option<u32>,
+    /// EXIF orientation (1-8); `width`/`height` already account for it.
+    orientation: Option<u16>,
+    camera_make: Option<String>,
+    camera_model: Option<String>,
+    /// Cached preview, when the scan was asked to generate thumbnails.
+    thumbnail: Option<String>,
+    /// Set for GIF, WebP and PNG: whether the image has more than one frame.
+    animated: Option<bool>,
+    /// blake3 of the contents, when the scan was asked to hash files.
+    hash: Option<String>,
+    /// First file in the same scan with identical contents; the sync skips
+    /// this one.
+    duplicate_of: Option<String>,
 }
 
 #[derive(serde::Serialize)]
 struct ScanResult {
+    scan_id: String,
     count: usize,
-    samples: Vec<String>,
+    samples: Vec<MediaMeta>,
     items: Vec<MediaMeta>,
+    timed_out: bool,
+    cancelled: bool,
+    errors: Vec<String>,
+    /// Paths behind the walk errors, e.g. folders the OS denied access to;
+    /// hand them to `retry_failed_paths` once access is granted.
+    failed_paths: Vec<String>,
+    unchanged: usize,
+    removed: Vec<String>,
+    total_bytes: u64,
+    by_modality: HashMap<String, usize>,
+    /// Matched files skipped because the same file was already listed.
+    duplicates_skipped: usize,
+    /// Matched files whose contents repeat an earlier file's; only counted
+    /// when hashing.
+    content_duplicates: usize,
+}
+
+fn extension_modality(ext: &str) -> Option<&'static str> {
+    if raw::EXTENSIONS.contains(&ext) {
+        return Some("image");
+    }
+    match ext {
+        "jpg" | "jpeg" | "png" | "gif" | "webp" | "bmp" | "tiff" | "tif" | "heic" | "heif" => {
+            Some("image")
+        }
+        "pdf" => Some("pdf_page"),
+        "mp4" | "mov" | "avi" | "mkv" => Some("video"),
+        _ => None,
+    }
 }
 
-fn is_media_file(entry: &std::path::Path) -> bool {
-    match entry
+fn mime_modality(mime: &str) -> Option<&'static str> {
+    match mime {
+        "image/jpeg" | "image/png" | "image/gif" | "image/webp" | "image/bmp" | "image/tiff"
+        | "image/heif" | "image/heic" | "image/x-canon-cr2" => Some("image"),
+        "application/pdf" => Some("pdf_page"),
+        "video/mp4" | "video/quicktime" | "video/x-msvideo" | "video/x-matroska" => Some("video"),
+        _ => None,
+    }
+}
+
+/// Classifies a file as image, video or pdf_page. The extension decides on
+/// its own unless it is missing or `sniff` is set, in which case the magic
+/// bytes are read and win over whatever the extension claims.
+fn media_modality(path: &std::path::Path, sniff: bool) -> Option<&'static str> {
+    let ext = path
         .extension()
         .and_then(|s| s.to_str())
-        .map(|s| s.to_lowercase())
-    {
-        Some(ext) => matches!(
-            ext.as_str(),
-            "jpg"
-                | "jpeg"
-                | "png"
-                | "gif"
-                | "webp"
-                | "bmp"
-                | "tiff"
-                | "tif"
-                | "heic"
-                | "heif"
-                | "pdf"
-                | "mp4"
-                | "mov"
-                | "avi"
-                | "mkv"
-        ),
-        None => false,
+        .map(|s| s.to_lowercase());
+    let by_ext = ext.as_deref().and_then(extension_modality);
+    if !sniff && ext.is_some() {
+        return by_ext;
+    }
+    match infer::get_from_path(path) {
+        Ok(Some(kind)) => mime_modality(kind.mime_type()),
+        // unrecognised header: trust the extension if there is one
+        _ => by_ext,
     }
 }
 
+// An empty list allows every media extension.
+fn extension_allowed(path: &std::path::Path, allowed: &[String]) -> bool {
+    allowed.is_empty()
+        || path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| allowed.contains(&e.to_ascii_lowercase()))
+}
+
 #[tauri::command]
-async fn get_default_folder() -> Result<String, String> {
+async fn get_default_folder(app: tauri::AppHandle) -> Result<String, String> {
+    let configured = settings::load(&app)
+        .default_folders
+        .into_iter()
+        .find(|f| std::path::Path::new(f).is_dir());
+    if let Some(folder) = configured {
+        return Ok(folder);
+    }
     let home_dir = std::env::var("USERPROFILE")
         .or_else(|_| std::env::var("HOME"))
         .unwrap_or_else(|_| "C:\\".to_string());
@@ -92,159 +235,1021 @@ async fn pick_folder() -> Result<Option<String>, String> {
     }
 }
 
+// Only a definite "not found" counts; a path the OS won't let us stat may
+// well still be there.
+fn is_gone(path: &str) -> bool {
+    std::fs::symlink_metadata(path).is_err_and(|e| e.kind() == io::ErrorKind::NotFound)
+}
+
+/// Dotfiles and dot-directories on Unix; hidden or system entries on Windows.
+fn is_hidden(entry: &walkdir::DirEntry) -> bool {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+        entry.metadata().is_ok_and(|md| {
+            md.file_attributes() & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0
+        })
+    }
+    #[cfg(not(windows))]
+    {
+        entry
+            .file_name()
+            .to_str()
+            .is_some_and(|n| n.starts_with('.'))
+    }
+}
+
+/// Identity of a file or folder however it was reached: device and inode on
+/// Unix, which also sees through bind mounts, and the canonical path elsewhere.
+#[cfg(unix)]
+type VisitKey = (u64, u64);
+#[cfg(not(unix))]
+type VisitKey = std::path::PathBuf;
+
+fn visit_key(entry: &walkdir::DirEntry) -> Option<VisitKey> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        // follows the link when the walk does, so a linked folder keys as its target
+        entry.metadata().ok().map(|md| (md.dev(), md.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        std::fs::canonicalize(entry.path()).ok()
+    }
+}
+
+/// Everything `scan_folder` takes besides the folder, each None for its
+/// default, so in-process scans only spell out what they change.
+#[derive(Default)]
+struct ScanOptions {
+    max_samples: Option<usize>,
+    sample_strategy: Option<String>,
+    throttle_ms: Option<u64>,
+    max_runtime_secs: Option<u64>,
+    max_depth: Option<usize>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    modified_after: Option<String>,
+    modified_before: Option<String>,
+    manifest_path: Option<String>,
+    merge_pdf_sequences: Option<bool>,
+    scan_id: Option<String>,
+    adaptive_throttle: Option<bool>,
+    target_files_per_sec: Option<f64>,
+    sniff_content: Option<bool>,
+    thumbnails: Option<bool>,
+    thumbnail_max_edge: Option<u32>,
+    skip_hidden: Option<bool>,
+    follow_links: Option<bool>,
+    hash_content: Option<bool>,
+    concurrency: Option<usize>,
+}
+
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn scan_folder(
     path: String,
     max_samples: Option<usize>,
+    sample_strategy: Option<String>,
     throttle_ms: Option<u64>,
+    max_runtime_secs: Option<u64>,
+    max_depth: Option<usize>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    modified_after: Option<String>,
+    modified_before: Option<String>,
+    manifest_path: Option<String>,
+    merge_pdf_sequences: Option<bool>,
+    scan_id: Option<String>,
+    adaptive_throttle: Option<bool>,
+    target_files_per_sec: Option<f64>,
+    sniff_content: Option<bool>,
+    thumbnails: Option<bool>,
+    thumbnail_max_edge: Option<u32>,
+    skip_hidden: Option<bool>,
+    follow_links: Option<bool>,
+    hash_content: Option<bool>,
+    concurrency: Option<usize>,
+    app: tauri::AppHandle,
+) -> Result<ScanResult, TauraError> {
+    let opts = ScanOptions {
+        max_samples,
+        sample_strategy,
+        throttle_ms,
+        max_runtime_secs,
+        max_depth,
+        min_size,
+        max_size,
+        modified_after,
+        modified_before,
+        manifest_path,
+        merge_pdf_sequences,
+        scan_id,
+        adaptive_throttle,
+        target_files_per_sec,
+        sniff_content,
+        thumbnails,
+        thumbnail_max_edge,
+        skip_hidden,
+        follow_links,
+        hash_content,
+        concurrency,
+    };
+    scan_folder_with(path, opts, app).await
+}
+
+async fn scan_folder_with(
+    path: String,
+    opts: ScanOptions,
     app: tauri::AppHandle,
-) -> Result<ScanResult, String> {
+) -> Result<ScanResult, TauraError> {
+    let ScanOptions {
+        max_samples,
+        sample_strategy,
+        throttle_ms,
+        max_runtime_secs,
+        max_depth,
+        min_size,
+        max_size,
+        modified_after,
+        modified_before,
+        manifest_path,
+        merge_pdf_sequences,
+        scan_id,
+        adaptive_throttle,
+        target_files_per_sec,
+        sniff_content,
+        thumbnails,
+        thumbnail_max_edge,
+        skip_hidden,
+        follow_links,
+        hash_content,
+        concurrency,
+    } = opts;
     if path.is_empty() {
-        return Err("path empty".into());
+        return Err(TauraError::invalid_input("path empty"));
     }
-    // Reset cancellation flag at start
-    CANCEL_SCAN.store(false, Ordering::SeqCst);
+    let scan = ScanRegistration::new(scan_id).map_err(TauraError::invalid_input)?;
+    let scan_id = scan.id.clone();
+    let parse_bound = |raw: &Option<String>, name: &str| match raw
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
+        Some(v) => chrono::DateTime::parse_from_rfc3339(v)
+            .map(|dt| Some(dt.with_timezone(&Utc)))
+            .map_err(|e| TauraError::invalid_input(format!("invalid {}: {}", name, e))),
+        None => Ok(None),
+    };
+    let modified_after = parse_bound(&modified_after, "modified_after")?;
+    let modified_before = parse_bound(&modified_before, "modified_before")?;
     let limit = max_samples.unwrap_or(10);
-    let mut samples = Vec::new();
+    let sample_strategy = sampling::SampleStrategy::parse(sample_strategy.as_deref())?;
     let mut count: usize = 0;
     let mut items: Vec<MediaMeta> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
+    let mut failed_paths: Vec<String> = Vec::new();
+    let mut error_count: usize = 0;
+    let manifest_path = manifest_path
+        .filter(|p| !p.trim().is_empty())
+        .map(std::path::PathBuf::from);
+    let previous_manifest: Arc<ScanManifest> = Arc::new(
+        manifest_path
+            .as_deref()
+            .map(load_manifest)
+            .unwrap_or_default(),
+    );
+    let mut seen_manifest: ScanManifest = ScanManifest::new();
+    let mut unchanged: usize = 0;
+    let mut total_bytes: u64 = 0;
+    let mut by_modality: HashMap<String, usize> = HashMap::new();
+    let mut duplicates_skipped: usize = 0;
+    // content hash -> first path seen with it
+    let mut first_by_hash: HashMap<String, String> = HashMap::new();
+    let mut content_duplicates: usize = 0;
 
-    let walker = WalkDir::new(&path).follow_links(false).max_depth(8);
+    // default depth of 8; an explicit 0 lifts the limit entirely
+    let depth_limit = match max_depth {
+        Some(0) => None,
+        Some(d) => Some(d),
+        None => Some(8),
+    };
+    let follow_links = follow_links.unwrap_or(false);
+    let mut walker = WalkDir::new(&path).follow_links(follow_links);
+    if let Some(d) = depth_limit {
+        walker = walker.max_depth(d);
+    }
+    let skip_hidden = skip_hidden.unwrap_or(true);
+    let config = settings::load(&app);
+    let root = std::path::PathBuf::from(&path);
+    let mut exclusions = exclude::rules(&config)
+        .map_err(TauraError::invalid_input)?
+        .walk(&root);
+    // walkdir reports links back to an ancestor as errors; this catches the
+    // rest, e.g. two links to the same folder, so it is only walked once
+    let mut seen_dirs: HashSet<VisitKey> = HashSet::new();
+    let mut first_visit = move |e: &walkdir::DirEntry| {
+        !follow_links
+            || !e.file_type().is_dir()
+            || visit_key(e).map_or(true, |dir| seen_dirs.insert(dir))
+    };
+    // pruning here keeps the walker from descending into hidden folders at all;
+    // the root itself is scanned even if hidden, since the user picked it
+    let walker = walker.into_iter().filter_entry(move |e| {
+        !(e.depth() > 0 && ((skip_hidden && is_hidden(e)) || exclusions.excludes(e)))
+            && first_visit(e)
+    });
     let mut processed: usize = 0;
     let mut last_emit = std::time::Instant::now();
+    let started = std::time::Instant::now();
+    let max_runtime = max_runtime_secs.map(std::time::Duration::from_secs);
 
     // initial event (indeterminate total)
     let _ = app.emit(
         "scan_progress",
         serde_json::json!({
+          "scan_id": scan_id,
           "path": path,
           "processed": 0,
           "total": 0,
-          "matched": 0
+          "matched": 0,
+          "error_count": 0,
+          "max_depth": depth_limit
         }),
     );
 
-    let sleep_every = 32usize; // after how many files to apply sleep
+    let sleep_every = 32usize; // adaptive mode re-measures after this many files
     let throttle = throttle_ms
         .or_else(|| {
             // use stored default throttle if user didn't explicitly pass one
             Some(*DEFAULT_THROTTLE_VALUE.lock().unwrap())
         })
         .unwrap_or(0);
-    for entry in walker {
-        if CANCEL_SCAN.load(Ordering::SeqCst) {
+    // adaptive mode starts from the fixed throttle and steers toward the target rate
+    let target_fps = target_files_per_sec.unwrap_or(DEFAULT_TARGET_FPS);
+    let mut adaptive = adaptive_throttle
+        .unwrap_or(false)
+        .then(|| throttle::AdaptiveThrottle::new(target_fps, throttle));
+    // throttle 0 means no throttling at all
+    let mut duty = (throttle > 0).then(|| throttle::DutyCycle::new(throttle));
+    let effective_throttle = |adaptive: &Option<throttle::AdaptiveThrottle>| {
+        adaptive.as_ref().map_or(throttle, |a| a.current_ms())
+    };
+    let mut cancelled;
+    let mut timed_out;
+    let mut paused_for = std::time::Duration::ZERO;
+    // summary timings, so a slow scan can be told apart as throttle-bound,
+    // stuck on per-file metadata, or just a slow disk
+    let mut throttle_slept = std::time::Duration::ZERO;
+    // summed over workers, so it can exceed the walk's wall time
+    let mut metadata_time = std::time::Duration::ZERO;
+    let hash_content = hash_content.unwrap_or(false);
+    // only new or changed files need reading in full
+    let known_hashes = if hash_content {
+        catalog::hashes_under(&app, &path)
+            .await
+            .unwrap_or_else(|err| {
+                log::warn!("catalog hashes unavailable, hashing every file: {}", err);
+                Default::default()
+            })
+    } else {
+        Default::default()
+    };
+    let rules = scan_pool::FileRules {
+        sniff: sniff_content.unwrap_or(false),
+        allowed_extensions: config.media_extensions,
+        min_size,
+        max_size,
+        modified_after,
+        modified_before,
+        previous_manifest: previous_manifest.clone(),
+        hash_content,
+        known_hashes: Arc::new(known_hashes),
+    };
+    let concurrency = concurrency
+        .unwrap_or_else(scan_pool::default_concurrency)
+        .clamp(1, scan_pool::MAX_CONCURRENCY);
+    let mut outcomes = scan_pool::spawn(walker, rules, concurrency);
+    loop {
+        if scan.control.paused.load(Ordering::SeqCst) {
+            // hold the walker where it is; it resumes from the same entry
+            let paused_at = std::time::Instant::now();
+            let pause_event = |paused: bool| {
+                let _ = app.emit(
+                    "scan_progress",
+                    serde_json::json!({
+                      "scan_id": scan_id,
+                      "path": path,
+                      "processed": processed,
+                      "total": 0,
+                      "matched": count,
+                      "error_count": error_count,
+                      "paused": paused
+                    }),
+                );
+            };
+            pause_event(true);
+            while scan.control.paused.load(Ordering::SeqCst)
+                && !scan.control.cancel.load(Ordering::SeqCst)
+            {
+                sleep(std::time::Duration::from_millis(200)).await;
+            }
+            pause_event(false);
+            paused_for += paused_at.elapsed();
+            // time spent paused isn't work to pay back with throttle sleeps
+            if let Some(duty) = duty.as_mut() {
+                duty.restart();
+            }
+            if let Some(adaptive) = adaptive.as_mut() {
+                adaptive.batch_started();
+            }
+        }
+        cancelled = scan.control.cancel.load(Ordering::SeqCst);
+        // runtime cap stops the walk the same way cancellation does; time
+        // spent paused doesn't count against it
+        timed_out =
+            max_runtime.is_some_and(|limit| started.elapsed().saturating_sub(paused_for) >= limit);
+        if cancelled || timed_out {
+            break;
+        }
+        // wake up now and then so cancel, pause and the runtime cap are
+        // noticed while a slow share has nothing to report
+        let outcome = match tokio::time::timeout(OUTCOME_POLL, outcomes.recv()).await {
+            Ok(Some(outcome)) => outcome,
+            Ok(None) => break,
+            Err(_) => continue,
+        };
+        match outcome {
+            scan_pool::Outcome::WalkError(err) => {
+                // keep walking, but remember why this entry was skipped
+                error_count += 1;
+                // only the messages are capped; every path is needed to retry
+                if let Some(p) = err.path() {
+                    failed_paths.push(p.to_string_lossy().to_string());
+                }
+                if errors.len() < MAX_SCAN_ERRORS {
+                    let location = err
+                        .path()
+                        .map(|p| p.display().to_string())
+                        .unwrap_or_else(|| path.clone());
+                    errors.push(format!("{}: {}", location, err));
+                }
+                continue;
+            }
+            scan_pool::Outcome::Ignored | scan_pool::Outcome::OutOfRange => {}
+            scan_pool::Outcome::Duplicate => duplicates_skipped += 1,
+            scan_pool::Outcome::Unchanged { key, fingerprint } => {
+                seen_manifest.insert(key, fingerprint);
+                unchanged += 1;
+            }
+            scan_pool::Outcome::Matched {
+                key,
+                fingerprint,
+                modality,
+                meta,
+                elapsed,
+            } => {
+                count += 1;
+                total_bytes += fingerprint.0;
+                *by_modality.entry(modality.to_string()).or_default() += 1;
+                metadata_time += elapsed;
+                seen_manifest.insert(key, fingerprint);
+                if let Some(mut meta) = meta {
+                    // the first copy to arrive is the one that gets uploaded
+                    if let Some(hash) = &meta.hash {
+                        match first_by_hash.get(hash) {
+                            Some(first) => {
+                                meta.duplicate_of = Some(first.clone());
+                                content_duplicates += 1;
+                            }
+                            None => {
+                                first_by_hash.insert(hash.clone(), meta.path.clone());
+                            }
+                        }
+                    }
+                    items.push(*meta);
+                }
+            }
+        }
+        processed += 1;
+        if last_emit.elapsed().as_millis() > 120 {
             let _ = app.emit(
                 "scan_progress",
                 serde_json::json!({
+                  "scan_id": scan_id,
                   "path": path,
                   "processed": processed,
-                  "total": processed,
+                  "total": 0, // unknown until end
                   "matched": count,
-                  "cancelled": true,
-                  "done": true
+                  "error_count": error_count,
+                  "total_bytes": total_bytes,
+                  "by_modality": by_modality,
+                  "throttle_ms": effective_throttle(&adaptive)
                 }),
             );
-            return Ok(ScanResult {
-                count,
-                samples,
-                items,
-            });
+            last_emit = std::time::Instant::now();
         }
-        let entry = match entry {
-            Ok(e) => e,
-            Err(_) => continue,
-        };
-        if entry.file_type().is_file() {
-            processed += 1;
-            let p = entry.path();
-            if is_media_file(p) {
-                count += 1;
-                if samples.len() < limit {
-                    if let Some(s) = p.to_str() {
-                        samples.push(s.to_string());
-                    }
-                }
-                let mut size: u64 = 0;
-                let mut modified: Option<String> = None;
-                if let Ok(md) = entry.metadata() {
-                    size = md.len();
-                    if let Ok(mt) = md.modified() {
-                        let dt: chrono::DateTime<chrono::Utc> = mt.into();
-                        modified = Some(dt.to_rfc3339());
-                    }
-                }
-                let (lat, lon, exif_timestamp) = (None, None, None);
-                let modality = match p
-                    .extension()
-                    .and_then(|s| s.to_str())
-                    .map(|s| s.to_lowercase())
-                {
-                    Some(ext) if ext == "pdf" => "pdf_page".to_string(),
-                    Some(ext) if matches!(ext.as_str(), "mp4" | "mov" | "avi" | "mkv") => {
-                        "video".to_string()
-                    }
-                    _ => "image".to_string(),
-                };
-                if let Some(s) = p.to_str() {
-                    items.push(MediaMeta {
-                        path: s.to_string(),
-                        size,
-                        modified,
-                        modality,
-                        lat,
-                        lon,
-                        timestamp: exif_timestamp,
-                    });
+        if let Some(adaptive) = adaptive.as_mut() {
+            if processed % sleep_every == 0 {
+                let pause = adaptive.next_sleep(processed, sleep_every);
+                if !pause.is_zero() {
+                    sleep(pause).await;
+                    throttle_slept += pause;
                 }
+                adaptive.batch_started();
             }
-            if last_emit.elapsed().as_millis() > 120 {
-                let _ = app.emit(
-                    "scan_progress",
-                    serde_json::json!({
-                      "path": path,
-                      "processed": processed,
-                      "total": 0, // unknown until end
-                      "matched": count
-                    }),
-                );
-                last_emit = std::time::Instant::now();
+        } else if let Some(duty) = duty.as_mut() {
+            // cooperative yield to keep disk + UI responsive
+            let pause = duty.due();
+            if !pause.is_zero() {
+                sleep(pause).await;
+                throttle_slept += pause;
+                duty.restart();
             }
-            if throttle > 0 && (processed % sleep_every == 0) {
-                // cooperative yield to keep disk + UI responsive
-                sleep(std::time::Duration::from_millis(throttle)).await;
+        }
+    }
+    // an early stop winds the walker and workers down
+    drop(outcomes);
+    let walk_time = started.elapsed();
+    let thumbnails_started = std::time::Instant::now();
+    if thumbnails.unwrap_or(false) && !cancelled && !timed_out {
+        let thumb_dir = thumbnail::cache_dir(&app)?;
+        let edge = thumbnail::clamp_edge(thumbnail_max_edge);
+        let previewable = |m: &MediaMeta| {
+            m.modality == "image"
+                && (heic::SUPPORTED || !heic::is_heif(std::path::Path::new(&m.path)))
+        };
+        for item in items.iter_mut().filter(|m| previewable(m)) {
+            // the walk itself finished, so stopping here only leaves the
+            // remaining items without a preview
+            if scan.control.cancel.load(Ordering::SeqCst)
+                || max_runtime
+                    .is_some_and(|limit| started.elapsed().saturating_sub(paused_for) >= limit)
+            {
+                break;
+            }
+            let src = std::path::PathBuf::from(&item.path);
+            let dir = thumb_dir.clone();
+            match tauri::async_runtime::spawn_blocking(move || {
+                thumbnail::cache_thumbnail(&src, &dir, edge)
+            })
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r)
+            {
+                Ok(out) => item.thumbnail = Some(out.to_string_lossy().to_string()),
+                Err(err) => {
+                    error_count += 1;
+                    if errors.len() < MAX_SCAN_ERRORS {
+                        errors.push(format!("{}: thumbnail failed: {}", item.path, err));
+                    }
+                }
             }
         }
     }
+    let thumbnail_time = thumbnails_started.elapsed();
+    if merge_pdf_sequences.unwrap_or(false) {
+        let cache_dir = cache::subdir(&app, cache::MERGED_PDFS)?;
+        let folded = merge_pdf_items(&mut items, &cache_dir, &mut errors);
+        count -= folded;
+        if let Some(pdfs) = by_modality.get_mut("pdf_page") {
+            *pdfs -= folded;
+        }
+    }
+    // picked last so samples carry thumbnails and merged PDFs
+    let samples = sampling::pick(&items, limit, sample_strategy, |m| &m.modality)
+        .into_iter()
+        .map(|i| items[i].clone())
+        .collect();
+    let complete = !cancelled && !timed_out;
+    let mut removed: Vec<String> = Vec::new();
+    if let Some(mp) = manifest_path.as_deref() {
+        let next = if complete {
+            // a full walk still misses files behind walk errors or denied
+            // folders, and ones it now skips; only those really gone count
+            // as removed, the rest keep their entries
+            let unseen: Vec<(String, (u64, Option<String>))> = previous_manifest
+                .iter()
+                .filter(|(k, _)| !seen_manifest.contains_key(*k))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+            let (gone, kept): (Vec<_>, Vec<_>) = tauri::async_runtime::spawn_blocking(move || {
+                unseen.into_iter().partition(|(k, _)| is_gone(k))
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+            removed = gone.into_iter().map(|(k, _)| k).collect();
+            let mut next = seen_manifest;
+            next.extend(kept);
+            next
+        } else {
+            // partial walk: keep unvisited entries so the next run picks up from here
+            let mut merged = Arc::unwrap_or_clone(previous_manifest);
+            merged.extend(seen_manifest);
+            merged
+        };
+        save_manifest(mp, &next)?;
+    }
+    // the catalog is a convenience; a scan that can't update it still succeeds
+    if let Err(err) = catalog::record_scan(&app, items.clone(), removed.clone()).await {
+        log::warn!("catalog not updated after scan {}: {}", scan_id, err);
+    }
     let _ = app.emit(
         "scan_progress",
         serde_json::json!({
+          "scan_id": scan_id,
           "path": path,
           "processed": processed,
           "total": processed, // final total
           "matched": count,
+          "unchanged": unchanged,
+          "duplicates_skipped": duplicates_skipped,
+          "content_duplicates": content_duplicates,
+          "error_count": error_count,
+          "total_bytes": total_bytes,
+          "by_modality": by_modality,
+          "cancelled": cancelled,
+          "timed_out": timed_out,
           "done": true
         }),
     );
+    let ms = |d: std::time::Duration| d.as_millis() as u64;
+    let _ = app.emit(
+        "scan_summary",
+        serde_json::json!({
+          "scan_id": scan_id,
+          "path": path,
+          "duration_ms": ms(started.elapsed()),
+          "walk_ms": ms(walk_time),
+          "metadata_ms": ms(metadata_time),
+          "thumbnail_ms": ms(thumbnail_time),
+          "throttle_sleep_ms": ms(throttle_slept),
+          "paused_ms": ms(paused_for),
+          "processed": processed,
+          "matched": count,
+          "match_rate": if processed > 0 { count as f64 / processed as f64 } else { 0.0 },
+          "total_bytes": total_bytes,
+          "error_count": error_count,
+          "cancelled": cancelled,
+          "timed_out": timed_out
+        }),
+    );
     Ok(ScanResult {
+        scan_id,
         count,
         samples,
         items,
+        timed_out,
+        cancelled,
+        errors,
+        failed_paths,
+        unchanged,
+        removed,
+        total_bytes,
+        by_modality,
+        duplicates_skipped,
+        content_duplicates,
+    })
+}
+
+// Reads what the index keeps about one media file beyond its size and mtime.
+// None for paths that aren't valid UTF-8, which the index can't address.
+fn media_meta(
+    p: &std::path::Path,
+    modality: &str,
+    size: u64,
+    modified: Option<String>,
+) -> Option<MediaMeta> {
+    let path = p.to_str()?.to_string();
+    let exif = if modality == "image" {
+        exif::read_meta(p)
+    } else {
+        exif::ExifMeta::default()
+    };
+    let orientation = exif.orientation;
+    let mut timestamp = exif.timestamp;
+    let video = if modality == "video" {
+        mp4::read_video_meta(p).unwrap_or_default()
+    } else {
+        mp4::VideoMeta::default()
+    };
+    let page_count = if modality == "pdf_page" {
+        pdf::page_count(p)
+    } else {
+        None
+    };
+    let animated = if modality == "image" {
+        animation::is_animated(p)
+    } else {
+        None
+    };
+    // report the size the image is displayed at, not how it is stored
+    let (width, height) = if modality == "image" {
+        thumbnail::dimensions(p)
+            .map(|(w, h)| {
+                if exif::swaps_dimensions(orientation) {
+                    (h, w)
+                } else {
+                    (w, h)
+                }
+            })
+            .unzip()
+    } else {
+        (video.width, video.height)
+    };
+    if let Some(created) = video.created {
+        timestamp = DateTime::<Utc>::from_timestamp(created, 0).map(|dt| dt.to_rfc3339());
+    }
+    Some(MediaMeta {
+        path,
+        size,
+        modified,
+        modality: modality.to_string(),
+        lat: exif.lat,
+        lon: exif.lon,
+        timestamp,
+        merged_from: None,
+        duration_secs: video.duration_secs,
+        width,
+        height,
+        page_count,
+        orientation,
+        camera_make: exif.make,
+        camera_model: exif.model,
+        thumbnail: None,
+        animated,
+        hash: None,
+        duplicate_of: None,
     })
 }
 
+#[derive(serde::Serialize, Default)]
+struct RetryResult {
+    /// Media that could be read this time.
+    items: Vec<MediaMeta>,
+    errors: Vec<String>,
+    /// Paths that are still unreadable, in the same form as
+    /// `ScanResult::failed_paths`.
+    failed_paths: Vec<String>,
+}
+
+// Reads one retried file, opening it first: with macOS privacy protection a
+// file can be listed and stat'ed long before its contents are readable.
+fn retry_file(p: &std::path::Path, allowed_extensions: &[String], result: &mut RetryResult) {
+    let allowed = extension_allowed(p, allowed_extensions);
+    let Some(modality) = media_modality(p, false).filter(|_| allowed) else {
+        return;
+    };
+    let md = match std::fs::File::open(p).and_then(|f| f.metadata()) {
+        Ok(md) => md,
+        Err(err) => {
+            result.errors.push(format!("{}: {}", p.display(), err));
+            result.failed_paths.push(p.to_string_lossy().to_string());
+            return;
+        }
+    };
+    let modified = md
+        .modified()
+        .ok()
+        .map(|mt| DateTime::<Utc>::from(mt).to_rfc3339());
+    result
+        .items
+        .extend(media_meta(p, modality, md.len(), modified));
+}
+
+/// Re-reads paths an earlier scan couldn't, typically after the user granted
+/// folder access in an OS permission prompt. Folders are walked again with
+/// hidden files and configured excludes skipped; pass the scan's `root` and
+/// `max_depth` so exclusions and the depth limit apply as they did in that
+/// scan (default depth 8, 0 for none). Files are read directly. Nothing is
+/// cached or synced; the caller merges the items.
 #[tauri::command]
-async fn stop_scan() -> Result<(), String> {
-    CANCEL_SCAN.store(true, Ordering::SeqCst);
-    Ok(())
+async fn retry_failed_paths(
+    paths: Vec<String>,
+    root: Option<String>,
+    max_depth: Option<usize>,
+    app: tauri::AppHandle,
+) -> Result<RetryResult, TauraError> {
+    let config = settings::load(&app);
+    let rules = exclude::rules(&config).map_err(TauraError::invalid_input)?;
+    let allowed_extensions = config.media_extensions;
+    let scan_root = root
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty())
+        .map(std::path::PathBuf::from);
+    let depth_limit = match max_depth {
+        Some(0) => None,
+        Some(d) => Some(d),
+        None => Some(8),
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut result = RetryResult::default();
+        let mut seen_files: HashSet<VisitKey> = HashSet::new();
+        for path in paths.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            let start = std::path::PathBuf::from(path);
+            // rules and depth count from the scan's root, not the failed path
+            let root = scan_root
+                .as_deref()
+                .filter(|r| start.starts_with(r))
+                .unwrap_or(&start);
+            let below = start
+                .strip_prefix(root)
+                .map_or(0, |rel| rel.components().count());
+            let mut walker = WalkDir::new(&start);
+            if let Some(d) = depth_limit {
+                if below > d {
+                    continue;
+                }
+                walker = walker.max_depth(d - below);
+            }
+            let mut exclusions = rules.walk(root);
+            let walker = walker
+                .into_iter()
+                .filter_entry(|e| e.depth() == 0 || !(is_hidden(e) || exclusions.excludes(e)));
+            for entry in walker {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(err) => {
+                        let location = err.path().unwrap_or(&start);
+                        result
+                            .failed_paths
+                            .push(location.to_string_lossy().to_string());
+                        if result.errors.len() < MAX_SCAN_ERRORS {
+                            result
+                                .errors
+                                .push(format!("{}: {}", location.display(), err));
+                        }
+                        continue;
+                    }
+                };
+                // overlapping retries (a folder and a file in it) list it once
+                if entry.file_type().is_file()
+                    && visit_key(&entry).map_or(true, |key| seen_files.insert(key))
+                {
+                    retry_file(entry.path(), &allowed_extensions, &mut result);
+                }
+            }
+        }
+        result
+    })
+    .await
+    .map_err(|e| TauraError::from(e.to_string()))
+}
+
+/// Replaces each run of numbered PDFs in `items` with one merged document and
+/// returns how many items were folded away. Runs that fail to merge are left
+/// as individual items and reported in `errors`.
+fn merge_pdf_items(
+    items: &mut Vec<MediaMeta>,
+    cache_dir: &std::path::Path,
+    errors: &mut Vec<String>,
+) -> usize {
+    let runs = pdf::find_pdf_sequences(
+        items
+            .iter()
+            .filter(|m| m.modality == "pdf_page")
+            .map(|m| m.path.as_str()),
+    );
+    let mut folded = 0;
+    for run in runs {
+        use sha2::{Digest, Sha256};
+        let digest = Sha256::digest(run.join("\n").as_bytes());
+        let name: String = digest
+            .iter()
+            .take(12)
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let out = cache_dir.join(format!("{}.pdf", name));
+        if let Err(err) = pdf::merge_pdfs(&run, &out) {
+            if errors.len() < MAX_SCAN_ERRORS {
+                errors.push(format!("pdf merge failed: {}", err));
+            }
+            continue;
+        }
+        let members: HashSet<&str> = run.iter().map(|s| s.as_str()).collect();
+        let mut size = 0;
+        let mut page_count: Option<u32> = Some(0);
+        let mut modified: Option<String> = None;
+        let mut first_index = None;
+        for (i, m) in items.iter().enumerate() {
+            if members.contains(m.path.as_str()) {
+                size += m.size;
+                // a member with an unknown count makes the total unknown
+                page_count = page_count.zip(m.page_count).map(|(a, b)| a + b);
+                // rfc3339 strings from the same clock compare chronologically
+                if m.modified > modified {
+                    modified.clone_from(&m.modified);
+                }
+                first_index.get_or_insert(i);
+            }
+        }
+        let Some(first_index) = first_index else {
+            continue;
+        };
+        let merged = MediaMeta {
+            path: out.to_string_lossy().to_string(),
+            size,
+            modified,
+            modality: "pdf_page".to_string(),
+            lat: None,
+            lon: None,
+            timestamp: None,
+            merged_from: Some(run.clone()),
+            duration_secs: None,
+            width: None,
+            height: None,
+            page_count,
+            orientation: None,
+            camera_make: None,
+            camera_model: None,
+            thumbnail: None,
+            animated: None,
+            hash: None,
+            duplicate_of: None,
+        };
+        items[first_index] = merged;
+        let mut idx = 0;
+        items.retain(|m| {
+            let keep = idx == first_index || !members.contains(m.path.as_str());
+            idx += 1;
+            keep
+        });
+        folded += run.len() - 1;
+    }
+    folded
+}
+
+#[derive(Default)]
+struct ScanControl {
+    cancel: AtomicBool,
+    paused: AtomicBool,
+}
+
+// Keeps a scan's controls registered for as long as the scan runs.
+struct ScanRegistration {
+    id: String,
+    control: Arc<ScanControl>,
+}
+
+impl ScanRegistration {
+    fn new(requested: Option<String>) -> Result<Self, String> {
+        let id = requested
+            .filter(|id| !id.trim().is_empty())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let control = Arc::new(ScanControl::default());
+        let mut scans = SCANS.lock().map_err(|_| "lock poisoned")?;
+        if scans.contains_key(&id) {
+            return Err(format!("scan {} already running", id));
+        }
+        scans.insert(id.clone(), control.clone());
+        Ok(ScanRegistration { id, control })
+    }
 }
 
+impl Drop for ScanRegistration {
+    fn drop(&mut self) {
+        if let Ok(mut scans) = SCANS.lock() {
+            scans.remove(&self.id);
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct StopScanResult {
+    /// False when there was nothing to cancel, e.g. the scan already ended;
+    /// no `cancelled` progress event will follow then.
+    stopped: bool,
+    /// Scans that were running and have been signalled; each still sends its
+    /// final `cancelled: true` progress event once it winds down.
+    scan_ids: Vec<String>,
+}
+
+/// Cancels the scan with `scan_id`, or every running scan when omitted.
 #[tauri::command]
-async fn set_default_throttle(ms: u64) -> Result<(), String> {
-    let mut guard = DEFAULT_THROTTLE_VALUE.lock().map_err(|_| "lock poisoned")?;
-    *guard = ms;
+async fn stop_scan(scan_id: Option<String>) -> Result<StopScanResult, String> {
+    let scans = SCANS.lock().map_err(|_| "lock poisoned")?;
+    let mut scan_ids = Vec::new();
+    for (id, scan) in scans.iter() {
+        if scan_id.as_ref().is_some_and(|wanted| wanted != id) {
+            continue;
+        }
+        // a second stop for a scan that is already winding down isn't news
+        if !scan.cancel.swap(true, Ordering::SeqCst) {
+            scan_ids.push(id.clone());
+        }
+    }
+    Ok(StopScanResult {
+        stopped: !scan_ids.is_empty(),
+        scan_ids,
+    })
+}
+
+/// Cancels the network commands and the scan running under `op_id`, so a
+/// scan and the sync that follows it can be abandoned together.
+#[tauri::command]
+async fn cancel_operation(op_id: String) -> Result<(), TauraError> {
+    let ops = operation::cancel(&op_id);
+    let scan = {
+        let scans = SCANS.lock().map_err(|_| "lock poisoned".to_string())?;
+        scans
+            .get(&op_id)
+            .map(|scan| scan.cancel.store(true, Ordering::SeqCst))
+            .is_some()
+    };
+    if !ops && !scan {
+        return Err(TauraError::invalid_input(format!(
+            "no running operation {}",
+            op_id
+        )));
+    }
     Ok(())
 }
-#[derive(serde::Deserialize, serde::Serialize)]
+
+fn set_scan_paused(scan_id: &str, paused: bool) -> Result<(), String> {
+    let scans = SCANS.lock().map_err(|_| "lock poisoned")?;
+    scans
+        .get(scan_id)
+        .ok_or_else(|| format!("no running scan {}", scan_id))?
+        .paused
+        .store(paused, Ordering::SeqCst);
+    Ok(())
+}
+
+#[tauri::command]
+async fn pause_scan(scan_id: String) -> Result<(), String> {
+    set_scan_paused(&scan_id, true)
+}
+
+#[tauri::command]
+async fn resume_scan(scan_id: String) -> Result<(), String> {
+    set_scan_paused(&scan_id, false)
+}
+
+#[tauri::command]
+async fn set_default_throttle(ms: u64, app: tauri::AppHandle) -> Result<(), String> {
+    {
+        let mut guard = DEFAULT_THROTTLE_VALUE.lock().map_err(|_| "lock poisoned")?;
+        *guard = ms;
+    }
+    settings::update(&app, |s| s.default_throttle_ms = Some(ms))
+}
+
+/// All stored preferences, including the ones set through their own commands.
+#[tauri::command]
+async fn get_settings(app: tauri::AppHandle) -> Result<settings::Settings, String> {
+    Ok(settings::load(&app))
+}
+
+/// Applies the fields present in `patch` and saves them in one write. Each
+/// takes effect immediately, just as through its own setter; an invalid
+/// shortcut, proxy or glob rejects the whole patch before any of it applies.
+#[tauri::command]
+async fn update_settings(
+    mut patch: settings::SettingsPatch,
+    app: tauri::AppHandle,
+) -> Result<settings::Settings, String> {
+    if let Some(globs) = &patch.excluded_globs {
+        exclude::compile(globs)?;
+    }
+    if let Some(proxy) = &patch.proxy_url {
+        http::check_proxy(proxy)?;
+    }
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    if let Some(accelerator) = &patch.overlay_shortcut {
+        // registering can still be refused, so it goes before anything else applies
+        shortcut::set_overlay_shortcut(accelerator.clone(), app.clone()).await?;
+    }
+    if let Some(proxy) = patch.proxy_url.take() {
+        http::apply_proxy(Some(proxy.as_str()))?;
+        let saved = http::store_proxy(&app, Some(proxy.as_str()))?;
+        patch.proxy_url = Some(saved.unwrap_or_default());
+    }
+    if let Some(ms) = patch.default_throttle_ms {
+        let mut guard = DEFAULT_THROTTLE_VALUE.lock().map_err(|_| "lock poisoned")?;
+        *guard = ms;
+    }
+    if let Some(enabled) = patch.overlay_hide_on_blur {
+        OVERLAY_HIDE_ON_BLUR.store(enabled, Ordering::SeqCst);
+    }
+    if let Some(enabled) = patch.close_to_tray {
+        CLOSE_TO_TRAY.store(enabled, Ordering::SeqCst);
+    }
+    if let Some(limits) = &patch.upload_limits {
+        bandwidth::set_limit(limits.max_bytes_per_sec);
+    }
+    settings::update(&app, |s| patch.apply_to(s))?;
+    Ok(settings::load(&app))
+}
+
+#[tauri::command]
+async fn get_default_throttle() -> Result<u64, String> {
+    let guard = DEFAULT_THROTTLE_VALUE.lock().map_err(|_| "lock poisoned")?;
+    Ok(*guard)
+}
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
 struct SyncPayloadItem {
     user_id: String,
     modality: String,
@@ -252,6 +1257,15 @@ struct SyncPayloadItem {
     ts: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     bytes_b64: Option<String>,
+    /// JPEG frames sampled from a video, so the server can embed what it shows.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    frames_b64: Option<Vec<String>>,
+    /// Set on items split from a PDF: the 1-based page this one stands for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    page: Option<u32>,
+    /// Text embedded in a PDF page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -276,16 +1290,58 @@ struct SyncResult {
     embed_queue_depth: Option<usize>,
     embed_errors: Option<Vec<SyncErrorItem>>,
     read_errors: Option<Vec<SyncErrorItem>>,
+    /// Batches that failed outright and were not queued; items in them were
+    /// not synced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    failed_batches: Option<Vec<SyncBatchError>>,
+    /// Set instead of upload counts when `sync_index` ran as a dry run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dry_run: Option<DryRunSummary>,
+    /// Items `sync_missing` skipped because the server already had them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    already_indexed: Option<usize>,
+    /// Items `sync_index` left in the sync queue for `resume_sync`: batches
+    /// that failed with a connection error, 429 or 5xx, and everything after
+    /// a `pause_sync`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    queued: Option<usize>,
+    /// Uploads resent after a 429, 5xx or connection error before they
+    /// went through; failed batches give their attempts in `error`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retries: Option<usize>,
 }
 
-#[tauri::command]
-async fn sync_index(server_url: String, payload: SyncPayload) -> Result<SyncResult, String> {
-    if server_url.is_empty() {
-        return Err("server_url empty".into());
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+struct DryRunSummary {
+    would_upload: usize,
+    /// On-disk size of the items that would upload; files that can't be
+    /// read count as zero.
+    estimated_bytes: u64,
+    already_indexed: usize,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+struct SyncBatchError {
+    index: usize,
+    items: usize,
+    error: String,
+}
+
+fn add_counts(total: &mut Option<usize>, part: Option<usize>) {
+    if let Some(n) = part {
+        *total = Some(total.unwrap_or(0) + n);
     }
-    let trimmed = server_url.trim_end_matches('/');
-    if payload.items.is_empty() {
-        return Ok(SyncResult {
+}
+
+fn add_errors(total: &mut Option<Vec<SyncErrorItem>>, part: Option<Vec<SyncErrorItem>>) {
+    if let Some(errs) = part {
+        total.get_or_insert_with(Vec::new).extend(errs);
+    }
+}
+
+impl SyncResult {
+    fn empty() -> Self {
+        SyncResult {
             upserted: 0,
             embedded_images: Some(0),
             embedded_success: Some(0),
@@ -295,78 +1351,579 @@ async fn sync_index(server_url: String, payload: SyncPayload) -> Result<SyncResu
             embed_queue_depth: Some(0),
             embed_errors: Some(Vec::new()),
             read_errors: Some(Vec::new()),
+            failed_batches: None,
+            dry_run: None,
+            already_indexed: None,
+            queued: None,
+            retries: None,
+        }
+    }
+
+    fn absorb(&mut self, batch: SyncResult) {
+        self.upserted += batch.upserted;
+        add_counts(&mut self.embedded_images, batch.embedded_images);
+        add_counts(&mut self.embedded_success, batch.embedded_success);
+        add_counts(&mut self.embedded_failed, batch.embedded_failed);
+        add_counts(&mut self.requested_embeds, batch.requested_embeds);
+        add_counts(&mut self.queued_embeds, batch.queued_embeds);
+        add_counts(&mut self.retries, batch.retries);
+        // queue depth is a gauge, so the latest batch's reading wins
+        if batch.embed_queue_depth.is_some() {
+            self.embed_queue_depth = batch.embed_queue_depth;
+        }
+        add_errors(&mut self.embed_errors, batch.embed_errors);
+        add_errors(&mut self.read_errors, batch.read_errors);
+    }
+}
+
+const SYNC_PROGRESS_EVERY: usize = 50;
+const DEFAULT_SYNC_BATCH: usize = 1000;
+// inlined bytes, frames and pages make items vary wildly in size, so chunks
+// are capped by body size as well as by count
+const DEFAULT_CHUNK_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+// Size of an item's NDJSON line, newline included, without building it.
+fn ndjson_line_len(item: &SyncPayloadItem) -> u64 {
+    struct Counter(u64);
+    impl io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += buf.len() as u64;
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    let mut counter = Counter(1);
+    let _ = serde_json::to_writer(&mut counter, item);
+    counter.0
+}
+
+// Splits prepared items into runs of at most `max_bytes` of NDJSON, each
+// paired with its size. An item bigger than that goes alone.
+fn byte_chunks(items: &[SyncPayloadItem], max_bytes: u64) -> Vec<(std::ops::Range<usize>, u64)> {
+    let mut chunks = Vec::new();
+    let (mut start, mut bytes) = (0, 0);
+    for (i, item) in items.iter().enumerate() {
+        let len = ndjson_line_len(item);
+        if i > start && bytes + len > max_bytes {
+            chunks.push((start..i, bytes));
+            (start, bytes) = (i, 0);
+        }
+        bytes += len;
+    }
+    if start < items.len() {
+        chunks.push((start..items.len(), bytes));
+    }
+    chunks
+}
+
+// Emits `sync_progress` as lines are pulled off the stream by the HTTP client,
+// so `sent` tracks what has actually been handed to the connection. `offset`
+// and `total` place this batch within the whole sync. The body is paced to
+// the upload rate limit.
+fn ndjson_body(
+    items: &[SyncPayloadItem],
+    offset: usize,
+    total: usize,
+    compress: bool,
+    cancel: &tokio_util::sync::CancellationToken,
+    app: &tauri::AppHandle,
+) -> reqwest::Body {
+    let lines: Vec<Result<Bytes, io::Error>> = items
+        .iter()
+        .map(|item| match serde_json::to_string(item) {
+            Ok(line) => Ok(Bytes::from(line + "\n")),
+            Err(err) => Err(io::Error::other(err)),
+        })
+        .collect();
+    let end = offset + lines.len();
+    let app = app.clone();
+    let cancel = cancel.clone();
+    let body = stream::iter(lines).enumerate().map(move |(i, line)| {
+        // an error rather than an early end, so the server never sees a
+        // truncated body as a complete one
+        if cancel.is_cancelled() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "sync cancelled"));
+        }
+        let sent = offset + i + 1;
+        if sent % SYNC_PROGRESS_EVERY == 0 || sent == end {
+            let _ = app.emit(
+                "sync_progress",
+                serde_json::json!({ "sent": sent, "total": total }),
+            );
+        }
+        line
+    });
+    if !compress {
+        return reqwest::Body::wrap_stream(bandwidth::limit(body));
+    }
+    // compress on the fly so the gzipped body is never held in memory whole
+    let reader = tokio_util::io::StreamReader::new(body);
+    let encoder = async_compression::tokio::bufread::GzipEncoder::new(reader);
+    reqwest::Body::wrap_stream(bandwidth::limit(tokio_util::io::ReaderStream::new(encoder)))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn sync_batch(
+    app: &tauri::AppHandle,
+    url: &str,
+    items: &[SyncPayloadItem],
+    offset: usize,
+    total: usize,
+    max_retries: Option<usize>,
+    compress: bool,
+    opts: &http::RequestOptions,
+) -> Result<SyncResult, TauraError> {
+    // the body is rebuilt per attempt so retries can resend it
+    let send = send_with_retry(app, max_retries, |client, token| {
+        let req = opts
+            .apply(client.post(url))
+            .bearer_auth(token)
+            .header("Content-Type", "application/x-ndjson");
+        let req = if compress {
+            req.header("Content-Encoding", "gzip")
+        } else {
+            req
+        };
+        req.body(ndjson_body(
+            items,
+            offset,
+            total,
+            compress,
+            opts.cancel_token(),
+            app,
+        ))
+    });
+    opts.run(async {
+        let sent = send.await?;
+        let status = sent.resp.status();
+        if !status.is_success() {
+            return Err(TauraError::server(
+                status,
+                format!(
+                    "failed on attempt {}/{}: {}",
+                    sent.attempt, sent.max_attempts, status
+                ),
+            ));
+        }
+        let mut part = sent.resp.json::<SyncResult>().await?;
+        part.retries = (sent.attempt > 1).then(|| sent.attempt - 1);
+        Ok(part)
+    })
+    .await
+}
+
+const DEFAULT_INLINE_MAX_BYTES: u64 = 256 * 1024;
+
+fn local_path(uri: &str) -> std::path::PathBuf {
+    if uri.starts_with("file://") {
+        if let Some(p) = tauri::Url::parse(uri)
+            .ok()
+            .and_then(|u| u.to_file_path().ok())
+        {
+            return p;
+        }
+    }
+    std::path::PathBuf::from(uri)
+}
+
+// Fills `bytes_b64` for files no larger than `max_bytes` so the server can
+// embed them without filesystem access. Items that already carry bytes or
+// are too large are left alone; unreadable ones are reported, not fatal.
+fn inline_file_bytes(items: &mut [SyncPayloadItem], max_bytes: u64) -> Vec<SyncErrorItem> {
+    use base64::Engine;
+    use std::io::Read;
+    let mut errors = Vec::new();
+    // a page's bytes are its rendered image, never the whole PDF
+    for item in items
+        .iter_mut()
+        .filter(|i| i.bytes_b64.is_none() && i.page.is_none())
+    {
+        let path = local_path(item.uri.trim());
+        let read = std::fs::File::open(&path).and_then(|file| {
+            if file.metadata()?.len() > max_bytes {
+                return Ok(None);
+            }
+            // the file may have grown since the size check
+            let mut buf = Vec::new();
+            file.take(max_bytes + 1).read_to_end(&mut buf)?;
+            Ok((buf.len() as u64 <= max_bytes).then_some(buf))
         });
+        match read {
+            Ok(Some(buf)) => {
+                item.bytes_b64 = Some(base64::engine::general_purpose::STANDARD.encode(buf));
+            }
+            Ok(None) => {}
+            Err(err) => errors.push(SyncErrorItem {
+                uri: item.uri.clone(),
+                error: err.to_string(),
+            }),
+        }
+    }
+    errors
+}
+
+// Fills `frames_b64` for videos. A video ffmpeg can't sample is reported and
+// still synced as metadata.
+fn attach_video_frames(
+    items: &mut [SyncPayloadItem],
+    sampling: &ffmpeg::FrameSampling,
+) -> Vec<SyncErrorItem> {
+    use base64::Engine;
+    let mut errors = Vec::new();
+    for item in items
+        .iter_mut()
+        .filter(|i| i.modality == "video" && i.frames_b64.is_none())
+    {
+        match ffmpeg::sample_frames(&local_path(item.uri.trim()), sampling) {
+            Ok(frames) => {
+                item.frames_b64 = Some(
+                    frames
+                        .iter()
+                        .map(|f| base64::engine::general_purpose::STANDARD.encode(f))
+                        .collect(),
+                );
+            }
+            Err(err) => errors.push(SyncErrorItem {
+                uri: item.uri.clone(),
+                error: format!("video frames: {}", err),
+            }),
+        }
+    }
+    errors
+}
+
+// `pdf_pages::split_pages` off the async runtime; counting pages reads files.
+async fn split_pdf_pages(
+    items: Vec<SyncPayloadItem>,
+    split: pdf_pages::PageSplit,
+) -> Result<Vec<SyncPayloadItem>, TauraError> {
+    let split = tauri::async_runtime::spawn_blocking(move || pdf_pages::split_pages(items, &split))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(split)
+}
+
+// What `prepare_batch` adds to the items of a batch before it is sent. Kept
+// with queued items so a flush sends them the way the sync would have.
+#[derive(Clone, Copy, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+struct BatchExtras {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inline_limit: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    video_frames: Option<ffmpeg::FrameSampling>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pdf_pages: Option<pdf_pages::PageSplit>,
+}
+
+impl BatchExtras {
+    fn any(&self) -> bool {
+        self.inline_limit.is_some() || self.video_frames.is_some() || self.pdf_pages.is_some()
+    }
+}
+
+// `inline_file_bytes`, `attach_video_frames` and `pdf_pages::fill_pages`.
+// Blocking; reads and renders files.
+fn prepare_items(items: &mut [SyncPayloadItem], extras: BatchExtras) -> Vec<SyncErrorItem> {
+    let mut errors = Vec::new();
+    // pages first: their rendered image takes the place of the PDF's bytes
+    if let Some(split) = extras.pdf_pages {
+        errors.extend(pdf_pages::fill_pages(items, &split));
+    }
+    if let Some(max_bytes) = extras.inline_limit {
+        errors.extend(inline_file_bytes(items, max_bytes));
+    }
+    if let Some(sampling) = extras.video_frames {
+        errors.extend(attach_video_frames(items, &sampling));
+    }
+    errors
+}
+
+// `prepare_items` on a copy of one batch, off the async runtime.
+async fn prepare_batch(
+    batch: &[SyncPayloadItem],
+    extras: BatchExtras,
+) -> Result<(Vec<SyncPayloadItem>, Vec<SyncErrorItem>), TauraError> {
+    let mut owned = batch.to_vec();
+    let inlined = tauri::async_runtime::spawn_blocking(move || {
+        let errors = prepare_items(&mut owned, extras);
+        (owned, errors)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(inlined)
+}
+
+// Mirrors a batch outcome into the local catalog.
+async fn record_sync_status(app: &tauri::AppHandle, items: &[SyncPayloadItem], status: SyncStatus) {
+    // pages of one PDF count toward the PDF's entry
+    let mut paths: Vec<String> = items
+        .iter()
+        .map(|i| match i.page {
+            Some(_) => pdf_pages::source_path(i).to_string_lossy().to_string(),
+            None => i.uri.clone(),
+        })
+        .collect();
+    paths.dedup();
+    if let Err(err) = catalog::mark_sync_status(app, paths, status).await {
+        log::warn!("catalog sync status not updated: {}", err);
     }
+}
 
-    let url = format!("{}/sync/stream", trimmed);
-    let client = reqwest::Client::new();
-
-    let stream =
-        stream::iter(
-            payload
-                .items
-                .into_iter()
-                .map(|item| match serde_json::to_string(&item) {
-                    Ok(line) => Ok::<Bytes, io::Error>(Bytes::from(line + "\n")),
-                    Err(err) => Err(io::Error::new(io::ErrorKind::Other, err)),
-                }),
-        );
+// Items sent without a user_id belong to the signed-in active account.
+fn tag_active_user(app: &tauri::AppHandle, items: &mut [SyncPayloadItem]) {
+    if items.iter().all(|i| !i.user_id.is_empty()) {
+        return;
+    }
+    if let Some(user_id) = oauth::active_user_id(app) {
+        for item in items.iter_mut().filter(|i| i.user_id.is_empty()) {
+            item.user_id = user_id.clone();
+        }
+    }
+}
 
-    let body = reqwest::Body::wrap_stream(stream);
+// Rough upload size of `items`. Pages split from one PDF are counted as
+// that PDF's size once, not once per page.
+fn estimate_upload_bytes(items: &[SyncPayloadItem]) -> u64 {
+    let mut seen = HashSet::new();
+    items
+        .iter()
+        .map(|item| match &item.bytes_b64 {
+            // inlined bytes are sent as-is; base64 is 4 chars per 3 bytes
+            Some(b64) => (b64.len() as u64 / 4) * 3,
+            None => {
+                let path = pdf_pages::source_path(item);
+                if item.page.is_some() && !seen.insert(path.clone()) {
+                    return 0;
+                }
+                std::fs::metadata(&path).map_or(0, |md| md.len())
+            }
+        })
+        .sum()
+}
 
-    let resp = client
-        .post(url)
-        .header("Content-Type", "application/x-ndjson")
-        .body(body)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-    if !resp.status().is_success() {
-        return Err(format!("sync failed: {}", resp.status()));
-    }
-    let result = resp.json::<SyncResult>().await.map_err(|e| e.to_string())?;
+// Probes which items the server is missing and sizes them, without opening
+// /sync/stream.
+async fn dry_run_summary(
+    server_url: String,
+    payload: SyncPayload,
+    max_retries: Option<usize>,
+    opts: &http::RequestOptions,
+    app: tauri::AppHandle,
+) -> Result<SyncResult, TauraError> {
+    let requested = payload.items.len();
+    let missing = filter_missing(&server_url, payload, None, max_retries, opts, &app).await?;
+    let would_upload = missing.len();
+    let estimated_bytes =
+        tauri::async_runtime::spawn_blocking(move || estimate_upload_bytes(&missing))
+            .await
+            .map_err(|e| e.to_string())?;
+    let mut result = SyncResult::empty();
+    result.dry_run = Some(DryRunSummary {
+        would_upload,
+        estimated_bytes,
+        already_indexed: requested - would_upload,
+    });
     Ok(result)
 }
 
 #[tauri::command]
-async fn filter_indexed(
+#[allow(clippy::too_many_arguments)]
+async fn sync_index(
     server_url: String,
-    payload: SyncPayload,
-) -> Result<Vec<SyncPayloadItem>, String> {
+    mut payload: SyncPayload,
+    max_retries: Option<usize>,
+    batch_size: Option<usize>,
+    compress: Option<bool>,
+    inline_small_files: Option<bool>,
+    inline_max_bytes: Option<u64>,
+    chunk_max_bytes: Option<u64>,
+    video_frames: Option<ffmpeg::FrameSampling>,
+    pdf_pages: Option<pdf_pages::PageSplit>,
+    dry_run: Option<bool>,
+    headers: Option<HashMap<String, String>>,
+    timeout_secs: Option<u64>,
+    op_id: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<SyncResult, TauraError> {
     if server_url.is_empty() {
-        return Err("server_url empty".into());
-    }
-    if payload.items.is_empty() {
-        return Ok(Vec::new());
+        return Err(TauraError::invalid_input("server_url empty"));
     }
     let trimmed = server_url.trim_end_matches('/');
-    let first_user = payload.items[0].user_id.clone();
-    if first_user.is_empty() {
-        return Ok(payload.items);
+    if payload.items.is_empty() {
+        return Ok(SyncResult::empty());
     }
-    if payload
-        .items
-        .iter()
-        .any(|item| item.user_id != first_user)
-    {
-        return Err("mixed user ids unsupported".into());
+    let op = operation::Operation::register(op_id)?;
+    let limits = settings::load(&app).upload_limits;
+    let max_retries = max_retries.or(limits.max_retries);
+    tag_active_user(&app, &mut payload.items);
+    if let Some(split) = pdf_pages {
+        payload.items = split_pdf_pages(payload.items, split).await?;
     }
-    #[derive(serde::Serialize)]
-    struct MissingRequestItem {
-        uri: String,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        ts: Option<String>,
+    if dry_run.unwrap_or(false) {
+        let opts = http::RequestOptions::new(headers, timeout_secs, http::PROBE_TIMEOUT)?
+            .cancellable(op.token());
+        return dry_run_summary(server_url, payload, max_retries, &opts, app).await;
     }
+    let opts = http::RequestOptions::new(headers, timeout_secs, http::STREAM_TIMEOUT)?
+        .cancellable(op.token());
 
-    fn parse_timestamp(ts: &str) -> Option<DateTime<Utc>> {
-        chrono::DateTime::parse_from_rfc3339(ts)
-            .map(|dt| dt.with_timezone(&Utc))
-            .ok()
+    let url = format!("{}/sync/stream", trimmed);
+    let batch_size = batch_size
+        .or(limits.batch_size)
+        .unwrap_or(DEFAULT_SYNC_BATCH)
+        .max(1);
+    let chunk_max_bytes = chunk_max_bytes
+        .or(limits.chunk_max_bytes)
+        .unwrap_or(DEFAULT_CHUNK_MAX_BYTES)
+        .max(1);
+    let total = payload.items.len();
+    let mut chunk_count = 0;
+    let mut bytes_sent: u64 = 0;
+    let mut result = SyncResult::empty();
+    let mut failed = Vec::new();
+    // kept whole so an all-failed sync reports why (auth, network, server)
+    let mut first_error: Option<TauraError> = None;
+    let extras = BatchExtras {
+        inline_limit: inline_small_files
+            .or(limits.inline_small_files)
+            .unwrap_or(false)
+            .then(|| {
+                inline_max_bytes
+                    .or(limits.inline_max_bytes)
+                    .unwrap_or(DEFAULT_INLINE_MAX_BYTES)
+            }),
+        video_frames,
+        pdf_pages,
+    };
+    let mut queued = 0;
+    // a failed chunk doesn't abort the rest; the caller gets partial results
+    'batches: for (index, batch) in payload.items.chunks(batch_size).enumerate() {
+        let offset = index * batch_size;
+        // inline one batch at a time so at most one batch of bytes is in memory
+        let mut inlined = None;
+        if extras.any() && !sync_queue::is_paused() {
+            let (owned, read_errors) = prepare_batch(batch, extras).await?;
+            if !read_errors.is_empty() {
+                result
+                    .read_errors
+                    .get_or_insert_with(Vec::new)
+                    .extend(read_errors);
+            }
+            inlined = Some(owned);
+        }
+        let prepared = inlined.as_deref().unwrap_or(batch);
+        for (range, bytes) in byte_chunks(prepared, chunk_max_bytes) {
+            if opts.is_cancelled() {
+                return Err(TauraError::cancelled("sync cancelled"));
+            }
+            let start = offset + range.start;
+            if sync_queue::is_paused() {
+                let rest = &payload.items[start..];
+                sync_queue::hold_for_resume(&app, trimmed, rest, extras).await?;
+                queued += rest.len();
+                break 'batches;
+            }
+            // spooled as given, without inlined bytes
+            let chunk = &batch[range.clone()];
+            let sending = &prepared[range];
+            let synced = sync_batch(
+                &app,
+                &url,
+                sending,
+                start,
+                total,
+                max_retries,
+                compress.unwrap_or(false),
+                &opts,
+            )
+            .await;
+            match synced {
+                Ok(part) => {
+                    record_sync_status(&app, sending, SyncStatus::Synced).await;
+                    bytes_sent += bytes;
+                    result.absorb(part)
+                }
+                // abandoning the sync abandons its partial results too
+                Err(error @ TauraError::Cancelled { .. }) => return Err(error),
+                Err(error) => {
+                    log::warn!("sync chunk {} {}", chunk_count + 1, error);
+                    record_sync_status(&app, sending, SyncStatus::Failed).await;
+                    // resending later only helps when the failure may clear up
+                    let held = error.is_retryable()
+                        && match sync_queue::hold_for_resume(&app, trimmed, chunk, extras).await {
+                            Ok(()) => true,
+                            Err(err) => {
+                                log::warn!("could not queue failed chunk: {}", err);
+                                false
+                            }
+                        };
+                    if held {
+                        queued += chunk.len();
+                    } else {
+                        failed.push(SyncBatchError {
+                            index: chunk_count,
+                            items: chunk.len(),
+                            error: error.to_string(),
+                        });
+                    }
+                    first_error.get_or_insert(error);
+                }
+            }
+            chunk_count += 1;
+            // what the server confirmed so far; `sync_progress` only counts
+            // lines handed to the connection
+            let _ = app.emit(
+                "sync_chunk",
+                serde_json::json!({
+                  "confirmed": start + chunk.len(),
+                  "total": total,
+                  "bytes_sent": bytes_sent,
+                  "upserted": result.upserted,
+                  "chunk": chunk_count,
+                  "failed_chunks": failed.len(),
+                }),
+            );
+        }
+    }
+    if queued > 0 {
+        result.queued = Some(queued);
+    }
+    if chunk_count > 0 && failed.len() == chunk_count {
+        if let Some(error) = first_error {
+            return Err(error.context(&format!("sync failed: batch {}", failed[0].index)));
+        }
+    }
+    if !failed.is_empty() {
+        result.failed_batches = Some(failed);
     }
+    Ok(result)
+}
+
+const DEFAULT_MISSING_CHUNK: usize = 2000;
+
+#[derive(serde::Serialize)]
+struct MissingRequestItem {
+    uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ts: Option<String>,
+}
+
+fn parse_timestamp(ts: &str) -> Option<DateTime<Utc>> {
+    chrono::DateTime::parse_from_rfc3339(ts)
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok()
+}
 
+// Collapses duplicate URIs, keeping the newest (or only non-empty) timestamp.
+fn dedupe_missing_items<'a>(
+    payload_items: impl IntoIterator<Item = &'a SyncPayloadItem>,
+) -> Vec<MissingRequestItem> {
     let mut dedupe: HashMap<String, (usize, Option<DateTime<Utc>>)> = HashMap::new();
     let mut items: Vec<MissingRequestItem> = Vec::new();
-    for item in &payload.items {
+    for item in payload_items {
         let trimmed_uri = item.uri.trim();
         if trimmed_uri.is_empty() {
             continue;
@@ -396,7 +1953,7 @@ async fn filter_indexed(
             }
             if should_replace {
                 items[*index].ts.clone_from(&ts_value);
-                *existing_ts = parsed_ts.clone();
+                *existing_ts = parsed_ts;
             } else if items[*index].ts.is_none() && ts_value.is_some() {
                 items[*index].ts.clone_from(&ts_value);
             }
@@ -408,14 +1965,23 @@ async fn filter_indexed(
             ts: ts_value,
         });
     }
-    if items.is_empty() {
-        return Ok(payload.items);
-    }
+    items
+}
 
+// Asks the server which of one user's URIs it doesn't have yet.
+async fn probe_missing(
+    app: &tauri::AppHandle,
+    url: &str,
+    user_id: &str,
+    items: &[MissingRequestItem],
+    chunk_size: usize,
+    max_retries: Option<usize>,
+    opts: &http::RequestOptions,
+) -> Result<HashSet<String>, TauraError> {
     #[derive(serde::Serialize)]
-    struct MissingRequest {
-        user_id: String,
-        items: Vec<MissingRequestItem>,
+    struct MissingRequest<'a> {
+        user_id: &'a str,
+        items: &'a [MissingRequestItem],
     }
 
     #[derive(serde::Deserialize)]
@@ -423,34 +1989,108 @@ async fn filter_indexed(
         missing: Vec<String>,
     }
 
-    let request = MissingRequest {
-        user_id: first_user.clone(),
-        items,
-    };
+    let mut missing_set: HashSet<String> = HashSet::new();
+    // probe in chunks so a large library doesn't produce one oversized body
+    for chunk in items.chunks(chunk_size) {
+        let request = MissingRequest {
+            user_id,
+            items: chunk,
+        };
+        let missing = opts
+            .run(async {
+                let sent = send_with_retry(app, max_retries, |client, token| {
+                    opts.apply(client.post(url))
+                        .bearer_auth(token)
+                        .json(&request)
+                })
+                .await?;
+                let status = sent.resp.status();
+                if !status.is_success() {
+                    return Err(TauraError::server(
+                        status,
+                        format!(
+                            "missing probe failed on attempt {}/{}: {}",
+                            sent.attempt, sent.max_attempts, status
+                        ),
+                    ));
+                }
+                Ok(sent.resp.json::<MissingResponse>().await?)
+            })
+            .await?;
+        missing_set.extend(missing.missing);
+    }
+    Ok(missing_set)
+}
 
-    let url = format!("{}/sync/missing", trimmed);
-    let client = reqwest::Client::new();
-    let resp = client
-        .post(url)
-        .json(&request)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-    if !resp.status().is_success() {
-        return Err(format!("missing probe failed: {}", resp.status()));
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn filter_indexed(
+    server_url: String,
+    payload: SyncPayload,
+    chunk_size: Option<usize>,
+    max_retries: Option<usize>,
+    headers: Option<HashMap<String, String>>,
+    timeout_secs: Option<u64>,
+    op_id: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<Vec<SyncPayloadItem>, TauraError> {
+    if server_url.is_empty() {
+        return Err(TauraError::invalid_input("server_url empty"));
     }
-    let missing = resp
-        .json::<MissingResponse>()
-        .await
-        .map_err(|e| e.to_string())?;
-    if missing.missing.is_empty() {
+    let op = operation::Operation::register(op_id)?;
+    let opts = http::RequestOptions::new(headers, timeout_secs, http::PROBE_TIMEOUT)?
+        .cancellable(op.token());
+    let max_retries = max_retries.or(settings::load(&app).upload_limits.max_retries);
+    filter_missing(&server_url, payload, chunk_size, max_retries, &opts, &app).await
+}
+
+async fn filter_missing(
+    server_url: &str,
+    mut payload: SyncPayload,
+    chunk_size: Option<usize>,
+    max_retries: Option<usize>,
+    opts: &http::RequestOptions,
+    app: &tauri::AppHandle,
+) -> Result<Vec<SyncPayloadItem>, TauraError> {
+    if payload.items.is_empty() {
         return Ok(Vec::new());
     }
-    let missing_set: HashSet<String> = missing.missing.into_iter().collect();
+    tag_active_user(app, &mut payload.items);
+    let trimmed = server_url.trim_end_matches('/');
+    let url = format!("{}/sync/missing", trimmed);
+    let chunk_size = chunk_size.unwrap_or(DEFAULT_MISSING_CHUNK).max(1);
+
+    // one probe per account; items without a user_id pass through untouched
+    let mut groups: Vec<(&str, Vec<&SyncPayloadItem>)> = Vec::new();
+    for item in payload.items.iter().filter(|i| !i.user_id.is_empty()) {
+        match groups.iter_mut().find(|(user, _)| *user == item.user_id) {
+            Some((_, members)) => members.push(item),
+            None => groups.push((&item.user_id, vec![item])),
+        }
+    }
+    // None means the group had no probeable URIs, so all of it passes through
+    let mut missing_by_user: HashMap<String, Option<HashSet<String>>> = HashMap::new();
+    for (user_id, members) in groups {
+        let items = dedupe_missing_items(members);
+        let missing = if items.is_empty() {
+            None
+        } else {
+            Some(probe_missing(app, &url, user_id, &items, chunk_size, max_retries, opts).await?)
+        };
+        missing_by_user.insert(user_id.to_string(), missing);
+    }
+
     let filtered: Vec<SyncPayloadItem> = payload
         .items
         .into_iter()
         .filter(|item| {
+            let Some(Some(missing_set)) = missing_by_user.get(&item.user_id) else {
+                return true;
+            };
+            // nothing missing for this user means nothing of theirs to upload
+            if missing_set.is_empty() {
+                return false;
+            }
             if item.uri.trim().is_empty() {
                 return true;
             }
@@ -460,12 +2100,348 @@ async fn filter_indexed(
     Ok(filtered)
 }
 
+const DEFAULT_PIPELINE_CONCURRENCY: usize = 2;
+const MAX_PIPELINE_CONCURRENCY: usize = 8;
+
+// What became of one chunk in `sync_missing`.
+struct PipelineChunk {
+    skipped: usize,
+    synced: Option<SyncResult>,
+    read_errors: Vec<SyncErrorItem>,
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn sync_missing_chunk(
+    app: &tauri::AppHandle,
+    server_url: &str,
+    stream_url: &str,
+    chunk: &[SyncPayloadItem],
+    offset: usize,
+    total: usize,
+    extras: BatchExtras,
+    max_retries: Option<usize>,
+    compress: bool,
+    probe_opts: &http::RequestOptions,
+    stream_opts: &http::RequestOptions,
+) -> Result<PipelineChunk, TauraError> {
+    let payload = SyncPayload {
+        items: chunk.to_vec(),
+    };
+    let missing = filter_missing(server_url, payload, None, max_retries, probe_opts, app).await?;
+    let mut outcome = PipelineChunk {
+        skipped: chunk.len() - missing.len(),
+        synced: None,
+        read_errors: Vec::new(),
+    };
+    if missing.is_empty() {
+        record_sync_status(app, chunk, SyncStatus::Synced).await;
+        return Ok(outcome);
+    }
+    let missing = if extras.any() {
+        let (inlined, read_errors) = prepare_batch(&missing, extras).await?;
+        outcome.read_errors = read_errors;
+        inlined
+    } else {
+        missing
+    };
+    let synced = sync_batch(
+        app,
+        stream_url,
+        &missing,
+        offset,
+        total,
+        max_retries,
+        compress,
+        stream_opts,
+    )
+    .await;
+    let synced = match synced {
+        Ok(synced) => synced,
+        Err(error) => {
+            if !matches!(error, TauraError::Cancelled { .. }) {
+                record_sync_status(app, &missing, SyncStatus::Failed).await;
+            }
+            return Err(error);
+        }
+    };
+    // the server already had everything this chunk didn't upload
+    record_sync_status(app, chunk, SyncStatus::Synced).await;
+    outcome.synced = Some(synced);
+    Ok(outcome)
+}
+
+/// `filter_indexed` and `sync_index` fused: each chunk is probed against
+/// `/sync/missing` and its missing items streamed straight away, so uploads
+/// start with the first probe instead of after the whole library. Up to
+/// `concurrency` chunks are in flight at once; with 1 they upload in payload
+/// order, otherwise they may finish out of order. `sync_progress` reports
+/// payload positions, so already-indexed items are skipped over.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn sync_missing(
+    server_url: String,
+    mut payload: SyncPayload,
+    chunk_size: Option<usize>,
+    concurrency: Option<usize>,
+    max_retries: Option<usize>,
+    compress: Option<bool>,
+    inline_small_files: Option<bool>,
+    inline_max_bytes: Option<u64>,
+    video_frames: Option<ffmpeg::FrameSampling>,
+    pdf_pages: Option<pdf_pages::PageSplit>,
+    headers: Option<HashMap<String, String>>,
+    timeout_secs: Option<u64>,
+    op_id: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<SyncResult, TauraError> {
+    if server_url.is_empty() {
+        return Err(TauraError::invalid_input("server_url empty"));
+    }
+    if payload.items.is_empty() {
+        return Ok(SyncResult::empty());
+    }
+    let op = operation::Operation::register(op_id)?;
+    let limits = settings::load(&app).upload_limits;
+    let max_retries = max_retries.or(limits.max_retries);
+    tag_active_user(&app, &mut payload.items);
+    if let Some(split) = pdf_pages {
+        payload.items = split_pdf_pages(payload.items, split).await?;
+    }
+    let probe_opts = http::RequestOptions::new(headers.clone(), timeout_secs, http::PROBE_TIMEOUT)?
+        .cancellable(op.token());
+    let stream_opts = http::RequestOptions::new(headers, timeout_secs, http::STREAM_TIMEOUT)?
+        .cancellable(op.token());
+
+    let stream_url = format!("{}/sync/stream", server_url.trim_end_matches('/'));
+    let chunk_size = chunk_size
+        .or(limits.batch_size)
+        .unwrap_or(DEFAULT_SYNC_BATCH)
+        .max(1);
+    let concurrency = concurrency
+        .unwrap_or(DEFAULT_PIPELINE_CONCURRENCY)
+        .clamp(1, MAX_PIPELINE_CONCURRENCY);
+    let total = payload.items.len();
+    let chunk_count = total.div_ceil(chunk_size);
+    let extras = BatchExtras {
+        inline_limit: inline_small_files
+            .or(limits.inline_small_files)
+            .unwrap_or(false)
+            .then(|| {
+                inline_max_bytes
+                    .or(limits.inline_max_bytes)
+                    .unwrap_or(DEFAULT_INLINE_MAX_BYTES)
+            }),
+        video_frames,
+        pdf_pages,
+    };
+    let compress = compress.unwrap_or(false);
+
+    // FuturesOrdered yields in payload order, so with concurrency 1 nothing
+    // can overtake an earlier chunk
+    let mut pending = stream::FuturesOrdered::new();
+    let mut queued = payload.items.chunks(chunk_size).enumerate();
+    let (app, server_url, stream_url) = (&app, &server_url, &stream_url);
+    let (probe_opts, stream_opts) = (&probe_opts, &stream_opts);
+    let mut result = SyncResult::empty();
+    let mut already_indexed = 0;
+    let mut failed = Vec::new();
+    let mut first_error: Option<TauraError> = None;
+    // a failed chunk doesn't abort the rest; the caller gets partial results
+    loop {
+        while pending.len() < concurrency {
+            let Some((index, chunk)) = queued.next() else {
+                break;
+            };
+            pending.push_back(async move {
+                let outcome = sync_missing_chunk(
+                    app,
+                    server_url,
+                    stream_url,
+                    chunk,
+                    index * chunk_size,
+                    total,
+                    extras,
+                    max_retries,
+                    compress,
+                    probe_opts,
+                    stream_opts,
+                )
+                .await;
+                (index, chunk.len(), outcome)
+            });
+        }
+        let Some((index, items, outcome)) = pending.next().await else {
+            break;
+        };
+        match outcome {
+            Ok(chunk) => {
+                already_indexed += chunk.skipped;
+                if !chunk.read_errors.is_empty() {
+                    add_errors(&mut result.read_errors, Some(chunk.read_errors));
+                }
+                if let Some(synced) = chunk.synced {
+                    result.absorb(synced);
+                }
+            }
+            // abandoning the sync abandons its partial results too
+            Err(error @ TauraError::Cancelled { .. }) => return Err(error),
+            Err(error) => {
+                log::warn!("sync chunk {}/{} {}", index + 1, chunk_count, error);
+                failed.push(SyncBatchError {
+                    index,
+                    items,
+                    error: error.to_string(),
+                });
+                first_error.get_or_insert(error);
+            }
+        }
+    }
+    if failed.len() == chunk_count {
+        if let Some(error) = first_error {
+            return Err(error.context(&format!("sync failed: chunk {}", failed[0].index)));
+        }
+    }
+    if !failed.is_empty() {
+        result.failed_batches = Some(failed);
+    }
+    result.already_indexed = Some(already_indexed);
+    Ok(result)
+}
+
+const DEFAULT_DELETE_CHUNK: usize = 500;
+
+#[derive(serde::Serialize, Debug, Clone)]
+struct DeleteResult {
+    deleted: usize,
+    /// URIs the server had no record of.
+    not_found: Vec<String>,
+    /// URIs whose chunk could not be deleted.
+    failed: Vec<SyncErrorItem>,
+}
+
+/// Removes items for `uris` from the server, in chunks, for `user_id` or the
+/// active account. A PDF's URI also removes the pages `pdf_pages` split from
+/// it. A failed chunk is reported per URI and doesn't stop the rest.
+/// `headers`, `timeout_secs` and `op_id` work as for `filter_indexed`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn delete_indexed(
+    server_url: String,
+    uris: Vec<String>,
+    user_id: Option<String>,
+    chunk_size: Option<usize>,
+    max_retries: Option<usize>,
+    headers: Option<HashMap<String, String>>,
+    timeout_secs: Option<u64>,
+    op_id: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<DeleteResult, TauraError> {
+    if server_url.is_empty() {
+        return Err(TauraError::invalid_input("server_url empty"));
+    }
+    let mut result = DeleteResult {
+        deleted: 0,
+        not_found: Vec::new(),
+        failed: Vec::new(),
+    };
+    let mut seen = HashSet::new();
+    let uris: Vec<String> = uris
+        .iter()
+        .map(|u| u.trim().to_string())
+        .filter(|u| !u.is_empty() && seen.insert(u.clone()))
+        .collect();
+    if uris.is_empty() {
+        return Ok(result);
+    }
+    let user_id = user_id
+        .filter(|u| !u.is_empty())
+        .or_else(|| oauth::active_user_id(&app))
+        .ok_or_else(|| TauraError::not_authenticated("not authenticated"))?;
+    let op = operation::Operation::register(op_id)?;
+    let opts = http::RequestOptions::new(headers, timeout_secs, http::PROBE_TIMEOUT)?
+        .cancellable(op.token());
+    let max_retries = max_retries.or(settings::load(&app).upload_limits.max_retries);
+    let url = format!("{}/sync/delete", server_url.trim_end_matches('/'));
+    let chunk_size = chunk_size.unwrap_or(DEFAULT_DELETE_CHUNK).max(1);
+
+    #[derive(serde::Serialize)]
+    struct DeleteRequest<'a> {
+        user_id: &'a str,
+        uris: &'a [String],
+    }
+
+    #[derive(serde::Deserialize)]
+    struct DeleteResponse {
+        deleted: Vec<String>,
+    }
+
+    let mut first_error: Option<TauraError> = None;
+    for chunk in uris.chunks(chunk_size) {
+        let request = DeleteRequest {
+            user_id: &user_id,
+            uris: chunk,
+        };
+        let response = opts
+            .run(async {
+                let sent = send_with_retry(&app, max_retries, |client, token| {
+                    opts.apply(client.post(&url))
+                        .bearer_auth(token)
+                        .json(&request)
+                })
+                .await?;
+                let status = sent.resp.status();
+                if !status.is_success() {
+                    return Err(TauraError::server(
+                        status,
+                        format!(
+                            "delete failed on attempt {}/{}: {}",
+                            sent.attempt, sent.max_attempts, status
+                        ),
+                    ));
+                }
+                Ok(sent.resp.json::<DeleteResponse>().await?)
+            })
+            .await;
+        match response {
+            Ok(body) => {
+                // a split PDF comes back as its pages
+                let deleted: HashSet<&str> = body
+                    .deleted
+                    .iter()
+                    .map(|uri| pdf_pages::base_uri(uri))
+                    .collect();
+                let (found, missing): (Vec<&String>, Vec<&String>) =
+                    chunk.iter().partition(|uri| deleted.contains(uri.as_str()));
+                result.deleted += found.len();
+                result.not_found.extend(missing.into_iter().cloned());
+            }
+            Err(error @ TauraError::Cancelled { .. }) => return Err(error),
+            Err(error) => {
+                result.failed.extend(chunk.iter().map(|uri| SyncErrorItem {
+                    uri: uri.clone(),
+                    error: error.to_string(),
+                }));
+                first_error.get_or_insert(error);
+            }
+        }
+    }
+    if result.deleted == 0 && result.not_found.is_empty() {
+        if let Some(error) = first_error {
+            return Err(error);
+        }
+    }
+    Ok(result)
+}
+
 #[tauri::command]
 async fn show_overlay(app: tauri::AppHandle) -> Result<(), String> {
     if !ensure_authenticated(&app).await? {
         return Err("not authenticated".into());
     }
     if let Some(overlay_window) = app.get_webview_window("overlay") {
+        if let Err(err) = overlay::restore_position(&app, &overlay_window) {
+            log::warn!("overlay position not restored: {}", err);
+        }
         overlay_window.show().map_err(|e| e.to_string())?;
         overlay_window.set_focus().map_err(|e| e.to_string())?;
         // Emit the toggle-overlay event to focus the input
@@ -474,15 +2450,51 @@ async fn show_overlay(app: tauri::AppHandle) -> Result<(), String> {
     Ok(())
 }
 
+// Single hide path for the toggle, Escape and focus loss.
+fn hide_overlay_window(app: &tauri::AppHandle) -> Result<(), String> {
+    if let Some(overlay_window) = app.get_webview_window("overlay") {
+        if overlay_window.is_visible().map_err(|e| e.to_string())? {
+            overlay::remember_if_moved(app);
+            overlay_window.hide().map_err(|e| e.to_string())?;
+            let _ = app.emit("overlay-hidden", ());
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn hide_overlay(app: tauri::AppHandle) -> Result<(), String> {
+    hide_overlay_window(&app)
+}
+
+/// Whether the overlay hides itself when it loses focus. On by default;
+/// turning it off keeps the overlay pinned until dismissed.
+#[tauri::command]
+async fn set_overlay_hide_on_blur(enabled: bool, app: tauri::AppHandle) -> Result<(), String> {
+    OVERLAY_HIDE_ON_BLUR.store(enabled, Ordering::SeqCst);
+    settings::update(&app, |s| s.overlay_hide_on_blur = Some(enabled))
+}
+
+/// Whether closing the main window hides it to the tray or quits the app
+/// (the default). The tray menu's Quit always exits.
+#[tauri::command]
+async fn set_close_to_tray(enabled: bool, app: tauri::AppHandle) -> Result<(), String> {
+    CLOSE_TO_TRAY.store(enabled, Ordering::SeqCst);
+    settings::update(&app, |s| s.close_to_tray = Some(enabled))
+}
+
 #[tauri::command]
 async fn toggle_overlay(app: tauri::AppHandle) -> Result<(), String> {
     if let Some(overlay_window) = app.get_webview_window("overlay") {
         if overlay_window.is_visible().map_err(|e| e.to_string())? {
-            overlay_window.hide().map_err(|e| e.to_string())?;
+            hide_overlay_window(&app)?;
         } else {
             if !ensure_authenticated(&app).await? {
                 return Err("not authenticated".into());
             }
+            if let Err(err) = overlay::restore_position(&app, &overlay_window) {
+                log::warn!("overlay position not restored: {}", err);
+            }
             overlay_window.show().map_err(|e| e.to_string())?;
             overlay_window.set_focus().map_err(|e| e.to_string())?;
             // Emit the toggle-overlay event to focus the input
@@ -504,6 +2516,24 @@ async fn ensure_authenticated(app: &tauri::AppHandle) -> Result<bool, String> {
     Ok(false)
 }
 
+// Keeps the process, the global shortcut and background sync alive when the
+// user closes the main window, as long as `close_to_tray` is on.
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+fn hide_main_on_close(app: &tauri::AppHandle) {
+    let Some(main_window) = app.get_webview_window("main") else {
+        return;
+    };
+    let window = main_window.clone();
+    main_window.on_window_event(move |event| {
+        if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+            if CLOSE_TO_TRAY.load(Ordering::SeqCst) {
+                api.prevent_close();
+                let _ = window.hide();
+            }
+        }
+    });
+}
+
 #[tauri::command]
 async fn show_main_window(app: tauri::AppHandle) -> Result<(), String> {
     if let Some(main_window) = app.get_webview_window("main") {
@@ -513,15 +2543,53 @@ async fn show_main_window(app: tauri::AppHandle) -> Result<(), String> {
     Ok(())
 }
 
-#[tauri::command]
-async fn open_file(path: String) -> Result<(), String> {
-    if path.is_empty() {
+// Characters cmd.exe treats specially even inside `start "" <path>`.
+#[cfg(target_os = "windows")]
+const CMD_UNSAFE: &[char] = &['"', '&', '|', '<', '>', '^', '%', '!'];
+
+// Resolves a path handed in by the UI to an existing regular file that is
+// safe to pass to the platform opener.
+fn existing_file(path: &str) -> Result<std::path::PathBuf, String> {
+    let trimmed = path.trim();
+    if trimmed.is_empty() {
         return Err("path empty".into());
     }
+    let canonical = std::fs::canonicalize(trimmed).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => "file no longer exists".to_string(),
+        _ => format!("{}: {}", trimmed, e),
+    })?;
+    if !canonical.is_file() {
+        return Err("not a file".into());
+    }
+    #[cfg(target_os = "windows")]
+    let canonical = {
+        // canonicalize yields a verbatim \\?\ path that `start` doesn't accept
+        let shown = canonical.to_string_lossy();
+        let plain = match shown.strip_prefix(r"\\?\UNC\") {
+            Some(unc) => format!(r"\\{}", unc),
+            None => shown.strip_prefix(r"\\?\").unwrap_or(&shown).to_string(),
+        };
+        if plain
+            .chars()
+            .any(|c| c.is_control() || CMD_UNSAFE.contains(&c))
+        {
+            return Err("path contains characters that cannot be opened safely".into());
+        }
+        std::path::PathBuf::from(plain)
+    };
+    Ok(canonical)
+}
+
+#[tauri::command]
+async fn open_file(path: String) -> Result<(), String> {
+    let path = existing_file(&path)?;
     #[cfg(target_os = "windows")]
     {
         Command::new("cmd")
-            .args(["/C", "start", "", &path])
+            .arg("/C")
+            .arg("start")
+            .arg("")
+            .arg(&path)
             .spawn()
             .map_err(|e| e.to_string())?;
     }
@@ -542,74 +2610,226 @@ async fn open_file(path: String) -> Result<(), String> {
     Ok(())
 }
 
+#[tauri::command]
+async fn reveal_in_folder(path: String) -> Result<(), String> {
+    let path = existing_file(&path)?;
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        // explorer parses its own command line; the path must follow the comma quoted
+        Command::new("explorer")
+            .raw_arg(format!("/select,\"{}\"", path.display()))
+            .spawn()
+            .map_err(|e| e.to_string())?;
+    }
+    #[cfg(target_os = "macos")]
+    {
+        Command::new("open")
+            .arg("-R")
+            .arg(&path)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+    }
+    #[cfg(target_os = "linux")]
+    {
+        let uri = tauri::Url::from_file_path(&path)
+            .map_err(|_| "invalid path".to_string())?
+            .to_string()
+            // dbus-send splits array elements on commas
+            .replace(',', "%2C");
+        let shown = Command::new("dbus-send")
+            .args([
+                "--session",
+                "--print-reply",
+                "--reply-timeout=3000",
+                "--dest=org.freedesktop.FileManager1",
+                "--type=method_call",
+                "/org/freedesktop/FileManager1",
+                "org.freedesktop.FileManager1.ShowItems",
+            ])
+            .arg(format!("array:string:{}", uri))
+            .arg("string:")
+            .output()
+            .is_ok_and(|out| out.status.success());
+        if !shown {
+            // no FileManager1 service: open the folder without selecting
+            let parent = path.parent().ok_or("file has no parent folder")?;
+            Command::new("xdg-open")
+                .arg(parent)
+                .spawn()
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
 pub fn run() {
-    tauri::Builder::default()
+    let builder = tauri::Builder::default();
+    // must be the first plugin: a second launch exits here before anything
+    // else (overlay, global shortcut) gets set up
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    let builder = builder.plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+        // on Windows and Linux a deep link launches a new process with the URL
+        // as an argument; hand it to the running instance
+        for arg in argv.iter().skip(1) {
+            oauth::handle_deep_link(arg);
+        }
+        let handle = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let _ = show_main_window(handle).await;
+        });
+    }));
+    builder
         .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_deep_link::init())
         .invoke_handler(tauri::generate_handler![
             get_default_folder,
             pick_folder,
             scan_folder,
+            incremental_scan,
+            prepare_upload_item,
+            add_library_folder,
+            remove_library_folder,
+            list_library_folders,
+            scan_all,
+            get_exclusions,
+            set_exclusions,
+            set_sync_schedule,
+            get_next_sync_time,
+            retry_failed_paths,
+            grant_folder_access,
+            get_index_stats,
+            query_index,
+            clear_index,
+            find_duplicates,
+            revoke_folder_access,
+            list_folder_access,
             stop_scan,
+            cancel_operation,
+            pause_scan,
+            resume_scan,
             set_default_throttle,
+            get_default_throttle,
+            get_settings,
+            update_settings,
+            set_upload_rate_limit,
             filter_indexed,
+            sync_missing,
+            delete_indexed,
+            search,
             sync_index,
+            queue_sync,
+            flush_sync_queue,
+            pause_sync,
+            resume_sync,
+            sync_queue_status,
+            watch_folder,
+            unwatch_folder,
             show_overlay,
             toggle_overlay,
+            hide_overlay,
+            set_overlay_hide_on_blur,
+            set_close_to_tray,
+            save_overlay_position,
+            reset_overlay_position,
             show_main_window,
             open_file,
+            reveal_in_folder,
             google_auth_start,
+            auth_start,
+            build_auth_url,
+            complete_auth,
             get_session,
+            describe_session,
+            list_sessions,
+            switch_active_session,
             logout,
             refresh_session,
-            ensure_fresh_session
+            ensure_fresh_session,
+            get_scope_status,
+            inspect_tls,
+            shift_timestamps,
+            generate_contact_sheet,
+            generate_thumbnail,
+            generate_thumbnails,
+            cancel_contact_sheet,
+            network_status,
+            get_cache_stats,
+            clear_cache,
+            ping_server,
+            get_embed_status,
+            watch_embed_status,
+            set_proxy,
+            set_auto_refresh,
+            #[cfg(not(any(target_os = "android", target_os = "ios")))]
+            shortcut::get_overlay_shortcut,
+            #[cfg(not(any(target_os = "android", target_os = "ios")))]
+            shortcut::set_overlay_shortcut
         ])
         .setup(|app| {
+            let saved = settings::load(app.handle());
+            if let Some(ms) = saved.default_throttle_ms {
+                if let Ok(mut guard) = DEFAULT_THROTTLE_VALUE.lock() {
+                    *guard = ms;
+                }
+            }
+            if let Err(err) = http::apply_proxy(http::saved_proxy(app.handle()).as_deref()) {
+                log::warn!("saved proxy not applied: {}", err);
+            }
+            bandwidth::set_limit(saved.upload_limits.max_bytes_per_sec);
+            let hide_on_blur = saved.overlay_hide_on_blur.unwrap_or(true);
+            OVERLAY_HIDE_ON_BLUR.store(hide_on_blur, Ordering::SeqCst);
+            CLOSE_TO_TRAY.store(saved.close_to_tray.unwrap_or(false), Ordering::SeqCst);
+            if let Some(overlay_window) = app.get_webview_window("overlay") {
+                let handle = app.handle().clone();
+                overlay_window.on_window_event(move |event| match event {
+                    tauri::WindowEvent::Focused(false)
+                        if OVERLAY_HIDE_ON_BLUR.load(Ordering::SeqCst) =>
+                    {
+                        let _ = hide_overlay_window(&handle);
+                    }
+                    tauri::WindowEvent::Moved(_) => overlay::mark_moved(),
+                    _ => {}
+                });
+            }
+            {
+                use tauri_plugin_deep_link::DeepLinkExt;
+                // installers register the scheme; this covers dev builds and AppImages
+                #[cfg(any(target_os = "windows", target_os = "linux"))]
+                if let Err(err) = app.deep_link().register_all() {
+                    log::warn!("deep link scheme not registered: {}", err);
+                }
+                app.deep_link().on_open_url(|event| {
+                    for url in event.urls() {
+                        oauth::handle_deep_link(url.as_str());
+                    }
+                });
+            }
+            tauri::async_runtime::spawn(oauth::run_refresh_timer(app.handle().clone()));
+            watcher::restore(app.handle());
+            tauri::async_runtime::spawn(scheduler::run_scheduler(app.handle().clone()));
+            fs_access::restore(app.handle());
             #[cfg(not(any(target_os = "android", target_os = "ios")))]
             {
                 use tauri_plugin_global_shortcut::ShortcutState;
 
-                let shortcut_str = if cfg!(target_os = "macos") {
-                    "command+shift+k"
-                } else {
-                    "ctrl+shift+k"
-                };
-
-                match tauri_plugin_global_shortcut::Builder::new()
-                    .with_shortcut(shortcut_str)
-                {
-                    Ok(builder) => {
-                        let plugin = builder
-                            .with_handler(|app_handle, _shortcut, event| {
-                                if event.state == ShortcutState::Pressed {
-                                    let handle = app_handle.clone();
-                                    tauri::async_runtime::spawn(async move {
-                                        let _ = show_overlay(handle).await;
-                                    });
-                                }
-                            })
-                            .build();
-
-                        if let Err(err) = app.handle().plugin(plugin) {
-                            if err
-                                .to_string()
-                                .contains("HotKey already registered")
-                            {
-                                log::warn!(
-                                    "Global shortcut {} already registered elsewhere; overlay toggle remains available via UI",
-                                    shortcut_str
-                                );
-                            } else {
-                                return Err(Box::new(err));
-                            }
+                // only the overlay combo is ever registered, so any press toggles it
+                let plugin = tauri_plugin_global_shortcut::Builder::new()
+                    .with_handler(|app_handle, _shortcut, event| {
+                        if event.state == ShortcutState::Pressed {
+                            let handle = app_handle.clone();
+                            tauri::async_runtime::spawn(async move {
+                                let _ = show_overlay(handle).await;
+                            });
                         }
-                    }
-                    Err(err) => {
-                        log::warn!(
-                            "Failed to configure global shortcut {}: {}",
-                            shortcut_str,
-                            err
-                        );
-                    }
+                    })
+                    .build();
+                app.handle().plugin(plugin)?;
+                shortcut::register_saved(app.handle());
+                match tray::create(app.handle()) {
+                    // a window hidden with no tray icon could never be brought back
+                    Ok(()) => hide_main_on_close(app.handle()),
+                    Err(err) => log::warn!("tray icon not created: {}", err),
                 }
             }
             if cfg!(debug_assertions) {
@@ -624,3 +2844,38 @@ pub fn run() {
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(uri: String, page: Option<u32>) -> SyncPayloadItem {
+        SyncPayloadItem {
+            user_id: String::new(),
+            modality: "pdf_page".into(),
+            uri,
+            ts: None,
+            bytes_b64: None,
+            frames_b64: None,
+            page,
+            text: None,
+        }
+    }
+
+    #[test]
+    fn dry_run_sizes_a_split_pdf_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let pdf = dir.path().join("doc.pdf");
+        std::fs::write(&pdf, vec![0u8; 1000]).unwrap();
+        let photo = dir.path().join("photo.jpg");
+        std::fs::write(&photo, vec![0u8; 300]).unwrap();
+        let pdf_uri = tauri::Url::from_file_path(&pdf).unwrap().to_string();
+        let items = vec![
+            item(format!("{}#page=1", pdf_uri), Some(1)),
+            item(format!("{}#page=2", pdf_uri), Some(2)),
+            item(format!("{}#page=3", pdf_uri), Some(3)),
+            item(photo.to_string_lossy().into_owned(), None),
+        ];
+        assert_eq!(estimate_upload_bytes(&items), 1300);
+    }
+}
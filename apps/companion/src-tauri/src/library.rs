@@ -0,0 +1,312 @@
+use crate::error::TauraError;
+use crate::fs_access::canonical_dir;
+use crate::{
+    scan_folder_with, settings, ScanControl, ScanOptions, ScanRegistration, ScanResult, SCANS,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{Emitter, Listener};
+
+// How quickly pause and stop on the library scan reach the folder being walked.
+const CONTROL_POLL: Duration = Duration::from_millis(200);
+
+/// A root of the media library, persisted across restarts.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LibraryFolder {
+    pub path: String,
+    /// RFC 3339.
+    pub added_at: String,
+}
+
+#[derive(Serialize)]
+pub struct LibraryFolderStatus {
+    #[serde(flatten)]
+    pub folder: LibraryFolder,
+    /// False while the folder is missing, e.g. on an unplugged drive.
+    pub available: bool,
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum FolderState {
+    Pending,
+    Scanning,
+    Done,
+    Cancelled,
+    Error,
+}
+
+// One folder's line in the merged `library_scan_progress` event.
+#[derive(Serialize)]
+struct FolderProgress {
+    path: String,
+    scan_id: String,
+    state: FolderState,
+    processed: u64,
+    matched: u64,
+    error_count: u64,
+    total_bytes: u64,
+}
+
+#[derive(Serialize)]
+pub struct FolderScan {
+    pub path: String,
+    /// None when the folder couldn't be scanned at all; see `error`.
+    pub result: Option<ScanResult>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct LibraryScanResult {
+    pub scan_id: String,
+    pub folders: Vec<FolderScan>,
+    /// Matched files across all folders.
+    pub count: usize,
+    pub total_bytes: u64,
+    /// True when the run was stopped; folders after the one being walked
+    /// then have no entry.
+    pub cancelled: bool,
+}
+
+fn lock<T>(m: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Roots saved before they were canonicalized are compared the same way; a
+// missing one keeps its stored path.
+fn root_dir(folder: &LibraryFolder) -> PathBuf {
+    canonical_dir(&folder.path).unwrap_or_else(|_| PathBuf::from(&folder.path))
+}
+
+fn status(folder: LibraryFolder) -> LibraryFolderStatus {
+    let available = Path::new(&folder.path).is_dir();
+    LibraryFolderStatus { folder, available }
+}
+
+fn emit_progress(app: &tauri::AppHandle, scan_id: &str, folders: &[FolderProgress], done: bool) {
+    let sum = |field: fn(&FolderProgress) -> u64| folders.iter().map(field).sum::<u64>();
+    let _ = app.emit(
+        "library_scan_progress",
+        serde_json::json!({
+            "scan_id": scan_id,
+            "processed": sum(|f| f.processed),
+            "matched": sum(|f| f.matched),
+            "error_count": sum(|f| f.error_count),
+            "total_bytes": sum(|f| f.total_bytes),
+            "folders": folders,
+            "done": done,
+        }),
+    );
+}
+
+#[tauri::command]
+pub async fn list_library_folders(
+    app: tauri::AppHandle,
+) -> Result<Vec<LibraryFolderStatus>, String> {
+    Ok(settings::load(&app)
+        .library_folders
+        .into_iter()
+        .map(status)
+        .collect())
+}
+
+/// Adds a root to the library, stored as its canonical path. Roots already
+/// inside it are dropped, since walking it covers them; a folder inside an
+/// existing root is refused.
+#[tauri::command]
+pub async fn add_library_folder(
+    path: String,
+    app: tauri::AppHandle,
+) -> Result<Vec<LibraryFolderStatus>, String> {
+    let dir = canonical_dir(&path)?;
+    let mut folders = settings::load(&app).library_folders;
+    if let Some(root) = folders.iter().find(|f| dir.starts_with(root_dir(f))) {
+        return Err(format!("already in the library under {}", root.path));
+    }
+    folders.retain(|f| !root_dir(f).starts_with(&dir));
+    folders.push(LibraryFolder {
+        path: dir.to_string_lossy().to_string(),
+        added_at: chrono::Utc::now().to_rfc3339(),
+    });
+    settings::update(&app, |s| s.library_folders = folders.clone())?;
+    Ok(folders.into_iter().map(status).collect())
+}
+
+/// Drops a root from the library. Its files stay in the catalog; use
+/// `clear_index` to forget them.
+#[tauri::command]
+pub async fn remove_library_folder(
+    path: String,
+    app: tauri::AppHandle,
+) -> Result<Vec<LibraryFolderStatus>, String> {
+    // a root that has since been deleted can still be removed by its old path
+    let dir = canonical_dir(&path).unwrap_or_else(|_| PathBuf::from(path.trim()));
+    let mut folders = settings::load(&app).library_folders;
+    let before = folders.len();
+    folders.retain(|f| f.path != path.trim() && root_dir(f) != dir);
+    if folders.len() == before {
+        return Err(format!("{} is not in the library", path));
+    }
+    settings::update(&app, |s| s.library_folders = folders.clone())?;
+    Ok(folders.into_iter().map(status).collect())
+}
+
+// Carries pause and stop on the library scan over to the folder scan that is
+// running, until `current` is cleared.
+async fn mirror_controls(library: Arc<ScanControl>, current: Arc<Mutex<Option<String>>>) {
+    loop {
+        tokio::time::sleep(CONTROL_POLL).await;
+        let Some(id) = lock(&current).clone() else {
+            return;
+        };
+        if let Some(scan) = SCANS.lock().ok().and_then(|s| s.get(&id).cloned()) {
+            if library.cancel.load(Ordering::SeqCst) {
+                scan.cancel.store(true, Ordering::SeqCst);
+            }
+            scan.paused
+                .store(library.paused.load(Ordering::SeqCst), Ordering::SeqCst);
+        }
+    }
+}
+
+/// Scans every library root in turn with `scan_folder` and the given
+/// options. Each folder runs as its own scan (`<scan_id>:<n>`) and sends its
+/// usual events; `library_scan_progress` merges them into running totals
+/// with a per-folder breakdown. `stop_scan`, `pause_scan` and `resume_scan`
+/// on `scan_id` apply to the whole run. Missing folders are reported and
+/// skipped.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn scan_all(
+    scan_id: Option<String>,
+    max_samples: Option<usize>,
+    throttle_ms: Option<u64>,
+    max_depth: Option<usize>,
+    skip_hidden: Option<bool>,
+    hash_content: Option<bool>,
+    concurrency: Option<usize>,
+    app: tauri::AppHandle,
+) -> Result<LibraryScanResult, TauraError> {
+    let folders = settings::load(&app).library_folders;
+    if folders.is_empty() {
+        return Err(TauraError::invalid_input("no library folders"));
+    }
+    let library = ScanRegistration::new(scan_id).map_err(TauraError::invalid_input)?;
+    let scan_id = library.id.clone();
+    let progress = Arc::new(Mutex::new(
+        folders
+            .iter()
+            .enumerate()
+            .map(|(n, f)| FolderProgress {
+                path: f.path.clone(),
+                scan_id: format!("{}:{}", scan_id, n),
+                state: FolderState::Pending,
+                processed: 0,
+                matched: 0,
+                error_count: 0,
+                total_bytes: 0,
+            })
+            .collect::<Vec<_>>(),
+    ));
+    emit_progress(&app, &scan_id, &lock(&progress), false);
+
+    // folder scans report through their own events; fold those in
+    let listener = {
+        let (app, scan_id, progress) = (app.clone(), scan_id.clone(), progress.clone());
+        app.clone().listen_any("scan_progress", move |event| {
+            let Ok(payload) = serde_json::from_str::<serde_json::Value>(event.payload()) else {
+                return;
+            };
+            let mut folders = lock(&progress);
+            let Some(folder) = folders
+                .iter_mut()
+                .find(|f| payload["scan_id"].as_str() == Some(f.scan_id.as_str()))
+            else {
+                return;
+            };
+            let count = |key: &str| payload[key].as_u64();
+            folder.processed = count("processed").unwrap_or(folder.processed);
+            folder.matched = count("matched").unwrap_or(folder.matched);
+            folder.error_count = count("error_count").unwrap_or(folder.error_count);
+            folder.total_bytes = count("total_bytes").unwrap_or(folder.total_bytes);
+            emit_progress(&app, &scan_id, &folders, false);
+        })
+    };
+    let current = Arc::new(Mutex::new(None::<String>));
+
+    let mut result = LibraryScanResult {
+        scan_id: scan_id.clone(),
+        folders: Vec::with_capacity(folders.len()),
+        count: 0,
+        total_bytes: 0,
+        cancelled: false,
+    };
+    for (n, folder) in folders.into_iter().enumerate() {
+        let folder_scan_id = format!("{}:{}", scan_id, n);
+        let set_state = |state| {
+            let mut progress = lock(&progress);
+            progress[n].state = state;
+            emit_progress(&app, &scan_id, &progress, false);
+        };
+        if library.control.cancel.load(Ordering::SeqCst) {
+            break;
+        }
+        if !Path::new(&folder.path).is_dir() {
+            set_state(FolderState::Error);
+            result.folders.push(FolderScan {
+                path: folder.path,
+                result: None,
+                error: Some("folder not available".into()),
+            });
+            continue;
+        }
+        set_state(FolderState::Scanning);
+        *lock(&current) = Some(folder_scan_id.clone());
+        let mirror =
+            tauri::async_runtime::spawn(mirror_controls(library.control.clone(), current.clone()));
+        let opts = ScanOptions {
+            max_samples,
+            throttle_ms,
+            max_depth,
+            scan_id: Some(folder_scan_id),
+            skip_hidden,
+            hash_content,
+            concurrency,
+            ..Default::default()
+        };
+        let scanned = scan_folder_with(folder.path.clone(), opts, app.clone()).await;
+        *lock(&current) = None;
+        let _ = mirror.await;
+        match scanned {
+            Ok(scanned) => {
+                set_state(if scanned.cancelled {
+                    FolderState::Cancelled
+                } else {
+                    FolderState::Done
+                });
+                result.count += scanned.count;
+                result.total_bytes += scanned.total_bytes;
+                result.folders.push(FolderScan {
+                    path: folder.path,
+                    result: Some(scanned),
+                    error: None,
+                });
+            }
+            Err(err) => {
+                set_state(FolderState::Error);
+                result.folders.push(FolderScan {
+                    path: folder.path,
+                    result: None,
+                    error: Some(err.to_string()),
+                });
+            }
+        }
+    }
+    app.unlisten(listener);
+    result.cancelled = library.control.cancel.load(Ordering::SeqCst);
+    emit_progress(&app, &scan_id, &lock(&progress), true);
+    Ok(result)
+}
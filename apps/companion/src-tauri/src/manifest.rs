@@ -0,0 +1,29 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// path -> (size, modified rfc3339) as seen by the last scan.
+pub type ScanManifest = HashMap<String, (u64, Option<String>)>;
+
+pub fn load_manifest(path: &Path) -> ScanManifest {
+    // a missing or corrupt manifest just means a full scan
+    fs::read(path)
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_manifest(path: &Path, manifest: &ScanManifest) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let data = serde_json::to_vec(manifest).map_err(|e| e.to_string())?;
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp = path.with_file_name(tmp_name);
+    fs::write(&tmp, data).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        e.to_string()
+    })
+}
@@ -0,0 +1,164 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+// Seconds between the MP4 epoch (1904-01-01) and the Unix epoch.
+const MP4_EPOCH_OFFSET: i64 = 2_082_844_800;
+// Guards against malformed files that would make us walk forever.
+const MAX_BOXES: usize = 256;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct VideoMeta {
+    pub duration_secs: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Unix seconds from the movie header, if it was set.
+    pub created: Option<i64>,
+}
+
+struct BoxHeader {
+    kind: [u8; 4],
+    // payload bounds within the file
+    start: u64,
+    end: u64,
+}
+
+fn read_u32(buf: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(buf.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn read_u64(buf: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(buf.get(at..at + 8)?.try_into().ok()?))
+}
+
+fn read_header(file: &mut File, pos: u64, limit: u64) -> Option<BoxHeader> {
+    if pos + 8 > limit {
+        return None;
+    }
+    file.seek(SeekFrom::Start(pos)).ok()?;
+    let mut head = [0u8; 8];
+    file.read_exact(&mut head).ok()?;
+    let size = read_u32(&head, 0)? as u64;
+    let kind: [u8; 4] = head[4..8].try_into().ok()?;
+    let (header_len, total) = match size {
+        0 => (8, limit - pos),
+        1 => {
+            let mut large = [0u8; 8];
+            file.read_exact(&mut large).ok()?;
+            (16, u64::from_be_bytes(large))
+        }
+        n => (8, n),
+    };
+    let end = pos.checked_add(total)?;
+    if total < header_len || end > limit {
+        return None;
+    }
+    Some(BoxHeader {
+        kind,
+        start: pos + header_len,
+        end,
+    })
+}
+
+// Finds the first child of `kind` between `start` and `end`.
+fn find_child(file: &mut File, start: u64, end: u64, kind: &[u8; 4]) -> Option<BoxHeader> {
+    children(file, start, end)
+        .into_iter()
+        .find(|b| &b.kind == kind)
+}
+
+fn children(file: &mut File, start: u64, end: u64) -> Vec<BoxHeader> {
+    let mut out = Vec::new();
+    let mut pos = start;
+    while out.len() < MAX_BOXES {
+        let Some(header) = read_header(file, pos, end) else {
+            break;
+        };
+        pos = header.end;
+        out.push(header);
+    }
+    out
+}
+
+fn read_payload(file: &mut File, header: &BoxHeader, max: u64) -> Option<Vec<u8>> {
+    let len = (header.end - header.start).min(max);
+    file.seek(SeekFrom::Start(header.start)).ok()?;
+    let mut buf = vec![0u8; len as usize];
+    file.read_exact(&mut buf).ok()?;
+    Some(buf)
+}
+
+fn parse_mvhd(buf: &[u8], meta: &mut VideoMeta) {
+    let (created, timescale, duration) = match buf.first() {
+        Some(1) => (read_u64(buf, 4), read_u32(buf, 20), read_u64(buf, 24)),
+        Some(0) => (
+            read_u32(buf, 4).map(u64::from),
+            read_u32(buf, 12),
+            read_u32(buf, 16).map(u64::from),
+        ),
+        _ => return,
+    };
+    if let (Some(scale), Some(d)) = (timescale, duration) {
+        // all-ones duration means "unknown"
+        if scale > 0 && d != u64::MAX && d != u32::MAX as u64 {
+            meta.duration_secs = Some(d as f64 / scale as f64);
+        }
+    }
+    meta.created = created
+        .filter(|&c| c > 0)
+        .map(|c| c as i64 - MP4_EPOCH_OFFSET);
+}
+
+// Returns display (width, height) for a track, swapping them when the
+// matrix rotates by 90 degrees as phones do for portrait clips.
+fn parse_tkhd(buf: &[u8]) -> Option<(u32, u32)> {
+    let base = match buf.first()? {
+        1 => 4 + 32,
+        0 => 4 + 20,
+        _ => return None,
+    };
+    // reserved(8) layer(2) alternate(2) volume(2) reserved(2)
+    let matrix = base + 16;
+    let width = read_u32(buf, matrix + 36)? >> 16;
+    let height = read_u32(buf, matrix + 40)? >> 16;
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let a = read_u32(buf, matrix)? as i32;
+    let b = read_u32(buf, matrix + 4)? as i32;
+    if a == 0 && b != 0 {
+        Some((height, width))
+    } else {
+        Some((width, height))
+    }
+}
+
+/// Reads duration, dimensions and creation time from an MP4/MOV header.
+/// Only box headers, `mvhd` and `tkhd` are read, so large files cost a
+/// handful of seeks. Returns None for anything that isn't an ISO BMFF file.
+pub fn read_video_meta(path: &Path) -> Option<VideoMeta> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    let moov = find_child(&mut file, 0, len, b"moov")?;
+    let mut meta = VideoMeta::default();
+    if let Some(mvhd) = find_child(&mut file, moov.start, moov.end, b"mvhd") {
+        if let Some(buf) = read_payload(&mut file, &mvhd, 128) {
+            parse_mvhd(&buf, &mut meta);
+        }
+    }
+    for trak in children(&mut file, moov.start, moov.end)
+        .into_iter()
+        .filter(|b| &b.kind == b"trak")
+    {
+        let Some(tkhd) = find_child(&mut file, trak.start, trak.end, b"tkhd") else {
+            continue;
+        };
+        // audio tracks carry zero dimensions, so the first sized track is video
+        if let Some((w, h)) = read_payload(&mut file, &tkhd, 128).and_then(|b| parse_tkhd(&b)) {
+            meta.width = Some(w);
+            meta.height = Some(h);
+            break;
+        }
+    }
+    Some(meta)
+}
@@ -0,0 +1,188 @@
+use crate::http;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+const LATENCY_SAMPLES: usize = 3;
+const PROBE_BYTES: usize = 256 * 1024;
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Serialize, Default)]
+pub struct NetworkStatus {
+    /// "wifi", "ethernet", "cellular" or "unknown".
+    pub interface: String,
+    pub interface_name: Option<String>,
+    /// None when the platform gives no reliable answer.
+    pub metered: Option<bool>,
+    pub latency_ms: Option<u64>,
+    pub upload_mbps: Option<f64>,
+    pub probe_error: Option<String>,
+}
+
+#[cfg(target_os = "linux")]
+fn detect_interface() -> (String, Option<String>, Option<bool>) {
+    // the default route is the line in /proc/net/route with a zero destination
+    let iface = std::fs::read_to_string("/proc/net/route")
+        .ok()
+        .and_then(|table| {
+            table.lines().skip(1).find_map(|line| {
+                let cols: Vec<&str> = line.split_whitespace().collect();
+                (cols.len() > 1 && cols[1] == "00000000").then(|| cols[0].to_string())
+            })
+        });
+    let Some(name) = iface else {
+        return ("unknown".into(), None, None);
+    };
+    let sys = std::path::Path::new("/sys/class/net").join(&name);
+    let uevent = std::fs::read_to_string(sys.join("uevent")).unwrap_or_default();
+    let kind = if sys.join("wireless").exists() || uevent.contains("DEVTYPE=wlan") {
+        "wifi"
+    } else if uevent.contains("DEVTYPE=wwan") || name.starts_with("wwan") {
+        "cellular"
+    } else {
+        "ethernet"
+    };
+    // cellular is the only case we can call metered without asking NetworkManager
+    let metered = (kind == "cellular").then_some(true);
+    (kind.into(), Some(name), metered)
+}
+
+#[cfg(target_os = "macos")]
+fn detect_interface() -> (String, Option<String>, Option<bool>) {
+    use std::process::Command;
+    let route = Command::new("route")
+        .args(["-n", "get", "default"])
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+        .unwrap_or_default();
+    let Some(name) = route
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("interface:"))
+        .map(|s| s.trim().to_string())
+    else {
+        return ("unknown".into(), None, None);
+    };
+    // map the BSD device (en0) back to its hardware port ("Wi-Fi", "Ethernet")
+    let ports = Command::new("networksetup")
+        .arg("-listallhardwareports")
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+        .unwrap_or_default();
+    let mut port = String::new();
+    let mut kind = "ethernet";
+    for line in ports.lines() {
+        if let Some(p) = line.strip_prefix("Hardware Port:") {
+            port = p.trim().to_lowercase();
+        } else if line.strip_prefix("Device:").map(str::trim) == Some(name.as_str()) {
+            if port.contains("wi-fi") || port.contains("airport") {
+                kind = "wifi";
+            } else if port.contains("iphone") || port.contains("cellular") {
+                kind = "cellular";
+            }
+            break;
+        }
+    }
+    let metered = (kind == "cellular").then_some(true);
+    (kind.into(), Some(name), metered)
+}
+
+#[cfg(target_os = "windows")]
+fn detect_interface() -> (String, Option<String>, Option<bool>) {
+    use std::os::windows::process::CommandExt;
+    use std::process::Command;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    // WinRT connection profile knows both the medium and the cost policy
+    let script = "$p=[Windows.Networking.Connectivity.NetworkInformation,Windows.Networking.Connectivity,ContentType=WindowsRuntime]::GetInternetConnectionProfile(); if ($p) { \"$($p.IsWlanConnectionProfile)|$($p.IsWwanConnectionProfile)|$($p.GetConnectionCost().NetworkCostType)|$($p.ProfileName)\" }";
+    let out = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .unwrap_or_default();
+    let parts: Vec<&str> = out.split('|').collect();
+    if parts.len() < 4 {
+        return ("unknown".into(), None, None);
+    }
+    let kind = if parts[0].eq_ignore_ascii_case("true") {
+        "wifi"
+    } else if parts[1].eq_ignore_ascii_case("true") {
+        "cellular"
+    } else {
+        "ethernet"
+    };
+    let metered = match parts[2] {
+        "Unrestricted" => Some(false),
+        "Fixed" | "Variable" => Some(true),
+        _ => None,
+    };
+    (
+        kind.into(),
+        Some(parts[3].to_string()).filter(|s| !s.is_empty()),
+        metered,
+    )
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn detect_interface() -> (String, Option<String>, Option<bool>) {
+    ("unknown".into(), None, None)
+}
+
+async fn probe(base: &str) -> Result<(u64, Option<f64>), String> {
+    let health = format!("{}/health", base);
+    let mut samples = Vec::with_capacity(LATENCY_SAMPLES);
+    for _ in 0..LATENCY_SAMPLES {
+        let started = Instant::now();
+        http::client()
+            .get(&health)
+            .timeout(PROBE_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        samples.push(started.elapsed());
+    }
+    samples.sort();
+    let latency = samples[samples.len() / 2];
+
+    // the gateway reads the whole body before answering, even with 405, so
+    // the round trip minus latency approximates upload time
+    let started = Instant::now();
+    let upload = http::client()
+        .post(&health)
+        .timeout(PROBE_TIMEOUT)
+        .body(vec![0u8; PROBE_BYTES])
+        .send()
+        .await;
+    let elapsed = started.elapsed();
+    let mbps = upload.ok().and_then(|_| {
+        let transfer = elapsed.checked_sub(latency)?.as_secs_f64();
+        (transfer > 0.0).then(|| (PROBE_BYTES as f64 * 8.0) / transfer / 1_000_000.0)
+    });
+    Ok((latency.as_millis() as u64, mbps))
+}
+
+#[tauri::command]
+pub async fn network_status(server_url: Option<String>) -> Result<NetworkStatus, String> {
+    let (interface, interface_name, metered) =
+        tauri::async_runtime::spawn_blocking(detect_interface)
+            .await
+            .map_err(|e| e.to_string())?;
+    let mut status = NetworkStatus {
+        interface,
+        interface_name,
+        metered,
+        ..Default::default()
+    };
+    let base = server_url
+        .as_deref()
+        .map(|s| s.trim().trim_end_matches('/'))
+        .filter(|s| !s.is_empty());
+    if let Some(base) = base {
+        match probe(base).await {
+            Ok((latency, mbps)) => {
+                status.latency_ms = Some(latency);
+                status.upload_mbps = mbps.map(|m| (m * 10.0).round() / 10.0);
+            }
+            Err(err) => status.probe_error = Some(err),
+        }
+    }
+    Ok(status)
+}
@@ -1,9 +1,35 @@
 use base64::Engine;
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use std::{fs, net::TcpListener, path::PathBuf};
-use tauri::Manager;
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
 
-const SESSION_FILE: &str = "session.json";
+use crate::error::TauraError;
+use crate::http;
+use crate::id_token;
+use crate::redact;
+use crate::session_store;
+use crate::settings;
+
+const DEFAULT_SCOPE: &str = "openid email profile";
+const GOOGLE: &str = "google";
+const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const GOOGLE_REVOKE_URL: &str = "https://oauth2.googleapis.com/revoke";
+const GITHUB_EMAILS_URL: &str = "https://api.github.com/user/emails";
+const APPLE_ISSUER: &str = "https://appleid.apple.com";
+
+// Google rotates refresh tokens, so two refreshes racing can invalidate the
+// session; every network refresh goes through this lock.
+static REFRESH_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+// Background refresh is opt-in; the timer re-reads state whenever woken.
+static AUTO_REFRESH: AtomicBool = AtomicBool::new(false);
+static REFRESH_TIMER_WAKE: Lazy<tokio::sync::Notify> = Lazy::new(tokio::sync::Notify::new);
+const TIMER_RETRY_SECS: u64 = 60;
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Session {
@@ -18,151 +44,1087 @@ pub struct Session {
     pub client_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub client_secret: Option<String>,
+    /// Space-separated scopes currently attached to the access token.
+    #[serde(default)]
+    pub scope: Option<String>,
+    /// Scopes granted at sign-in; refreshes are compared against this.
+    #[serde(default)]
+    pub granted_scope: Option<String>,
+    /// Identity provider that issued the tokens; sessions from before this
+    /// field existed are Google.
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_endpoint: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revocation_endpoint: Option<String>,
 }
 
-fn session_path(app: &tauri::AppHandle) -> PathBuf {
-    let base = app
-        .path()
-        .app_config_dir()
-        .or_else(|_| app.path().app_data_dir())
-        .unwrap_or_else(|_| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
-    base.join(SESSION_FILE)
+fn is_legacy_google(sess: &Session) -> bool {
+    matches!(sess.provider.as_deref(), None | Some(GOOGLE))
 }
 
-fn load_session(app: &tauri::AppHandle) -> Option<Session> {
-    let p = session_path(app);
-    if !p.exists() {
-        return None;
+fn token_endpoint(sess: &Session) -> Result<String, String> {
+    match &sess.token_endpoint {
+        Some(url) => Ok(url.clone()),
+        None if is_legacy_google(sess) => Ok(GOOGLE_TOKEN_URL.to_string()),
+        None => Err("token endpoint missing from session".into()),
     }
-    let data = fs::read(p).ok()?;
-    serde_json::from_slice(&data).ok()
+}
+
+fn revocation_endpoint(sess: &Session) -> Option<String> {
+    match &sess.revocation_endpoint {
+        Some(url) => Some(url.clone()),
+        None if sess.token_endpoint.is_none() && is_legacy_google(sess) => {
+            Some(GOOGLE_REVOKE_URL.to_string())
+        }
+        None => None,
+    }
+}
+
+fn split_scopes(scope: Option<&str>) -> Vec<String> {
+    scope
+        .unwrap_or("")
+        .split_whitespace()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+#[derive(Serialize)]
+pub struct ScopeStatus {
+    pub granted: Vec<String>,
+    pub current: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+fn scope_status(sess: &Session) -> ScopeStatus {
+    let granted = split_scopes(sess.granted_scope.as_deref());
+    let current = split_scopes(sess.scope.as_deref().or(sess.granted_scope.as_deref()));
+    let missing = granted
+        .iter()
+        .filter(|s| !current.contains(s))
+        .cloned()
+        .collect();
+    ScopeStatus {
+        granted,
+        current,
+        missing,
+    }
+}
+
+/// Stable id an account is stored under: the provider subject, else the email.
+pub fn account_id(sess: &Session) -> String {
+    [&sess.sub, &sess.email]
+        .into_iter()
+        .flatten()
+        .map(|s| s.trim())
+        .find(|s| !s.is_empty())
+        .unwrap_or("default")
+        .to_string()
+}
+
+fn load_account(app: &tauri::AppHandle, account: &str) -> Option<Session> {
+    let data = session_store::load(app, account)?;
+    serde_json::from_str(&data).ok()
+}
+
+// Moves a session saved before accounts were keyed into its own slot and
+// makes it the active one.
+fn migrate_legacy_session(app: &tauri::AppHandle) -> Option<String> {
+    let sess: Session = serde_json::from_str(&session_store::load_legacy(app)?).ok()?;
+    let id = account_id(&sess);
+    persist_session(app, &sess).ok()?;
+    settings::update(app, |s| s.active_account = Some(id.clone())).ok()?;
+    session_store::clear_legacy(app);
+    Some(id)
+}
+
+fn active_account(app: &tauri::AppHandle) -> Option<String> {
+    settings::load(app)
+        .active_account
+        .or_else(|| migrate_legacy_session(app))
+}
+
+fn load_session(app: &tauri::AppHandle) -> Option<Session> {
+    load_account(app, &active_account(app)?)
 }
 
 fn persist_session(app: &tauri::AppHandle, sess: &Session) -> Result<(), String> {
-    let p = session_path(app);
-    if let Some(parent) = p.parent() {
-        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-    }
-    let data = serde_json::to_vec_pretty(sess).map_err(|e| e.to_string())?;
-    fs::write(&p, data).map_err(|e| e.to_string())?;
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let mut perms = fs::metadata(&p).map_err(|e| e.to_string())?.permissions();
-        perms.set_mode(0o600);
-        fs::set_permissions(&p, perms).map_err(|e| e.to_string())?;
+    let id = account_id(sess);
+    let data = serde_json::to_string(sess).map_err(|e| e.to_string())?;
+    session_store::save(app, &id, &data)?;
+    if !settings::load(app).accounts.contains(&id) {
+        settings::update(app, |s| s.accounts.push(id))?;
     }
     Ok(())
 }
 
+/// User id uploads are tagged with: the active account's id.
+pub fn active_user_id(app: &tauri::AppHandle) -> Option<String> {
+    load_session(app).map(|sess| account_id(&sess))
+}
+
 #[tauri::command]
-pub async fn get_session(app: tauri::AppHandle) -> Result<Option<Session>, String> {
+pub async fn get_session(app: tauri::AppHandle) -> Result<Option<Session>, TauraError> {
     Ok(load_session(&app))
 }
 
+/// What the UI may show about the active session; never carries tokens.
+#[derive(Serialize)]
+pub struct SessionInfo {
+    pub account_id: String,
+    pub email: Option<String>,
+    pub name: Option<String>,
+    pub picture: Option<String>,
+    pub provider: String,
+    pub expires_at: Option<i64>,
+    pub scopes: Vec<String>,
+    pub has_refresh_token: bool,
+}
+
+#[tauri::command]
+pub async fn describe_session(app: tauri::AppHandle) -> Result<Option<SessionInfo>, TauraError> {
+    Ok(load_session(&app).map(|sess| SessionInfo {
+        account_id: account_id(&sess),
+        // sessions saved before providers were recorded are Google ones
+        provider: sess.provider.clone().unwrap_or_else(|| GOOGLE.into()),
+        scopes: sess
+            .scope
+            .as_deref()
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(str::to_string)
+            .collect(),
+        has_refresh_token: sess.refresh_token.is_some(),
+        email: sess.email,
+        name: sess.name,
+        picture: sess.picture,
+        expires_at: sess.expires_at,
+    }))
+}
+
+#[derive(Serialize)]
+pub struct AccountSummary {
+    pub account_id: String,
+    pub email: Option<String>,
+    pub name: Option<String>,
+    pub picture: Option<String>,
+    pub provider: Option<String>,
+    pub active: bool,
+}
+
+#[tauri::command]
+pub async fn list_sessions(app: tauri::AppHandle) -> Result<Vec<AccountSummary>, TauraError> {
+    let active = active_account(&app);
+    Ok(settings::load(&app)
+        .accounts
+        .into_iter()
+        .filter_map(|id| {
+            let sess = load_account(&app, &id)?;
+            Some(AccountSummary {
+                active: active.as_deref() == Some(id.as_str()),
+                account_id: id,
+                email: sess.email,
+                name: sess.name,
+                picture: sess.picture,
+                provider: sess.provider,
+            })
+        })
+        .collect())
+}
+
 #[tauri::command]
-pub async fn logout(app: tauri::AppHandle) -> Result<(), String> {
-    let p = session_path(&app);
-    if p.exists() {
-        let _ = fs::remove_file(p);
+pub async fn switch_active_session(
+    account_id: String,
+    app: tauri::AppHandle,
+) -> Result<Session, TauraError> {
+    let sess = load_account(&app, &account_id)
+        .ok_or_else(|| TauraError::invalid_input("unknown account"))?;
+    settings::update(&app, |s| s.active_account = Some(account_id))?;
+    // the timer schedules from the active account's expiry
+    REFRESH_TIMER_WAKE.notify_one();
+    Ok(sess)
+}
+
+/// Signs out one account (the active one by default). If it was active, the
+/// next remaining account takes over.
+#[tauri::command]
+pub async fn logout(account_id: Option<String>, app: tauri::AppHandle) -> Result<(), TauraError> {
+    // wait out any in-flight refresh so it can't re-persist the session
+    let _guard = REFRESH_LOCK.lock().await;
+    let Some(id) = account_id.or_else(|| active_account(&app)) else {
+        return Ok(());
+    };
+    let revoke = load_account(&app, &id).and_then(|sess| Some((revocation_endpoint(&sess)?, sess)));
+    if let Some((url, sess)) = revoke {
+        // revoking the refresh token also invalidates its access tokens
+        let token = sess.refresh_token.unwrap_or(sess.access_token);
+        let mut params = vec![("token", token.as_str())];
+        // Apple won't revoke without the client authenticating
+        if let Some(client_id) = sess.client_id.as_deref() {
+            params.push(("client_id", client_id));
+        }
+        if let Some(secret) = sess.client_secret.as_deref() {
+            params.push(("client_secret", secret));
+        }
+        let revoked = http::client()
+            .post(&url)
+            .timeout(Duration::from_secs(10))
+            .form(&params)
+            .send()
+            .await;
+        match revoked {
+            Ok(resp) if resp.status().is_success() => {}
+            Ok(resp) => log::warn!("token revoke failed: {}", resp.status()),
+            Err(err) => log::warn!("token revoke failed: {}", err),
+        }
     }
+    session_store::clear(&app, &id);
+    settings::update(&app, |s| {
+        s.accounts.retain(|a| a != &id);
+        if s.active_account.as_deref() == Some(id.as_str()) {
+            s.active_account = s.accounts.first().cloned();
+        }
+    })?;
+    REFRESH_TIMER_WAKE.notify_one();
     Ok(())
 }
 
 #[derive(Deserialize)]
-pub struct GoogleAuthConfig {
+pub struct AuthConfig {
     #[serde(alias = "clientId", alias = "clientID")]
     client_id: String,
     #[serde(default, alias = "clientSecret", alias = "client_secret")]
     client_secret: Option<String>,
+    /// Replaces the provider's default scope set when present.
+    #[serde(default)]
+    scopes: Option<Vec<String>>,
+    /// Microsoft tenant: `common` (default), `organizations`, `consumers` or a tenant id.
+    #[serde(default)]
+    tenant: Option<String>,
+    /// OIDC issuer; endpoints come from its discovery document unless given below.
+    #[serde(default)]
+    issuer: Option<String>,
+    #[serde(default, alias = "authorizationEndpoint")]
+    authorization_endpoint: Option<String>,
+    #[serde(default, alias = "tokenEndpoint")]
+    token_endpoint: Option<String>,
+    #[serde(default, alias = "userinfoEndpoint")]
+    userinfo_endpoint: Option<String>,
+    #[serde(default, alias = "revocationEndpoint")]
+    revocation_endpoint: Option<String>,
+    /// Keys that sign the provider's id_tokens. Needed with explicit
+    /// endpoints, where there is no discovery document to name them.
+    #[serde(default, alias = "jwksUri")]
+    jwks_uri: Option<String>,
+    /// How the redirect comes back: `loopback` (default) or `deep_link`.
+    #[serde(default)]
+    redirect: Option<String>,
+    /// Fixed loopback port, for clients (e.g. Google "Web application")
+    /// that only accept pre-registered redirect URIs.
+    #[serde(default, alias = "redirectPort")]
+    redirect_port: Option<u16>,
+    /// Further ports to try, in order, when `redirect_port` is taken.
+    #[serde(default, alias = "redirectPorts")]
+    redirect_ports: Option<Vec<u16>>,
 }
 
+/// Kept for the existing `google_auth_start` callers.
+pub type GoogleAuthConfig = AuthConfig;
+
 #[derive(Serialize)]
 pub struct AuthResult {
     pub session: Session,
 }
 
-// Simplified loopback PKCE flow for installed apps.
+/// Endpoints and quirks of one identity provider.
+struct Provider {
+    name: &'static str,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    /// Without one, profile fields are read from the id_token instead.
+    userinfo_endpoint: Option<String>,
+    revocation_endpoint: Option<String>,
+    /// Accepted id_token `iss` values; may contain Microsoft's `{tenantid}`.
+    issuers: Vec<String>,
+    jwks_uri: Option<String>,
+    default_scope: &'static str,
+    extra_auth_params: &'static [(&'static str, &'static str)],
+    userinfo_format: UserInfoFormat,
+}
+
+/// Shape of the provider's userinfo response.
+#[derive(Clone, Copy)]
+enum UserInfoFormat {
+    /// Standard OIDC claims: `sub`, `email`, `name`, `picture`.
+    Oidc,
+    /// GitHub's REST `/user`, which may leave out a private email.
+    GitHub,
+}
+
+fn google_provider() -> Provider {
+    Provider {
+        name: GOOGLE,
+        authorization_endpoint: "https://accounts.google.com/o/oauth2/v2/auth".into(),
+        token_endpoint: GOOGLE_TOKEN_URL.into(),
+        userinfo_endpoint: Some("https://openidconnect.googleapis.com/v1/userinfo".into()),
+        revocation_endpoint: Some(GOOGLE_REVOKE_URL.into()),
+        issuers: vec![
+            "https://accounts.google.com".into(),
+            "accounts.google.com".into(),
+        ],
+        jwks_uri: Some("https://www.googleapis.com/oauth2/v3/certs".into()),
+        default_scope: DEFAULT_SCOPE,
+        // offline + consent is what makes Google hand out a refresh token
+        extra_auth_params: &[("access_type", "offline"), ("prompt", "consent")],
+        userinfo_format: UserInfoFormat::Oidc,
+    }
+}
+
+fn microsoft_provider(tenant: Option<&str>) -> Provider {
+    let tenant = tenant
+        .map(|t| t.trim())
+        .filter(|t| !t.is_empty())
+        .unwrap_or("common");
+    let tenant = urlencoding::encode(tenant);
+    let base = format!("https://login.microsoftonline.com/{}/oauth2/v2.0", tenant);
+    Provider {
+        name: "microsoft",
+        authorization_endpoint: format!("{}/authorize", base),
+        token_endpoint: format!("{}/token", base),
+        userinfo_endpoint: Some("https://graph.microsoft.com/oidc/userinfo".into()),
+        // the v2.0 endpoints have no token revocation
+        revocation_endpoint: None,
+        // tokens carry the user's own tenant even when signing in via `common`
+        issuers: vec!["https://login.microsoftonline.com/{tenantid}/v2.0".into()],
+        jwks_uri: Some(format!(
+            "https://login.microsoftonline.com/{}/discovery/v2.0/keys",
+            tenant
+        )),
+        default_scope: "openid email profile offline_access",
+        extra_auth_params: &[],
+        userinfo_format: UserInfoFormat::Oidc,
+    }
+}
+
+// Plain OAuth 2.0: no id_token, so the profile always comes from the API.
+// Refresh tokens are only issued when the app has expiring tokens enabled.
+fn github_provider() -> Provider {
+    Provider {
+        name: "github",
+        authorization_endpoint: "https://github.com/login/oauth/authorize".into(),
+        token_endpoint: "https://github.com/login/oauth/access_token".into(),
+        userinfo_endpoint: Some("https://api.github.com/user".into()),
+        // revoking needs the client secret as basic auth on a DELETE
+        revocation_endpoint: None,
+        issuers: Vec::new(),
+        jwks_uri: None,
+        default_scope: "read:user user:email",
+        extra_auth_params: &[],
+        userinfo_format: UserInfoFormat::GitHub,
+    }
+}
+
+// Apple posts the redirect back when name or email is requested, and only to
+// https URLs registered for the Services ID; the registered URL has to
+// forward to the loopback listener on `redirect_port`. There's no userinfo
+// endpoint: the profile is whatever the id_token carries.
+fn apple_provider() -> Provider {
+    Provider {
+        name: "apple",
+        authorization_endpoint: format!("{}/auth/authorize", APPLE_ISSUER),
+        token_endpoint: format!("{}/auth/token", APPLE_ISSUER),
+        userinfo_endpoint: None,
+        revocation_endpoint: Some(format!("{}/auth/revoke", APPLE_ISSUER)),
+        issuers: vec![APPLE_ISSUER.into()],
+        jwks_uri: Some(format!("{}/auth/keys", APPLE_ISSUER)),
+        default_scope: "name email",
+        extra_auth_params: &[("response_mode", "form_post")],
+        userinfo_format: UserInfoFormat::Oidc,
+    }
+}
+
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value
+        .as_ref()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+async fn oidc_provider(cfg: &AuthConfig) -> Result<Provider, TauraError> {
+    #[derive(Deserialize)]
+    struct Discovery {
+        issuer: String,
+        authorization_endpoint: String,
+        token_endpoint: String,
+        userinfo_endpoint: Option<String>,
+        revocation_endpoint: Option<String>,
+        jwks_uri: Option<String>,
+    }
+    let mut provider = Provider {
+        name: "oidc",
+        authorization_endpoint: non_empty(&cfg.authorization_endpoint).unwrap_or_default(),
+        token_endpoint: non_empty(&cfg.token_endpoint).unwrap_or_default(),
+        userinfo_endpoint: non_empty(&cfg.userinfo_endpoint),
+        revocation_endpoint: non_empty(&cfg.revocation_endpoint),
+        issuers: non_empty(&cfg.issuer).into_iter().collect(),
+        jwks_uri: non_empty(&cfg.jwks_uri),
+        default_scope: DEFAULT_SCOPE,
+        extra_auth_params: &[],
+        userinfo_format: UserInfoFormat::Oidc,
+    };
+    let endpoints_missing =
+        provider.authorization_endpoint.is_empty() || provider.token_endpoint.is_empty();
+    // discovery also supplies the keys needed to verify id_tokens
+    if endpoints_missing || (provider.jwks_uri.is_none() && cfg.issuer.is_some()) {
+        let issuer = non_empty(&cfg.issuer).ok_or_else(|| {
+            TauraError::invalid_input("oidc needs an issuer or explicit endpoints")
+        })?;
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            issuer.trim_end_matches('/')
+        );
+        let doc = http::client()
+            .get(&url)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|e| TauraError::from(e).context("oidc discovery failed"))?
+            .json::<Discovery>()
+            .await
+            .map_err(|e| TauraError::from(e).context("oidc discovery decode failed"))?;
+        // explicitly configured endpoints win over discovered ones
+        if provider.authorization_endpoint.is_empty() {
+            provider.authorization_endpoint = doc.authorization_endpoint;
+        }
+        if provider.token_endpoint.is_empty() {
+            provider.token_endpoint = doc.token_endpoint;
+        }
+        provider.userinfo_endpoint = provider.userinfo_endpoint.or(doc.userinfo_endpoint);
+        provider.revocation_endpoint = provider.revocation_endpoint.or(doc.revocation_endpoint);
+        provider.issuers = vec![doc.issuer];
+        provider.jwks_uri = provider.jwks_uri.or(doc.jwks_uri);
+    }
+    Ok(provider)
+}
+
+#[derive(Deserialize, Default)]
+struct UserInfo {
+    sub: Option<String>,
+    email: Option<String>,
+    name: Option<String>,
+    picture: Option<String>,
+}
+
+async fn fetch_userinfo(
+    client: &reqwest::Client,
+    url: &str,
+    access_token: &str,
+    format: UserInfoFormat,
+) -> Result<UserInfo, TauraError> {
+    let resp = client
+        .get(url)
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| TauraError::from(e).context("userinfo request failed"))?;
+    match format {
+        UserInfoFormat::Oidc => Ok(resp.json::<UserInfo>().await?),
+        UserInfoFormat::GitHub => {
+            #[derive(Deserialize)]
+            struct GitHubUser {
+                id: u64,
+                login: String,
+                name: Option<String>,
+                email: Option<String>,
+                avatar_url: Option<String>,
+            }
+            let user = resp.json::<GitHubUser>().await?;
+            let email = match user.email {
+                Some(email) => Some(email),
+                None => github_primary_email(client, access_token).await,
+            };
+            Ok(UserInfo {
+                sub: Some(user.id.to_string()),
+                email,
+                name: user.name.or(Some(user.login)),
+                picture: user.avatar_url,
+            })
+        }
+    }
+}
+
+// `/user` leaves out emails the user keeps private; `user:email` still
+// grants the list. Only a verified primary address is taken.
+async fn github_primary_email(client: &reqwest::Client, access_token: &str) -> Option<String> {
+    #[derive(Deserialize)]
+    struct GitHubEmail {
+        email: String,
+        primary: bool,
+        verified: bool,
+    }
+    let emails = client
+        .get(GITHUB_EMAILS_URL)
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .ok()?
+        .json::<Vec<GitHubEmail>>()
+        .await
+        .ok()?;
+    emails
+        .into_iter()
+        .find(|e| e.primary && e.verified)
+        .map(|e| e.email)
+}
+
+// Providers that refuse a code exchange without the client secret; Apple's
+// is a JWT the developer signs with their key.
+fn require_client_secret(cfg: &AuthConfig, provider: &str) -> Result<(), TauraError> {
+    if non_empty(&cfg.client_secret).is_none() {
+        return Err(TauraError::invalid_input(format!(
+            "{} sign-in needs client_secret",
+            provider
+        )));
+    }
+    Ok(())
+}
+
+async fn resolve_provider(name: &str, cfg: &AuthConfig) -> Result<Provider, TauraError> {
+    Ok(match name.trim().to_lowercase().as_str() {
+        GOOGLE => google_provider(),
+        "microsoft" | "azure" => microsoft_provider(cfg.tenant.as_deref()),
+        "github" => {
+            require_client_secret(cfg, "github")?;
+            github_provider()
+        }
+        "apple" => {
+            require_client_secret(cfg, "apple")?;
+            apple_provider()
+        }
+        "oidc" => oidc_provider(cfg).await?,
+        other => {
+            return Err(TauraError::invalid_input(format!(
+                "unknown auth provider: {}",
+                other
+            )))
+        }
+    })
+}
+
+#[tauri::command]
+pub async fn auth_start(
+    app: tauri::AppHandle,
+    provider: String,
+    cfg: AuthConfig,
+) -> Result<AuthResult, TauraError> {
+    let provider = resolve_provider(&provider, &cfg).await?;
+    pkce_login(&app, provider, &cfg).await
+}
+
 #[tauri::command]
 pub async fn google_auth_start(
     app: tauri::AppHandle,
     cfg: GoogleAuthConfig,
-) -> Result<AuthResult, String> {
-    let client_id = cfg.client_id.trim();
-    if client_id.is_empty() {
-        return Err("client_id empty (set VITE_TAURA_GOOGLE_CLIENT_ID)".into());
+) -> Result<AuthResult, TauraError> {
+    if cfg.client_id.trim().is_empty() {
+        return Err(TauraError::invalid_input(
+            "client_id empty (set VITE_TAURA_GOOGLE_CLIENT_ID)",
+        ));
     }
-    let client_secret_opt = cfg
-        .client_secret
-        .as_ref()
-        .map(|s| s.trim())
-        .filter(|s| !s.is_empty());
-
-    // --- PKCE code verifier & challenge ---
-    use rand::RngCore;
-    // Use 48 random bytes -> ~64 URL-safe base64 chars; PKCE requires 43-128
-    let mut vr = [0u8; 48];
-    rand::rngs::OsRng.fill_bytes(&mut vr);
-    let mut code_verifier = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(vr);
-    // Safety: ensure length within (43..=128); if shorter, append 'A'; if longer trim
-    while code_verifier.len() < 43 { code_verifier.push('A'); }
-    if code_verifier.len() > 128 { code_verifier.truncate(128); }
-    use sha2::{Digest, Sha256};
-    let challenge_hash = Sha256::digest(code_verifier.as_bytes());
-    let code_challenge = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(challenge_hash);
-
-    // Loopback ephemeral listener
-    let listener = TcpListener::bind("127.0.0.1:0").map_err(|e| e.to_string())?;
-    let redirect_port = listener.local_addr().map_err(|e| e.to_string())?.port();
-    let redirect_uri = format!("http://127.0.0.1:{}", redirect_port);
+    pkce_login(&app, google_provider(), &cfg).await
+}
+
+// Manual sign-ins waiting for their pasted redirect, keyed by handle.
+static PENDING_AUTHS: Lazy<std::sync::Mutex<HashMap<String, PendingAuth>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+// Providers expire authorization codes within minutes; a handle older than
+// this could never complete anyway.
+const PENDING_AUTH_TTL: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Serialize)]
+pub struct AuthUrl {
+    pub url: String,
+    pub state: String,
+    /// Passed back to `complete_auth`; the PKCE verifier never leaves the app.
+    pub handle: String,
+}
 
+/// First half of a sign-in for when the browser can't be opened for the
+/// user (headless, remote desktop): returns the consent URL to open by hand.
+/// Nothing listens for a loopback redirect; the user pastes the URL it lands
+/// on into `complete_auth`, even if that page fails to load. A `deep_link`
+/// redirect comes back to the app instead, which `complete_auth` waits for
+/// when given an empty `redirect_url`.
+#[tauri::command]
+pub async fn build_auth_url(
+    provider: Option<String>,
+    cfg: AuthConfig,
+) -> Result<AuthUrl, TauraError> {
+    let provider = resolve_provider(provider.as_deref().unwrap_or(GOOGLE), &cfg).await?;
     let state = uuid::Uuid::new_v4().to_string();
-    let scope = "openid email profile";
-    let auth_url = format!(
-        "https://accounts.google.com/o/oauth2/v2/auth?response_type=code&client_id={}&redirect_uri={}&scope={}&state={}&code_challenge={}&code_challenge_method=S256&access_type=offline&prompt=consent",
-        urlencoding::encode(client_id),
-        urlencoding::encode(&redirect_uri),
-        urlencoding::encode(scope),
-        urlencoding::encode(&state),
-        urlencoding::encode(&code_challenge)
-    );
+    let (capture, redirect_uri) = redirect_target(&cfg, &state)?;
+    let mut pending = PendingAuth::new(provider, &cfg, state, redirect_uri)?;
+    // the deep link stays registered as long as the handle; a loopback
+    // listener isn't needed when the address is pasted
+    if let RedirectCapture::DeepLink(deep_link) = capture {
+        pending.deep_link = Some(deep_link);
+    }
+    let handle = uuid::Uuid::new_v4().to_string();
+    let result = AuthUrl {
+        url: pending.auth_url.clone(),
+        state: pending.state.clone(),
+        handle: handle.clone(),
+    };
+    let mut pending_auths = PENDING_AUTHS
+        .lock()
+        .map_err(|_| TauraError::from("lock poisoned".to_string()))?;
+    pending_auths.retain(|_, p| p.created.elapsed() < PENDING_AUTH_TTL);
+    pending_auths.insert(handle, pending);
+    Ok(result)
+}
 
-    // Open system browser
-    if let Err(e) = open::that(&auth_url) {
-        return Err(format!("failed to open browser: {}", e));
+/// Second half of `build_auth_url`: takes the URL the browser was redirected
+/// to (or just its query string) and finishes the sign-in. An empty
+/// `redirect_url` waits for a deep-link redirect instead. A paste without a
+/// code or error leaves the handle usable for another try.
+#[tauri::command]
+pub async fn complete_auth(
+    app: tauri::AppHandle,
+    handle: String,
+    redirect_url: String,
+) -> Result<AuthResult, TauraError> {
+    let redirect_url = redirect_url.trim();
+    let query = redirect_url
+        .split_once('?')
+        .map_or(redirect_url, |(_, q)| q);
+    // browsers sometimes keep a #fragment on the copied address
+    let query = query.split('#').next().unwrap_or("");
+    let (mut pending, deep_link) = {
+        let mut pending_auths = PENDING_AUTHS
+            .lock()
+            .map_err(|_| TauraError::from("lock poisoned".to_string()))?;
+        let mut pending = match pending_auths.remove(&handle) {
+            Some(p) if p.created.elapsed() < PENDING_AUTH_TTL => p,
+            _ => {
+                return Err(TauraError::invalid_input(
+                    "sign-in expired or unknown; start again",
+                ))
+            }
+        };
+        let deep_link = if query.is_empty() {
+            pending.deep_link.take()
+        } else {
+            None
+        };
+        if deep_link.is_none() && !is_redirect_query(query) {
+            pending_auths.insert(handle, pending);
+            return Err(TauraError::invalid_input(
+                "pasted URL has no code; copy the full address the browser landed on",
+            ));
+        }
+        (pending, deep_link)
+    };
+    // a code or provider error ends this attempt either way
+    let code = match deep_link {
+        Some(deep_link) => deep_link.code(&pending.state).await,
+        None => redirect_code(query, &pending.state),
+    }?;
+    pending.deep_link = None;
+    exchange_code(&app, pending, &code).await
+}
+
+const DEEP_LINK_REDIRECT: &str = "taura://auth";
+// How long a sign-in waits for the browser to hand the code back.
+const REDIRECT_TIMEOUT: Duration = Duration::from_secs(300);
+// a form_post redirect carries an id_token; anything far bigger isn't one
+const MAX_REDIRECT_REQUEST: usize = 64 * 1024;
+
+// Deep-link sign-ins waiting for their redirect, keyed by OAuth state.
+static PENDING_DEEP_LINKS: Lazy<
+    std::sync::Mutex<HashMap<String, tokio::sync::oneshot::Sender<String>>>,
+> = Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+/// How the authorization redirect gets back to us.
+enum RedirectCapture {
+    Loopback(TcpListener),
+    DeepLink(PendingDeepLink),
+}
+
+// Pulls the authorization code out of a redirect's query string.
+fn redirect_code(qs: &str, state: &str) -> Result<String, TauraError> {
+    let mut params: HashMap<&str, String> = HashMap::new();
+    for pair in qs.split('&') {
+        let mut kv = pair.splitn(2, '=');
+        let k = kv.next().unwrap_or("");
+        let v_raw = kv.next().unwrap_or("").replace('+', " ");
+        let v = urlencoding::decode(&v_raw).unwrap_or_default().to_string();
+        params.insert(k, v);
+    }
+    // nothing in the redirect is trusted until the state matches
+    if params.get("state").map(String::as_str) != Some(state) {
+        return Err(TauraError::invalid_input("state mismatch"));
+    }
+    if let Some(err) = params.get("error") {
+        let message = match params.get("error_description") {
+            Some(desc) if !desc.is_empty() => format!("{}: {}", err, desc),
+            _ => err.clone(),
+        };
+        // the user said no on the consent page
+        return Err(if err == "access_denied" {
+            TauraError::cancelled(message)
+        } else {
+            TauraError::from(message)
+        });
     }
+    params
+        .remove("code")
+        .ok_or_else(|| TauraError::invalid_input("authorization code missing"))
+}
 
-    // Accept single connection
-    let (mut stream, _) = listener.accept().map_err(|e| e.to_string())?;
-    use std::io::Read;
-    let mut buf = [0u8; 2048];
-    let n = stream.read(&mut buf).map_err(|e| e.to_string())?;
-    let req = String::from_utf8_lossy(&buf[..n]);
-    let line = req.lines().next().unwrap_or("");
-    // Expect GET /?code=...&state=...
-    let code = {
-        // Parse first request line: GET /?code=...&state=... HTTP/1.1
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() < 2 { return Err("malformed redirect request".into()); }
-        let path_q = parts[1];
-        let q_idx = path_q.find('?').ok_or_else(|| "missing query in redirect".to_string())?;
-        let qs = &path_q[q_idx + 1..];
-        let mut code_opt = None;
-        for pair in qs.split('&') {
-            let mut kv = pair.splitn(2,'=');
-            let k = kv.next().unwrap_or("");
-            let v_raw = kv.next().unwrap_or("");
-            let v = urlencoding::decode(v_raw).unwrap_or_default().to_string();
-            if k == "state" && v != state { return Err("state mismatch".into()); }
-            if k == "code" { code_opt = Some(v); }
-        }
-        code_opt.ok_or_else(|| "authorization code missing".to_string())?
+// Binds the first free configured port, or an ephemeral one if none are set.
+fn bind_loopback(cfg: &AuthConfig) -> Result<TcpListener, String> {
+    let ports: Vec<u16> = cfg
+        .redirect_port
+        .into_iter()
+        .chain(cfg.redirect_ports.iter().flatten().copied())
+        .filter(|p| *p != 0)
+        .collect();
+    if ports.is_empty() {
+        return TcpListener::bind("127.0.0.1:0").map_err(|e| e.to_string());
+    }
+    for port in &ports {
+        match TcpListener::bind(("127.0.0.1", *port)) {
+            Ok(listener) => return Ok(listener),
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => continue,
+            Err(e) => return Err(format!("cannot bind redirect port {}: {}", port, e)),
+        }
+    }
+    let tried: Vec<String> = ports.iter().map(|p| p.to_string()).collect();
+    Err(format!(
+        "redirect port {} already in use; close the app using it or register another port",
+        tried.join(", ")
+    ))
+}
+
+// Whether a loopback request carries the redirect rather than browser noise
+// such as `/favicon.ico` or a preflight. A `form_post` redirect (Apple) has
+// its parameters in the body instead of the query.
+fn redirect_query(request: &str) -> Option<&str> {
+    let request_line = request.lines().next().unwrap_or("");
+    let query = if request_line.starts_with("POST ") {
+        request.split_once("\r\n\r\n")?.1
+    } else {
+        let path = request_line.split_whitespace().nth(1)?;
+        path.split_once('?')?.1
     };
+    is_redirect_query(query).then_some(query)
+}
 
-    // Respond basic HTML
-    let resp = b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nConnection: close\r\n\r\n<!doctype html><html><body><h3>You can return to Taura.</h3></body></html>";
+// Reads one request, body included: form posts can exceed a single read.
+fn read_request(stream: &mut std::net::TcpStream) -> std::io::Result<String> {
+    use std::io::Read;
+    let mut req = Vec::new();
+    let mut buf = [0u8; 4096];
+    while req.len() < MAX_REDIRECT_REQUEST {
+        let n = stream.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        req.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&req);
+        let Some((head, body)) = text.split_once("\r\n\r\n") else {
+            continue;
+        };
+        let content_length = head
+            .lines()
+            .filter_map(|l| l.split_once(':'))
+            .find(|(k, _)| k.trim().eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, v)| v.trim().parse::<usize>().ok())
+            .unwrap_or(0);
+        if body.len() >= content_length {
+            break;
+        }
+    }
+    Ok(String::from_utf8_lossy(&req).into_owned())
+}
+
+fn is_redirect_query(query: &str) -> bool {
+    query.split('&').any(|pair| {
+        let key = pair.split('=').next().unwrap_or("");
+        key == "code" || key == "error"
+    })
+}
+
+fn loopback_code(listener: TcpListener, state: &str) -> Result<String, TauraError> {
     use std::io::Write;
-    let _ = stream.write_all(resp);
+    // polled so the wait gives up with the same deadline as a deep link
+    listener.set_nonblocking(true)?;
+    let deadline = std::time::Instant::now() + REDIRECT_TIMEOUT;
+    loop {
+        let mut stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                if std::time::Instant::now() >= deadline {
+                    return Err(TauraError::cancelled(
+                        "sign-in timed out waiting for the browser",
+                    ));
+                }
+                std::thread::sleep(Duration::from_millis(50));
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        // some platforms hand out accepted sockets in the listener's mode
+        let _ = stream.set_nonblocking(false);
+        // a speculative connection that never sends anything must not stall us
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+        let req = match read_request(&mut stream) {
+            Ok(req) => req,
+            Err(_) => continue,
+        };
+        // Expect GET /?code=...&state=... or, if the user declined, ?error=...&state=...
+        let Some(query) = redirect_query(&req) else {
+            let _ = stream.write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n");
+            continue;
+        };
+        let parsed = redirect_code(query, state);
+
+        // Respond basic HTML
+        let resp: &[u8] = if parsed.is_ok() {
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nConnection: close\r\n\r\n<!doctype html><html><body><h3>You can return to Taura.</h3></body></html>"
+        } else {
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nConnection: close\r\n\r\n<!doctype html><html><body><h3>Sign-in did not complete. You can return to Taura.</h3></body></html>"
+        };
+        let _ = stream.write_all(resp);
+        return parsed;
+    }
+}
+
+/// A deep-link sign-in waiting for `taura://auth?...` to come back through
+/// the scheme handler. Dropping it forgets the state.
+struct PendingDeepLink {
+    state: String,
+    rx: tokio::sync::oneshot::Receiver<String>,
+}
+
+impl PendingDeepLink {
+    fn register(state: &str) -> Result<Self, String> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let mut pending = PENDING_DEEP_LINKS.lock().map_err(|_| "lock poisoned")?;
+        pending.insert(state.to_string(), tx);
+        Ok(PendingDeepLink {
+            state: state.to_string(),
+            rx,
+        })
+    }
+
+    async fn code(mut self, state: &str) -> Result<String, TauraError> {
+        let query = tokio::time::timeout(REDIRECT_TIMEOUT, &mut self.rx)
+            .await
+            .map_err(|_| TauraError::cancelled("sign-in timed out waiting for the browser"))?
+            .map_err(|_| TauraError::cancelled("sign-in cancelled"))?;
+        redirect_code(&query, state)
+    }
+}
+
+impl Drop for PendingDeepLink {
+    fn drop(&mut self) {
+        if let Ok(mut pending) = PENDING_DEEP_LINKS.lock() {
+            pending.remove(&self.state);
+        }
+    }
+}
+
+/// Hands a `taura://auth` URL from the deep-link plugin to the sign-in
+/// waiting on its state. Other URLs are ignored.
+pub fn handle_deep_link(url: &str) {
+    let Ok(parsed) = reqwest::Url::parse(url) else {
+        return;
+    };
+    if parsed.scheme() != "taura" || parsed.host_str() != Some("auth") {
+        return;
+    }
+    let state = parsed
+        .query_pairs()
+        .find(|(k, _)| k == "state")
+        .map(|(_, v)| v.into_owned())
+        .unwrap_or_default();
+    let waiting = PENDING_DEEP_LINKS
+        .lock()
+        .ok()
+        .and_then(|mut pending| pending.remove(&state));
+    match waiting {
+        Some(tx) => {
+            let _ = tx.send(parsed.query().unwrap_or("").to_string());
+        }
+        None => log::warn!("ignoring auth deep link with no pending sign-in"),
+    }
+}
+
+/// One sign-in between showing the consent page and exchanging the code.
+struct PendingAuth {
+    provider: Provider,
+    client_id: String,
+    client_secret: Option<String>,
+    code_verifier: String,
+    state: String,
+    /// Echoed in the id_token, tying it to this sign-in.
+    nonce: String,
+    redirect_uri: String,
+    auth_url: String,
+    created: Instant,
+    /// Keeps the state registered for a `build_auth_url` deep-link redirect
+    /// until the code arrives or the handle expires.
+    deep_link: Option<PendingDeepLink>,
+}
+
+impl PendingAuth {
+    fn new(
+        provider: Provider,
+        cfg: &AuthConfig,
+        state: String,
+        redirect_uri: String,
+    ) -> Result<Self, TauraError> {
+        let client_id = cfg.client_id.trim();
+        if client_id.is_empty() {
+            return Err(TauraError::invalid_input("client_id empty"));
+        }
+        let client_secret = cfg
+            .client_secret
+            .as_ref()
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(str::to_string);
+
+        // --- PKCE code verifier & challenge ---
+        use rand::RngCore;
+        // Use 48 random bytes -> ~64 URL-safe base64 chars; PKCE requires 43-128
+        let mut vr = [0u8; 48];
+        rand::rngs::OsRng.fill_bytes(&mut vr);
+        let mut code_verifier = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(vr);
+        // Safety: ensure length within (43..=128); if shorter, append 'A'; if longer trim
+        while code_verifier.len() < 43 {
+            code_verifier.push('A');
+        }
+        if code_verifier.len() > 128 {
+            code_verifier.truncate(128);
+        }
+        use sha2::{Digest, Sha256};
+        let challenge_hash = Sha256::digest(code_verifier.as_bytes());
+        let code_challenge =
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(challenge_hash);
+
+        let requested: Vec<&str> = cfg
+            .scopes
+            .iter()
+            .flatten()
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+        let scope = if requested.is_empty() {
+            provider.default_scope.to_string()
+        } else {
+            requested.join(" ")
+        };
+        let nonce = uuid::Uuid::new_v4().to_string();
+        let mut auth_url = format!(
+            "{}{}response_type=code&client_id={}&redirect_uri={}&scope={}&state={}&nonce={}&code_challenge={}&code_challenge_method=S256",
+            provider.authorization_endpoint,
+            if provider.authorization_endpoint.contains('?') { '&' } else { '?' },
+            urlencoding::encode(client_id),
+            urlencoding::encode(&redirect_uri),
+            urlencoding::encode(&scope),
+            urlencoding::encode(&state),
+            urlencoding::encode(&nonce),
+            urlencoding::encode(&code_challenge)
+        );
+        for (k, v) in provider.extra_auth_params {
+            auth_url.push_str(&format!("&{}={}", k, urlencoding::encode(v)));
+        }
+        Ok(PendingAuth {
+            client_id: client_id.to_string(),
+            client_secret,
+            provider,
+            code_verifier,
+            state,
+            nonce,
+            redirect_uri,
+            auth_url,
+            created: Instant::now(),
+            deep_link: None,
+        })
+    }
+}
+
+// Picks the redirect URI for `cfg.redirect` and starts listening for it.
+fn redirect_target(cfg: &AuthConfig, state: &str) -> Result<(RedirectCapture, String), TauraError> {
+    match cfg.redirect.as_deref().map(str::trim) {
+        None | Some("") | Some("loopback") => {
+            let listener = bind_loopback(cfg)?;
+            let redirect_port = listener.local_addr()?.port();
+            let redirect_uri = format!("http://127.0.0.1:{}", redirect_port);
+            Ok((RedirectCapture::Loopback(listener), redirect_uri))
+        }
+        Some("deep_link") => Ok((
+            RedirectCapture::DeepLink(PendingDeepLink::register(state)?),
+            DEEP_LINK_REDIRECT.to_string(),
+        )),
+        Some(other) => Err(TauraError::invalid_input(format!(
+            "unknown redirect method: {}",
+            other
+        ))),
+    }
+}
+
+// Simplified PKCE flow for installed apps.
+async fn pkce_login(
+    app: &tauri::AppHandle,
+    provider: Provider,
+    cfg: &AuthConfig,
+) -> Result<AuthResult, TauraError> {
+    let state = uuid::Uuid::new_v4().to_string();
+    let (capture, redirect_uri) = redirect_target(cfg, &state)?;
+    let pending = PendingAuth::new(provider, cfg, state, redirect_uri)?;
+
+    // Open system browser
+    if let Err(e) = open::that(&pending.auth_url) {
+        return Err(format!("failed to open browser: {}", e).into());
+    }
+
+    let code = match capture {
+        RedirectCapture::Loopback(listener) => {
+            // polls for up to REDIRECT_TIMEOUT, so it gets a blocking thread
+            let state = pending.state.clone();
+            tauri::async_runtime::spawn_blocking(move || loopback_code(listener, &state))
+                .await
+                .map_err(|e| TauraError::from(e.to_string()))??
+        }
+        RedirectCapture::DeepLink(deep_link) => deep_link.code(&pending.state).await?,
+    };
+    exchange_code(app, pending, &code).await
+}
+
+// Trades an authorization code for tokens and saves the resulting session.
+async fn exchange_code(
+    app: &tauri::AppHandle,
+    pending: PendingAuth,
+    code: &str,
+) -> Result<AuthResult, TauraError> {
+    let PendingAuth {
+        provider,
+        client_id,
+        client_secret,
+        code_verifier,
+        nonce,
+        redirect_uri,
+        ..
+    } = pending;
+    let client_id = client_id.as_str();
+    let client_secret_opt = client_secret.as_deref();
 
     // Exchange code
     #[derive(Deserialize)]
@@ -177,47 +1139,85 @@ pub async fn google_auth_start(
     // Build form params dynamically (include client_secret if provided for OAuth Web type; Installed App often doesn't need it)
     let mut params: Vec<(&str, &str)> = vec![
         ("client_id", client_id),
-        ("code", &code),
+        ("code", code),
         ("code_verifier", &code_verifier),
         ("grant_type", "authorization_code"),
         ("redirect_uri", &redirect_uri),
     ];
     if let Some(cs) = client_secret_opt { params.push(("client_secret", cs)); }
-    let client = reqwest::Client::new();
+    let client = http::client();
     let token_resp = client
-        .post("https://oauth2.googleapis.com/token")
+        .post(&provider.token_endpoint)
+        // GitHub answers form-encoded unless asked for JSON
+        .header(reqwest::header::ACCEPT, "application/json")
         .form(&params)
         .send()
         .await
-        .map_err(|e| format!("token request failed: {e}"))?;
+        .map_err(|e| TauraError::from(e).context("token request failed"))?;
 
     if !token_resp.status().is_success() {
         let status = token_resp.status();
         let body_txt = token_resp.text().await.unwrap_or_default();
-        return Err(format!("token exchange failed: {} body={}", status, body_txt));
+        return Err(TauraError::server(
+            status,
+            format!(
+                "token exchange failed: {} body={}",
+                status,
+                redact::redact(&body_txt)
+            ),
+        ));
     }
-    let tok = token_resp
-        .json::<TokenResp>()
+    // GitHub reports a rejected code as 200 with an `error` body
+    let body_txt = token_resp
+        .text()
         .await
-        .map_err(|e| format!("token decode failed: {e}"))?;
+        .map_err(|e| TauraError::from(e).context("token request failed"))?;
+    let tok = serde_json::from_str::<TokenResp>(&body_txt).map_err(|e| {
+        format!(
+            "token decode failed: {e} body={}",
+            redact::redact(&body_txt)
+        )
+    })?;
+
+    // a session is never saved on an id_token that fails verification
+    let claims = match &tok.id_token {
+        Some(idt) => {
+            let jwks_uri = provider.jwks_uri.as_deref().ok_or_else(|| {
+                format!("{} has no jwks_uri to verify the id_token", provider.name)
+            })?;
+            if provider.issuers.is_empty() {
+                return Err(
+                    format!("{} has no issuer to verify the id_token", provider.name).into(),
+                );
+            }
+            Some(id_token::verify(idt, client_id, &provider.issuers, jwks_uri, &nonce).await?)
+        }
+        None => None,
+    };
 
     // Fetch userinfo
-    #[derive(Deserialize)]
-    struct UserInfo {
-        sub: Option<String>,
-        email: Option<String>,
-        name: Option<String>,
-        picture: Option<String>,
-    }
-    let userinfo = client
-        .get("https://openidconnect.googleapis.com/v1/userinfo")
-        .bearer_auth(&tok.access_token)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?
-        .json::<UserInfo>()
-        .await
-        .map_err(|e| e.to_string())?;
+    let userinfo = match &provider.userinfo_endpoint {
+        Some(url) => {
+            fetch_userinfo(&client, url, &tok.access_token, provider.userinfo_format).await?
+        }
+        None => claims
+            .as_ref()
+            .map(|c| UserInfo {
+                sub: c.sub.clone(),
+                email: c.email.clone(),
+                name: c.name.clone(),
+                picture: c.picture.clone(),
+            })
+            .unwrap_or_default(),
+    };
+    // userinfo must describe the same user the id_token was issued for
+    if let Some(id_sub) = claims.as_ref().and_then(|c| c.sub.as_deref()) {
+        if userinfo.sub.as_deref().is_some_and(|sub| sub != id_sub) {
+            return Err(TauraError::from(
+                "userinfo subject doesn't match the id_token".to_string(),
+            ));
+        }
+    }
 
     let expires_at = tok
         .expires_in
@@ -233,21 +1233,43 @@ pub async fn google_auth_start(
         sub: userinfo.sub.clone(),
         client_id: Some(client_id.to_string()),
         client_secret: client_secret_opt.map(|s| s.to_string()),
+        scope: tok.scope.clone(),
+        granted_scope: tok.scope,
+        provider: Some(provider.name.to_string()),
+        token_endpoint: Some(provider.token_endpoint.clone()),
+        revocation_endpoint: provider.revocation_endpoint.clone(),
     };
-    persist_session(&app, &session)?;
+    persist_session(app, &session)?;
+    // signing in with another account makes it the active one
+    settings::update(app, |s| s.active_account = Some(account_id(&session)))?;
+    REFRESH_TIMER_WAKE.notify_one();
     Ok(AuthResult { session })
 }
 
-async fn do_refresh(app: &tauri::AppHandle, mut existing: Session) -> Result<Session, String> {
+// Whether a token endpoint's error body says the grant itself is gone, which
+// no retry fixes.
+fn is_dead_grant(body: &str) -> bool {
+    #[derive(Deserialize)]
+    struct OAuthError {
+        error: String,
+    }
+    // GitHub names an expired refresh token its own way
+    serde_json::from_str::<OAuthError>(body)
+        .is_ok_and(|e| matches!(e.error.as_str(), "invalid_grant" | "bad_refresh_token"))
+}
+
+async fn do_refresh(app: &tauri::AppHandle, mut existing: Session) -> Result<Session, TauraError> {
+    // without these the session can't be renewed, only signed in again
     let refresh_token = existing
         .refresh_token
         .clone()
-        .ok_or_else(|| "no refresh_token present".to_string())?;
+        .ok_or_else(|| TauraError::not_authenticated("no refresh_token present"))?;
     let client_id = existing
         .client_id
         .clone()
-        .ok_or_else(|| "client_id missing from session".to_string())?;
+        .ok_or_else(|| TauraError::not_authenticated("client_id missing from session"))?;
     let client_secret = existing.client_secret.clone();
+    let url = token_endpoint(&existing).map_err(TauraError::not_authenticated)?;
 
     #[derive(Deserialize)]
     struct TokenResp {
@@ -267,22 +1289,37 @@ async fn do_refresh(app: &tauri::AppHandle, mut existing: Session) -> Result<Ses
     if let Some(cs) = client_secret.as_ref() {
         params_vec.push(("client_secret", cs.as_str()));
     }
-    let client = reqwest::Client::new();
-    let resp = client
-        .post("https://oauth2.googleapis.com/token")
+    let resp = http::client()
+        .post(&url)
+        .header(reqwest::header::ACCEPT, "application/json")
         .form(&params_vec)
         .send()
         .await
-        .map_err(|e| format!("refresh token request failed: {e}"))?;
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let body_txt = resp.text().await.unwrap_or_default();
-        return Err(format!("refresh failed: {} body={}", status, body_txt));
-    }
-    let tok = resp
-        .json::<TokenResp>()
+        .map_err(|e| TauraError::from(e).context("refresh token request failed"))?;
+    let status = resp.status();
+    let body_txt = resp
+        .text()
         .await
-        .map_err(|e| format!("refresh decode failed: {e}"))?;
+        .map_err(|e| TauraError::from(e).context("refresh token request failed"))?;
+    // GitHub reports a dead refresh token as 200 with an `error` body
+    if !status.is_success() || is_dead_grant(&body_txt) {
+        let message = format!(
+            "refresh failed: {} body={}",
+            status,
+            redact::redact(&body_txt)
+        );
+        return Err(if is_dead_grant(&body_txt) {
+            TauraError::not_authenticated(message)
+        } else {
+            TauraError::server(status, message)
+        });
+    }
+    let tok = serde_json::from_str::<TokenResp>(&body_txt).map_err(|e| {
+        TauraError::from(format!(
+            "refresh decode failed: {e} body={}",
+            redact::redact(&body_txt)
+        ))
+    })?;
 
     existing.access_token = tok.access_token;
     if let Some(rt) = tok.refresh_token {
@@ -292,25 +1329,230 @@ async fn do_refresh(app: &tauri::AppHandle, mut existing: Session) -> Result<Ses
     existing.expires_at = tok
         .expires_in
         .map(|s| chrono::Utc::now().timestamp() + s - 30);
+    if let Some(new_scope) = tok.scope {
+        let previous = split_scopes(
+            existing
+                .scope
+                .as_deref()
+                .or(existing.granted_scope.as_deref()),
+        );
+        let current = split_scopes(Some(&new_scope));
+        let dropped: Vec<String> = previous
+            .iter()
+            .filter(|s| !current.contains(s))
+            .cloned()
+            .collect();
+        if existing.granted_scope.is_none() {
+            existing.granted_scope = Some(new_scope.clone());
+        }
+        existing.scope = Some(new_scope);
+        if !dropped.is_empty() {
+            let _ = app.emit(
+                "scope_changed",
+                serde_json::json!({
+                  "previous": previous,
+                  "current": current,
+                  "dropped": dropped
+                }),
+            );
+        }
+    }
 
     persist_session(app, &existing)?;
     Ok(existing)
 }
 
+fn is_expiring(sess: &Session) -> bool {
+    let now = chrono::Utc::now().timestamp();
+    !matches!(sess.expires_at, Some(exp) if exp - now > 60)
+}
+
+// Runs `refresh` while holding `lock`, unless the session re-read under the
+// lock shows another caller already did the work.
+async fn serialized_refresh<L, N, R, F>(
+    lock: &tokio::sync::Mutex<()>,
+    load: L,
+    needs_refresh: N,
+    refresh: R,
+) -> Result<Session, TauraError>
+where
+    L: Fn() -> Option<Session>,
+    N: Fn(&Session) -> bool,
+    R: FnOnce(Session) -> F,
+    F: Future<Output = Result<Session, TauraError>>,
+{
+    let _guard = lock.lock().await;
+    let sess = load().ok_or_else(|| TauraError::not_authenticated("no session"))?;
+    if !needs_refresh(&sess) {
+        return Ok(sess);
+    }
+    refresh(sess).await
+}
+
+async fn ensure_fresh_with<L, R, F>(
+    lock: &tokio::sync::Mutex<()>,
+    load: L,
+    refresh: R,
+) -> Result<Session, TauraError>
+where
+    L: Fn() -> Option<Session>,
+    R: FnOnce(Session) -> F,
+    F: Future<Output = Result<Session, TauraError>>,
+{
+    let sess = load().ok_or_else(|| TauraError::not_authenticated("no session"))?;
+    if !is_expiring(&sess) {
+        return Ok(sess);
+    }
+    serialized_refresh(lock, load, is_expiring, refresh).await
+}
+
 #[tauri::command]
-pub async fn refresh_session(app: tauri::AppHandle) -> Result<Session, String> {
-    let sess = load_session(&app).ok_or_else(|| "no session".to_string())?;
-    do_refresh(&app, sess).await
+pub async fn refresh_session(app: tauri::AppHandle) -> Result<Session, TauraError> {
+    force_refresh(&app).await
 }
 
 #[tauri::command]
-pub async fn ensure_fresh_session(app: tauri::AppHandle) -> Result<Session, String> {
-    let sess = load_session(&app).ok_or_else(|| "no session".to_string())?;
-    let now = chrono::Utc::now().timestamp();
-    if let Some(exp) = sess.expires_at {
-        if exp - now > 60 {
-            return Ok(sess);
+pub async fn ensure_fresh_session(app: tauri::AppHandle) -> Result<Session, TauraError> {
+    fresh_session(&app).await
+}
+
+async fn force_refresh(app: &tauri::AppHandle) -> Result<Session, TauraError> {
+    let stale = load_session(app)
+        .ok_or_else(|| TauraError::not_authenticated("no session"))?
+        .access_token;
+    // a forced refresh is satisfied by any refresh that landed while we waited
+    serialized_refresh(
+        &REFRESH_LOCK,
+        || load_session(app),
+        |sess| sess.access_token == stale,
+        |sess| do_refresh(app, sess),
+    )
+    .await
+}
+
+async fn fresh_session(app: &tauri::AppHandle) -> Result<Session, TauraError> {
+    ensure_fresh_with(
+        &REFRESH_LOCK,
+        || load_session(app),
+        |sess| do_refresh(app, sess),
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn get_scope_status(app: tauri::AppHandle) -> Result<ScopeStatus, TauraError> {
+    let sess = load_session(&app).ok_or_else(|| TauraError::not_authenticated("no session"))?;
+    Ok(scope_status(&sess))
+}
+
+/// Access token for authenticated backend calls, refreshed if close to expiry.
+pub async fn access_token(app: &tauri::AppHandle) -> Result<String, TauraError> {
+    if load_session(app).is_none() {
+        return Err(TauraError::not_authenticated("not authenticated"));
+    }
+    fresh_session(app).await.map(|sess| sess.access_token)
+}
+
+/// Forces a refresh, used after the server rejects a token with 401.
+pub async fn refreshed_access_token(app: &tauri::AppHandle) -> Result<String, TauraError> {
+    force_refresh(app).await.map(|sess| sess.access_token)
+}
+
+/// Long-lived task spawned from `setup`. While auto refresh is enabled and a
+/// session exists it sleeps until a minute before `expires_at`, refreshes,
+/// and reschedules from the new expiry. Logout and sign-in wake it so it
+/// re-reads the session; with no session it idles until woken again.
+pub async fn run_refresh_timer(app: tauri::AppHandle) {
+    loop {
+        let expires_at = load_session(&app).and_then(|sess| sess.expires_at);
+        let (true, Some(exp)) = (AUTO_REFRESH.load(Ordering::SeqCst), expires_at) else {
+            REFRESH_TIMER_WAKE.notified().await;
+            continue;
+        };
+        let wait = (exp - chrono::Utc::now().timestamp() - 60).max(0) as u64;
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(wait)) => {}
+            _ = REFRESH_TIMER_WAKE.notified() => continue,
+        }
+        if !AUTO_REFRESH.load(Ordering::SeqCst) {
+            continue;
+        }
+        match fresh_session(&app).await {
+            Ok(sess) => {
+                let _ = app.emit(
+                    "session_refreshed",
+                    serde_json::json!({ "expires_at": sess.expires_at }),
+                );
+                // a fresh token is a good moment to drain anything queued offline
+                crate::sync_queue::flush_in_background(&app).await;
+            }
+            Err(TauraError::NotAuthenticated { .. }) if load_session(&app).is_none() => {}
+            // the grant is gone or was never there; another attempt can't fix it
+            Err(err @ TauraError::NotAuthenticated { .. }) => {
+                log::warn!("background refresh failed permanently: {}", err);
+                let _ = app.emit(
+                    "session_expired",
+                    serde_json::json!({ "error": err.message() }),
+                );
+                // don't hammer the token endpoint until something changes
+                REFRESH_TIMER_WAKE.notified().await;
+            }
+            Err(err) => {
+                log::warn!("background refresh failed, retrying: {}", err);
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(TIMER_RETRY_SECS)) => {}
+                    _ = REFRESH_TIMER_WAKE.notified() => {}
+                }
+            }
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn set_auto_refresh(enabled: bool) -> Result<(), TauraError> {
+    AUTO_REFRESH.store(enabled, Ordering::SeqCst);
+    REFRESH_TIMER_WAKE.notify_one();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_ensure_fresh_refreshes_once() {
+        let lock = Arc::new(tokio::sync::Mutex::new(()));
+        let store = Arc::new(Mutex::new(Session {
+            access_token: "old".into(),
+            expires_at: Some(chrono::Utc::now().timestamp() - 10),
+            ..Default::default()
+        }));
+        let token_requests = Arc::new(AtomicUsize::new(0));
+
+        let mut tasks = Vec::new();
+        for _ in 0..8 {
+            let (lock, store, token_requests) =
+                (lock.clone(), store.clone(), token_requests.clone());
+            tasks.push(tokio::spawn(async move {
+                let load = || Some(store.lock().unwrap().clone());
+                let refresh = |mut sess: Session| async {
+                    token_requests.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    sess.access_token = "new".into();
+                    sess.expires_at = Some(chrono::Utc::now().timestamp() + 3600);
+                    *store.lock().unwrap() = sess.clone();
+                    Ok(sess)
+                };
+                ensure_fresh_with(&lock, load, refresh).await
+            }));
+        }
+        for task in tasks {
+            let sess = task.await.unwrap().unwrap();
+            assert_eq!(sess.access_token, "new");
         }
+        assert_eq!(token_requests.load(Ordering::SeqCst), 1);
     }
-    do_refresh(&app, sess).await
 }
@@ -0,0 +1,63 @@
+use crate::error::TauraError;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+// Token per caller-chosen id, with the number of commands currently using it.
+// Several commands may share an id so the UI can abandon them as a unit.
+static OPERATIONS: Lazy<Mutex<HashMap<String, (CancellationToken, usize)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Keeps a network command cancellable by `op_id` for as long as it runs.
+/// Without an id the token exists but nothing can reach it.
+pub struct Operation {
+    id: Option<String>,
+    token: CancellationToken,
+}
+
+impl Operation {
+    pub fn register(op_id: Option<String>) -> Result<Self, TauraError> {
+        let Some(id) = op_id.filter(|id| !id.trim().is_empty()) else {
+            return Ok(Operation {
+                id: None,
+                token: CancellationToken::new(),
+            });
+        };
+        let mut ops = OPERATIONS.lock().map_err(|_| "lock poisoned".to_string())?;
+        let (token, users) = ops
+            .entry(id.clone())
+            .or_insert_with(|| (CancellationToken::new(), 0));
+        *users += 1;
+        Ok(Operation {
+            id: Some(id),
+            token: token.clone(),
+        })
+    }
+
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+}
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        let Some(id) = &self.id else { return };
+        if let Ok(mut ops) = OPERATIONS.lock() {
+            if let Some((_, users)) = ops.get_mut(id) {
+                *users -= 1;
+                if *users == 0 {
+                    ops.remove(id);
+                }
+            }
+        }
+    }
+}
+
+/// Cancels every command running under `op_id`; false if none is.
+pub fn cancel(op_id: &str) -> bool {
+    match OPERATIONS.lock() {
+        Ok(ops) => ops.get(op_id).map(|(token, _)| token.cancel()).is_some(),
+        Err(_) => false,
+    }
+}
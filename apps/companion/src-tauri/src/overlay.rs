@@ -0,0 +1,97 @@
+use crate::settings;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{Manager, PhysicalPosition, WebviewWindow};
+
+// Set by move events; drags fire many of them, so the position is written
+// once when the overlay is hidden rather than on every event.
+static MOVED: AtomicBool = AtomicBool::new(false);
+
+/// Where the user last left the overlay, relative to the monitor it was on
+/// so a rearranged desktop doesn't push it off-screen.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OverlayPosition {
+    pub monitor: Option<String>,
+    pub x: i32,
+    pub y: i32,
+}
+
+fn overlay_window(app: &tauri::AppHandle) -> Result<WebviewWindow, String> {
+    app.get_webview_window("overlay")
+        .ok_or_else(|| "overlay window missing".to_string())
+}
+
+/// Saves the overlay's current position and monitor.
+pub fn remember_position(app: &tauri::AppHandle) -> Result<(), String> {
+    let window = overlay_window(app)?;
+    let pos = window.outer_position().map_err(|e| e.to_string())?;
+    let monitor = window.current_monitor().map_err(|e| e.to_string())?;
+    let (name, origin) = match &monitor {
+        Some(m) => (m.name().cloned(), *m.position()),
+        None => (None, PhysicalPosition::new(0, 0)),
+    };
+    let saved = OverlayPosition {
+        monitor: name,
+        x: pos.x - origin.x,
+        y: pos.y - origin.y,
+    };
+    settings::update(app, |s| s.overlay_position = Some(saved))
+}
+
+pub fn mark_moved() {
+    MOVED.store(true, Ordering::SeqCst);
+}
+
+/// Saves the position if the overlay was moved since the last save.
+pub fn remember_if_moved(app: &tauri::AppHandle) {
+    if MOVED.swap(false, Ordering::SeqCst) {
+        if let Err(err) = remember_position(app) {
+            log::warn!("overlay position not saved: {}", err);
+        }
+    }
+}
+
+/// Moves the overlay to its saved spot before it is shown. Without one, or
+/// when the saved monitor is gone, it is centered on the primary display.
+pub fn restore_position(app: &tauri::AppHandle, window: &WebviewWindow) -> Result<(), String> {
+    let size = window.outer_size().map_err(|e| e.to_string())?;
+    let monitors = window.available_monitors().map_err(|e| e.to_string())?;
+    let saved = settings::load(app).overlay_position.and_then(|p| {
+        let monitor = monitors
+            .iter()
+            .find(|m| p.monitor.is_some() && m.name() == p.monitor.as_ref())?;
+        Some((monitor.clone(), p.x, p.y))
+    });
+    let (monitor, x, y) = match saved {
+        Some(found) => found,
+        None => {
+            let Some(primary) = window.primary_monitor().map_err(|e| e.to_string())? else {
+                return Ok(());
+            };
+            let x = (primary.size().width as i32 - size.width as i32) / 2;
+            let y = (primary.size().height as i32 - size.height as i32) / 2;
+            (primary, x, y)
+        }
+    };
+    // keep the whole window on that monitor even if its resolution shrank
+    let max_x = (monitor.size().width as i32 - size.width as i32).max(0);
+    let max_y = (monitor.size().height as i32 - size.height as i32).max(0);
+    let origin = monitor.position();
+    window
+        .set_position(PhysicalPosition::new(
+            origin.x + x.clamp(0, max_x),
+            origin.y + y.clamp(0, max_y),
+        ))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn save_overlay_position(app: tauri::AppHandle) -> Result<(), String> {
+    remember_position(&app)
+}
+
+/// Forgets the saved position so the overlay opens centered again.
+#[tauri::command]
+pub async fn reset_overlay_position(app: tauri::AppHandle) -> Result<(), String> {
+    settings::update(&app, |s| s.overlay_position = None)
+}
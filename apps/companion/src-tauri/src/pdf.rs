@@ -0,0 +1,323 @@
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+// Attributes a page may inherit from its ancestors in the page tree.
+const INHERITABLE: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+fn numbered_stem(path: &Path) -> Option<(String, u64)> {
+    let stem = path.file_stem()?.to_str()?;
+    let prefix = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+    let digits = &stem[prefix.len()..];
+    if digits.is_empty() {
+        return None;
+    }
+    Some((prefix.to_lowercase(), digits.parse().ok()?))
+}
+
+/// Groups numbered PDFs (`scan_001.pdf`, `scan_002.pdf`, ...) that share a
+/// directory and prefix into runs of consecutive numbers. Only runs of two or
+/// more files are returned, each ordered by number.
+pub fn find_pdf_sequences<'a>(paths: impl IntoIterator<Item = &'a str>) -> Vec<Vec<String>> {
+    let mut groups: HashMap<(PathBuf, String), Vec<(u64, String)>> = HashMap::new();
+    for raw in paths {
+        let path = Path::new(raw);
+        let is_pdf = path
+            .extension()
+            .and_then(|s| s.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"));
+        if !is_pdf {
+            continue;
+        }
+        if let Some((prefix, n)) = numbered_stem(path) {
+            let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            groups
+                .entry((dir, prefix))
+                .or_default()
+                .push((n, raw.to_string()));
+        }
+    }
+    let mut runs = Vec::new();
+    for (_, mut members) in groups {
+        members.sort();
+        let mut run: Vec<String> = Vec::new();
+        let mut last: Option<u64> = None;
+        for (n, p) in members {
+            if last.is_some_and(|l| n != l + 1) {
+                if run.len() > 1 {
+                    runs.push(std::mem::take(&mut run));
+                }
+                run.clear();
+            }
+            run.push(p);
+            last = Some(n);
+        }
+        if run.len() > 1 {
+            runs.push(run);
+        }
+    }
+    runs.sort();
+    runs
+}
+
+// Copies inheritable attributes from the page tree onto the page itself so it
+// keeps rendering correctly once re-parented.
+fn flatten_page(doc: &Document, page_id: ObjectId) -> Result<Dictionary, String> {
+    let mut page = doc
+        .get_dictionary(page_id)
+        .map_err(|e| e.to_string())?
+        .clone();
+    let mut parent = page.get(b"Parent").and_then(Object::as_reference).ok();
+    let mut depth = 0;
+    while let Some(pid) = parent {
+        depth += 1;
+        if depth > 64 {
+            break;
+        }
+        let Ok(node) = doc.get_dictionary(pid) else {
+            break;
+        };
+        for key in INHERITABLE {
+            if !page.has(key) {
+                if let Ok(v) = node.get(key) {
+                    page.set(key, v.clone());
+                }
+            }
+        }
+        parent = node.get(b"Parent").and_then(Object::as_reference).ok();
+    }
+    Ok(page)
+}
+
+/// Concatenates the pages of `paths`, in order, into a single PDF at `out`.
+pub fn merge_pdfs(paths: &[String], out: &Path) -> Result<(), String> {
+    let mut max_id = 1;
+    let mut pages: Vec<(ObjectId, Dictionary)> = Vec::new();
+    let mut objects: BTreeMap<ObjectId, Object> = BTreeMap::new();
+    for p in paths {
+        let mut doc = Document::load(p).map_err(|e| format!("{}: {}", p, e))?;
+        if doc.is_encrypted() {
+            return Err(format!("{}: encrypted", p));
+        }
+        doc.renumber_objects_with(max_id);
+        max_id = doc.max_id + 1;
+        for page_id in doc.get_pages().into_values() {
+            pages.push((page_id, flatten_page(&doc, page_id)?));
+        }
+        objects.extend(doc.objects);
+    }
+
+    let mut merged = Document::with_version("1.5");
+    let mut catalog: Option<(ObjectId, Dictionary)> = None;
+    for (id, object) in objects {
+        match object.type_name().unwrap_or("") {
+            "Catalog" => {
+                if catalog.is_none() {
+                    catalog = object.as_dict().ok().map(|d| (id, d.clone()));
+                }
+            }
+            // the page tree is rebuilt below with inherited attributes already
+            // flattened; outlines would point at dropped objects
+            "Pages" | "Page" | "Outlines" | "Outline" => {}
+            _ => {
+                merged.objects.insert(id, object);
+            }
+        }
+    }
+    let (catalog_id, mut catalog_dict) = catalog.ok_or("catalog not found")?;
+    let pages_id = catalog_dict
+        .get(b"Pages")
+        .and_then(Object::as_reference)
+        .map_err(|e| e.to_string())?;
+    let mut pages_dict = Dictionary::new();
+    pages_dict.set("Type", "Pages");
+
+    let mut kids = Vec::with_capacity(pages.len());
+    for (page_id, mut page) in pages {
+        page.set("Parent", pages_id);
+        merged.objects.insert(page_id, Object::Dictionary(page));
+        kids.push(Object::Reference(page_id));
+    }
+    pages_dict.set("Count", kids.len() as u32);
+    pages_dict.set("Kids", kids);
+    merged
+        .objects
+        .insert(pages_id, Object::Dictionary(pages_dict));
+    catalog_dict.set("Pages", pages_id);
+    catalog_dict.remove(b"Outlines");
+    merged
+        .objects
+        .insert(catalog_id, Object::Dictionary(catalog_dict));
+    merged.trailer.set("Root", catalog_id);
+    merged.max_id = max_id;
+    merged.renumber_objects();
+
+    if let Some(parent) = out.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    merged.save(out).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Above this size a PDF whose xref we can't walk by hand is skipped rather
+// than loaded whole.
+const FULL_LOAD_LIMIT: u64 = 32 * 1024 * 1024;
+const MAX_XREF_SECTIONS: usize = 16;
+
+fn read_at(file: &mut File, offset: u64, len: usize) -> Option<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    let mut buf = Vec::with_capacity(len);
+    file.take(len as u64).read_to_end(&mut buf).ok()?;
+    Some(buf)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn skip_ws(buf: &[u8], mut at: usize) -> usize {
+    while buf.get(at).is_some_and(|b| b.is_ascii_whitespace()) {
+        at += 1;
+    }
+    at
+}
+
+fn parse_uint(buf: &[u8], at: usize) -> Option<(u64, usize)> {
+    let start = skip_ws(buf, at);
+    let mut end = start;
+    while buf.get(end).is_some_and(u8::is_ascii_digit) {
+        end += 1;
+    }
+    let n = std::str::from_utf8(&buf[start..end]).ok()?.parse().ok()?;
+    Some((n, end))
+}
+
+// Integer value of a dictionary key such as `/Count 12` or `/Root 5 0 R`,
+// making sure `/Pages` doesn't match `/PagesFoo`.
+fn key_uint(buf: &[u8], key: &[u8]) -> Option<u64> {
+    let mut from = 0;
+    while let Some(rel) = find(&buf[from..], key) {
+        let after = from + rel + key.len();
+        if !buf.get(after).is_some_and(u8::is_ascii_alphanumeric) {
+            return parse_uint(buf, after).map(|(n, _)| n);
+        }
+        from = after;
+    }
+    None
+}
+
+struct ClassicXref {
+    // (first object number, entry count, file offset of the first entry)
+    sections: Vec<(u64, u64, u64)>,
+    trailer: Vec<u8>,
+}
+
+fn read_classic_xref(file: &mut File, offset: u64) -> Option<ClassicXref> {
+    let head = read_at(file, offset, 4)?;
+    if head != b"xref" {
+        return None;
+    }
+    let mut pos = offset + 4;
+    let mut sections = Vec::new();
+    for _ in 0..4096 {
+        let chunk = read_at(file, pos, 64)?;
+        let at = skip_ws(&chunk, 0);
+        if chunk[at..].starts_with(b"trailer") {
+            let trailer = read_at(file, pos + at as u64, 2048)?;
+            return Some(ClassicXref { sections, trailer });
+        }
+        let (first, next) = parse_uint(&chunk, at)?;
+        let (count, next) = parse_uint(&chunk, next)?;
+        // entries start on the line after the subsection header
+        let entries = pos + skip_ws(&chunk, next) as u64;
+        sections.push((first, count, entries));
+        // counts come from the file; a corrupt one must not wrap
+        pos = count.checked_mul(20)?.checked_add(entries)?;
+    }
+    None
+}
+
+fn classic_offset(file: &mut File, xref: &ClassicXref, obj: u64) -> Option<u64> {
+    let &(first, _, entries) = xref.sections.iter().find(|(first, count, _)| {
+        first
+            .checked_add(*count)
+            .is_some_and(|end| (*first..end).contains(&obj))
+    })?;
+    let at = (obj - first).checked_mul(20)?.checked_add(entries)?;
+    let entry = read_at(file, at, 18)?;
+    if entry.get(17) != Some(&b'n') {
+        return None;
+    }
+    parse_uint(&entry, 0).map(|(n, _)| n)
+}
+
+fn read_object(file: &mut File, offset: u64) -> Option<Vec<u8>> {
+    let mut buf = read_at(file, offset, 64 * 1024)?;
+    if let Some(end) = find(&buf, b"endobj") {
+        buf.truncate(end);
+    }
+    Some(buf)
+}
+
+// Follows startxref through classic cross-reference tables (including /Prev
+// chains from incremental saves) to the page tree root's /Count.
+fn count_via_classic_xref(file: &mut File, len: u64) -> Option<u32> {
+    let tail_len = len.min(2048);
+    let tail = read_at(file, len - tail_len, tail_len as usize)?;
+    let at = tail.windows(9).rposition(|w| w == b"startxref")?;
+    let (mut offset, _) = parse_uint(&tail, at + 9)?;
+
+    let mut tables = Vec::new();
+    let mut root = None;
+    for _ in 0..MAX_XREF_SECTIONS {
+        let xref = read_classic_xref(file, offset)?;
+        if find(&xref.trailer, b"/Encrypt").is_some() {
+            return None;
+        }
+        root = root.or_else(|| key_uint(&xref.trailer, b"/Root"));
+        let prev = key_uint(&xref.trailer, b"/Prev");
+        tables.push(xref);
+        match prev {
+            Some(p) => offset = p,
+            None => break,
+        }
+    }
+    // newest table wins when an object was rewritten
+    let locate = |file: &mut File, obj: u64| {
+        tables
+            .iter()
+            .find_map(|xref| classic_offset(file, xref, obj))
+    };
+    let catalog_at = locate(file, root?)?;
+    let pages = key_uint(&read_object(file, catalog_at)?, b"/Pages")?;
+    let pages_at = locate(file, pages)?;
+    let count = key_uint(&read_object(file, pages_at)?, b"/Count")?;
+    u32::try_from(count).ok()
+}
+
+/// Number of pages in a PDF, or None when it is encrypted or can't be read
+/// cheaply. Linearized files and classic xref tables are read with a few
+/// small seeks; other layouts fall back to a full parse for files up to 32MB.
+pub fn page_count(path: &Path) -> Option<u32> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    let head = read_at(&mut file, 0, 1024)?;
+    if find(&head, b"/Linearized").is_some() {
+        if let Some(n) = key_uint(&head, b"/N") {
+            return u32::try_from(n).ok();
+        }
+    }
+    if let Some(n) = count_via_classic_xref(&mut file, len) {
+        return Some(n);
+    }
+    if len > FULL_LOAD_LIMIT {
+        return None;
+    }
+    let doc = Document::load(path).ok()?;
+    if doc.is_encrypted() {
+        return None;
+    }
+    u32::try_from(doc.get_pages().len()).ok()
+}
@@ -0,0 +1,176 @@
+use crate::{local_path, pdf, SyncErrorItem, SyncPayloadItem};
+use lopdf::Document;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const DEFAULT_MAX_PAGES: u32 = 50;
+const MAX_PAGES: u32 = 500;
+const DEFAULT_PAGE_EDGE: u32 = 1024;
+const PAGE_QUALITY: u8 = 80;
+// lopdf parses the whole file to read any text; bigger PDFs only get images
+const TEXT_LOAD_LIMIT: u64 = 64 * 1024 * 1024;
+// per page, so one dense page can't swamp a sync batch
+const MAX_PAGE_TEXT_CHARS: usize = 16_000;
+
+/// How `sync_index` splits PDFs into pages. Every field is optional.
+#[derive(Deserialize, Serialize, Clone, Copy, Default)]
+pub struct PageSplit {
+    /// Pages sent per PDF, from the first; default 50 and at most 500.
+    pub max_pages: Option<u32>,
+    /// Longest edge of each rendered page; default 1024.
+    pub max_edge: Option<u32>,
+    /// Send page images; default true. Needs poppler's `pdftoppm`.
+    pub render: Option<bool>,
+    /// Send the text embedded in each page; default true.
+    pub text: Option<bool>,
+}
+
+/// The pdftoppm binary: `TAURA_PDFTOPPM` when set, otherwise whatever
+/// `pdftoppm` resolves to on PATH.
+fn pdftoppm() -> PathBuf {
+    std::env::var_os("TAURA_PDFTOPPM")
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("pdftoppm"))
+}
+
+// A pdftoppm run that doesn't flash a console window on Windows.
+fn command() -> Command {
+    #[allow(unused_mut)]
+    let mut cmd = Command::new(pdftoppm());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+    cmd
+}
+
+fn page_uri(uri: &str, page: u32) -> String {
+    format!("{}#page={}", uri, page)
+}
+
+/// The URI a page URI was split from; other URIs come back unchanged.
+pub fn base_uri(uri: &str) -> &str {
+    match uri.rsplit_once("#page=") {
+        Some((base, page)) if page.parse::<u32>().is_ok() => base,
+        _ => uri,
+    }
+}
+
+/// The PDF a page item was split from, or the item's own file otherwise.
+pub fn source_path(item: &SyncPayloadItem) -> PathBuf {
+    let uri = item.uri.trim();
+    let uri = match item.page {
+        Some(page) => uri.strip_suffix(&format!("#page={}", page)).unwrap_or(uri),
+        None => uri,
+    };
+    local_path(uri)
+}
+
+/// Replaces each whole-PDF item with one item per page, numbered from 1 and
+/// addressed as `<uri>#page=<n>`. Only the page count is read here; images
+/// and text are filled per batch by `fill_pages`. PDFs whose pages can't be
+/// counted, e.g. encrypted ones, stay whole.
+pub fn split_pages(items: Vec<SyncPayloadItem>, split: &PageSplit) -> Vec<SyncPayloadItem> {
+    let max_pages = split
+        .max_pages
+        .unwrap_or(DEFAULT_MAX_PAGES)
+        .clamp(1, MAX_PAGES);
+    let mut out = Vec::with_capacity(items.len());
+    for item in items {
+        if item.modality != "pdf_page" || item.page.is_some() {
+            out.push(item);
+            continue;
+        }
+        let Some(count) = pdf::page_count(&source_path(&item)).filter(|n| *n > 0) else {
+            out.push(item);
+            continue;
+        };
+        for page in 1..=count.min(max_pages) {
+            let mut page_item = item.clone();
+            page_item.uri = page_uri(&item.uri, page);
+            page_item.page = Some(page);
+            // the whole file's bytes don't belong on a page
+            page_item.bytes_b64 = None;
+            out.push(page_item);
+        }
+    }
+    out
+}
+
+/// Renders one page to JPEG, fitted into `max_edge`.
+pub fn render_page(path: &Path, page: u32, max_edge: u32) -> Result<Vec<u8>, String> {
+    let out = command()
+        .args(["-f", &page.to_string(), "-l", &page.to_string()])
+        .args(["-scale-to", &max_edge.to_string(), "-singlefile"])
+        .args(["-jpeg", "-jpegopt", &format!("quality={}", PAGE_QUALITY)])
+        .arg(path)
+        .output()
+        .map_err(|e| format!("could not run pdftoppm: {}", e))?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        return Err(format!("pdftoppm failed: {}", stderr.trim()));
+    }
+    if out.stdout.is_empty() {
+        return Err(format!("page {} rendered empty", page));
+    }
+    Ok(out.stdout)
+}
+
+fn page_text(doc: &Document, page: u32) -> Option<String> {
+    let text = doc.extract_text(&[page]).ok()?;
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        return None;
+    }
+    Some(text.chars().take(MAX_PAGE_TEXT_CHARS).collect())
+}
+
+fn load_for_text(path: &Path) -> Option<Document> {
+    if std::fs::metadata(path).ok()?.len() > TEXT_LOAD_LIMIT {
+        return None;
+    }
+    Document::load(path).ok().filter(|doc| !doc.is_encrypted())
+}
+
+/// Fills the image (`bytes_b64`) and `text` of page items from
+/// `split_pages`. A page that can't be rendered is reported and still sent
+/// with whatever text it has.
+pub fn fill_pages(items: &mut [SyncPayloadItem], split: &PageSplit) -> Vec<SyncErrorItem> {
+    use base64::Engine;
+    let max_edge = split.max_edge.unwrap_or(DEFAULT_PAGE_EDGE).clamp(64, 4096);
+    let render = split.render.unwrap_or(true);
+    let text = split.text.unwrap_or(true);
+    let mut errors = Vec::new();
+    // pages of one PDF sit next to each other, so one parse serves them all
+    let mut loaded: Option<(PathBuf, Option<Document>)> = None;
+    for item in items.iter_mut() {
+        let Some(page) = item.page else {
+            continue;
+        };
+        let path = source_path(item);
+        if render && item.bytes_b64.is_none() {
+            match render_page(&path, page, max_edge) {
+                Ok(jpeg) => {
+                    item.bytes_b64 = Some(base64::engine::general_purpose::STANDARD.encode(jpeg))
+                }
+                Err(err) => errors.push(SyncErrorItem {
+                    uri: item.uri.clone(),
+                    error: err,
+                }),
+            }
+        }
+        if text && item.text.is_none() {
+            if loaded.as_ref().map_or(true, |(p, _)| *p != path) {
+                loaded = Some((path.clone(), load_for_text(&path)));
+            }
+            if let Some((_, Some(doc))) = &loaded {
+                item.text = page_text(doc, page);
+            }
+        }
+    }
+    errors
+}
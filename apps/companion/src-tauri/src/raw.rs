@@ -0,0 +1,152 @@
+use crate::exif::Tiff;
+use std::path::Path;
+
+/// Camera RAW extensions indexed as images.
+pub const EXTENSIONS: &[&str] = &["cr2", "cr3", "nef", "arw", "dng", "raf"];
+
+const TAG_NEW_SUBFILE_TYPE: u16 = 0x00FE;
+const TAG_COMPRESSION: u16 = 0x0103;
+const TAG_STRIP_OFFSETS: u16 = 0x0111;
+const TAG_STRIP_BYTE_COUNTS: u16 = 0x0117;
+const TAG_SUB_IFDS: u16 = 0x014A;
+const TAG_JPEG_OFFSET: u16 = 0x0201;
+const TAG_JPEG_LENGTH: u16 = 0x0202;
+const RAF_MAGIC: &[u8] = b"FUJIFILMCCD-RAW";
+// bounds the walk on corrupt or hostile files
+const MAX_IFDS: usize = 32;
+
+pub fn is_raw(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Offset of the embedded JPEG in a Fujifilm RAF, whose header stores it at
+/// a fixed position.
+pub fn raf_jpeg(buf: &[u8]) -> Option<(usize, usize)> {
+    if !buf.starts_with(RAF_MAGIC) {
+        return None;
+    }
+    let be = |at: usize| {
+        buf.get(at..at + 4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+    };
+    Some((be(84)?, be(88)?))
+}
+
+// Only baseline and progressive JPEGs decode; CR2 and DNG also store the
+// sensor data as lossless JPEG, which looks the same from the IFD.
+fn displayable_jpeg(data: &[u8]) -> bool {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return false;
+    }
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            return false;
+        }
+        match data[pos + 1] {
+            0xC0..=0xC2 => return true,
+            0xC3..=0xCF if data[pos + 1] != 0xC4 && data[pos + 1] != 0xCC => return false,
+            0xDA | 0xD9 => return false,
+            _ => {}
+        }
+        let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        pos += 2 + len;
+    }
+    false
+}
+
+fn tiff_value(tiff: &Tiff, typ: u16, pos: usize) -> Option<usize> {
+    match typ {
+        3 => tiff.u16_at(pos).map(usize::from),
+        4 => tiff.u32_at(pos).map(|v| v as usize),
+        _ => None,
+    }
+}
+
+// Every JPEG referenced from the IFD0 chain or its SubIFDs.
+fn tiff_previews(buf: &[u8]) -> Vec<(usize, usize)> {
+    let mut found = Vec::new();
+    let Some(tiff) = Tiff::new(buf, 0) else {
+        return found;
+    };
+    let mut ifds: Vec<usize> = tiff.u32_at(4).map(|o| o as usize).into_iter().collect();
+    let mut visited = Vec::new();
+    while let Some(ifd) = ifds.pop() {
+        if ifd == 0 || visited.contains(&ifd) || visited.len() >= MAX_IFDS {
+            continue;
+        }
+        visited.push(ifd);
+        let entries = tiff.entries(ifd);
+        if let Some(next) = tiff.u32_at(ifd + 2 + entries.len() * 12) {
+            ifds.push(next as usize);
+        }
+        let (mut jpeg_offset, mut jpeg_length) = (None, None);
+        let (mut strip_offset, mut strip_length) = (None, None);
+        let (mut compression, mut subfile) = (None, None);
+        for (tag, typ, count, pos) in entries {
+            match tag {
+                TAG_SUB_IFDS if count == 1 => ifds.extend(tiff.u32_at(pos).map(|o| o as usize)),
+                TAG_SUB_IFDS => {
+                    if let Some(list) = tiff.u32_at(pos) {
+                        let list = list as usize;
+                        // a corrupt count would otherwise queue billions of reads
+                        ifds.extend(
+                            (0..(count as usize).min(MAX_IFDS))
+                                .filter_map(|i| tiff.u32_at(list + i * 4))
+                                .map(|o| o as usize),
+                        );
+                    }
+                }
+                TAG_JPEG_OFFSET => jpeg_offset = tiff_value(&tiff, typ, pos),
+                TAG_JPEG_LENGTH => jpeg_length = tiff_value(&tiff, typ, pos),
+                // a single strip holds the whole image; multi-strip IFDs are raw data
+                TAG_STRIP_OFFSETS if count == 1 => strip_offset = tiff_value(&tiff, typ, pos),
+                TAG_STRIP_BYTE_COUNTS if count == 1 => strip_length = tiff_value(&tiff, typ, pos),
+                TAG_COMPRESSION => compression = tiff_value(&tiff, typ, pos),
+                TAG_NEW_SUBFILE_TYPE => subfile = tiff_value(&tiff, typ, pos),
+                _ => {}
+            }
+        }
+        if let (Some(off), Some(len)) = (jpeg_offset, jpeg_length) {
+            found.push((off, len));
+        }
+        // 6 is old-style JPEG (CR2), 7 is JPEG (DNG previews, subfile type 1)
+        let jpeg_strip = compression == Some(6) || (compression == Some(7) && subfile == Some(1));
+        if let (true, Some(off), Some(len)) = (jpeg_strip, strip_offset, strip_length) {
+            found.push((off, len));
+        }
+    }
+    found
+}
+
+// CR3 is ISO-BMFF; the preview sits in a `PRVW` box right after a short header.
+fn cr3_preview(buf: &[u8]) -> Option<(usize, usize)> {
+    let tag = buf.windows(4).position(|w| w == b"PRVW")?;
+    let start = tag.checked_sub(4)?;
+    let size = u32::from_be_bytes(buf.get(start..tag)?.try_into().ok()?) as usize;
+    let end = (start + size).min(buf.len());
+    let search_end = (tag + 64).min(end);
+    let soi = tag
+        + buf
+            .get(tag..search_end)?
+            .windows(3)
+            .position(|w| w == [0xFF, 0xD8, 0xFF])?;
+    Some((soi, end - soi))
+}
+
+/// The largest displayable JPEG embedded in a RAW file. Nearly every camera
+/// writes one, so previews never need the sensor data demosaiced.
+pub fn preview_jpeg(buf: &[u8]) -> Option<&[u8]> {
+    let mut candidates = tiff_previews(buf);
+    candidates.extend(raf_jpeg(buf));
+    if candidates.is_empty() {
+        candidates.extend(cr3_preview(buf));
+    }
+    candidates
+        .into_iter()
+        .filter_map(|(off, len)| buf.get(off..off.checked_add(len)?))
+        .filter(|data| displayable_jpeg(data))
+        .max_by_key(|data| data.len())
+}
@@ -0,0 +1,51 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+const MASK: &str = "[redacted]";
+
+// `"access_token": "…"`, `refresh_token=…` and friends, in JSON or form
+// bodies. Only whole keys count, so `code` leaves `code_challenge_method` or
+// `error-code` alone.
+static SECRET_FIELD: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?i)((?:^|[?&\s{,])"?(?:access_token|refresh_token|id_token|client_secret|code_verifier|code|assertion|password)"?\s*[:=]\s*"?)[^"&,\s}]+"#,
+    )
+    .expect("valid regex")
+});
+static BEARER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)(\bbearer\s+)[A-Za-z0-9\-._~+/]+=*").expect("valid regex"));
+// bare JWTs and Google's access (ya29.) and refresh (1//) token shapes
+static BARE_TOKEN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\beyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]*|\bya29\.[A-Za-z0-9_-]+|\b1//[A-Za-z0-9_-]+")
+        .expect("valid regex")
+});
+
+/// Masks anything in `text` that looks like a credential, so provider error
+/// bodies can be logged or shown without leaking tokens.
+pub fn redact(text: &str) -> String {
+    let text = SECRET_FIELD.replace_all(text, format!("${{1}}{}", MASK));
+    let text = BEARER.replace_all(&text, format!("${{1}}{}", MASK));
+    BARE_TOKEN.replace_all(&text, MASK).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_only_whole_secret_keys() {
+        assert_eq!(
+            redact("code=abc&code_challenge_method=S256&state=xyz"),
+            "code=[redacted]&code_challenge_method=S256&state=xyz"
+        );
+        assert_eq!(
+            redact("redirect?error-code=42&code_verifier=v1"),
+            "redirect?error-code=42&code_verifier=[redacted]"
+        );
+        assert_eq!(
+            redact(r#"{"error":"invalid_grant","refresh_token":"1x"}"#),
+            r#"{"error":"invalid_grant","refresh_token":"[redacted]"}"#
+        );
+        assert_eq!(redact("error_code: 7"), "error_code: 7");
+    }
+}
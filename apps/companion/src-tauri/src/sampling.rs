@@ -0,0 +1,75 @@
+use crate::error::TauraError;
+use rand::seq::IteratorRandom;
+
+/// How `scan_folder` picks the preview samples out of the matched items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleStrategy {
+    /// The first items in walk order.
+    First,
+    /// A uniform random subset, reservoir-sampled.
+    Random,
+    /// Round-robin across modalities so each one found shows up.
+    ByModality,
+}
+
+impl SampleStrategy {
+    pub fn parse(raw: Option<&str>) -> Result<Self, TauraError> {
+        match raw.map(str::trim).filter(|s| !s.is_empty()) {
+            None | Some("first") => Ok(Self::First),
+            Some("random") => Ok(Self::Random),
+            Some("by_modality") => Ok(Self::ByModality),
+            Some(other) => Err(TauraError::invalid_input(format!(
+                "unknown sample_strategy \"{}\" (expected first, random or by_modality)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Indices of up to `limit` items chosen by `strategy`, in walk order.
+pub fn pick<T>(
+    items: &[T],
+    limit: usize,
+    strategy: SampleStrategy,
+    modality: impl Fn(&T) -> &str,
+) -> Vec<usize> {
+    let mut picked = match strategy {
+        SampleStrategy::First => (0..items.len().min(limit)).collect(),
+        SampleStrategy::Random => (0..items.len()).choose_multiple(&mut rand::thread_rng(), limit),
+        SampleStrategy::ByModality => round_robin(items, limit, modality),
+    };
+    picked.sort_unstable();
+    picked
+}
+
+// Groups keep the order modalities were first seen in, so a folder that is
+// mostly photos still leads with them.
+fn round_robin<T>(items: &[T], limit: usize, modality: impl Fn(&T) -> &str) -> Vec<usize> {
+    let mut groups: Vec<(&str, Vec<usize>)> = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        let m = modality(item);
+        match groups.iter_mut().find(|(name, _)| *name == m) {
+            Some((_, idx)) if idx.len() < limit => idx.push(i),
+            Some(_) => {}
+            None => groups.push((m, vec![i])),
+        }
+    }
+    let mut picked = Vec::with_capacity(limit);
+    let mut round = 0;
+    while picked.len() < limit {
+        let before = picked.len();
+        for (_, idx) in &groups {
+            if picked.len() == limit {
+                break;
+            }
+            if let Some(&i) = idx.get(round) {
+                picked.push(i);
+            }
+        }
+        if picked.len() == before {
+            break;
+        }
+        round += 1;
+    }
+    picked
+}
@@ -0,0 +1,177 @@
+use crate::catalog::KnownHashes;
+use crate::manifest::ScanManifest;
+use crate::{
+    content_hash, extension_allowed, media_meta, media_modality, visit_key, MediaMeta, VisitKey,
+};
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc as async_mpsc;
+
+pub const MAX_CONCURRENCY: usize = 32;
+// entries queued per worker; small, so a paused or throttled scan stops the
+// walk soon after it stops reading results
+const QUEUE_PER_WORKER: usize = 4;
+
+/// Workers used when the caller doesn't say: per-file work is mostly waiting
+/// on the disk, so a few more than the core count still helps on network
+/// shares.
+pub fn default_concurrency() -> usize {
+    std::thread::available_parallelism()
+        .map_or(4, |n| n.get())
+        .clamp(2, 8)
+}
+
+/// What every worker needs to judge a file, shared read-only.
+pub struct FileRules {
+    pub sniff: bool,
+    pub allowed_extensions: Vec<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub modified_after: Option<DateTime<Utc>>,
+    pub modified_before: Option<DateTime<Utc>>,
+    /// Empty when the scan has no manifest.
+    pub previous_manifest: Arc<ScanManifest>,
+    pub hash_content: bool,
+    /// Catalogued hashes, reused for files whose size and mtime still match.
+    pub known_hashes: Arc<KnownHashes>,
+}
+
+/// What became of one walked file, or a walk error.
+pub enum Outcome {
+    /// Not media, or an extension the settings leave out.
+    Ignored,
+    /// The same file already came through another mount, junction or link.
+    Duplicate,
+    /// Media outside the requested size/date window.
+    OutOfRange,
+    Unchanged {
+        key: String,
+        fingerprint: (u64, Option<String>),
+    },
+    Matched {
+        key: String,
+        fingerprint: (u64, Option<String>),
+        modality: &'static str,
+        /// None for paths the index can't address.
+        meta: Option<Box<MediaMeta>>,
+        /// Spent reading metadata and hashing, on this worker.
+        elapsed: Duration,
+    },
+    WalkError(walkdir::Error),
+}
+
+fn inspect(
+    entry: &walkdir::DirEntry,
+    rules: &FileRules,
+    seen: &Mutex<HashSet<VisitKey>>,
+) -> Outcome {
+    let p = entry.path();
+    let allowed = extension_allowed(p, &rules.allowed_extensions);
+    let Some(modality) = media_modality(p, rules.sniff).filter(|_| allowed) else {
+        return Outcome::Ignored;
+    };
+    if visit_key(entry).is_some_and(|key| !seen.lock().unwrap().insert(key)) {
+        return Outcome::Duplicate;
+    }
+    let mut size: u64 = 0;
+    let mut modified_dt: Option<DateTime<Utc>> = None;
+    if let Ok(md) = entry.metadata() {
+        size = md.len();
+        if let Ok(mt) = md.modified() {
+            modified_dt = Some(mt.into());
+        }
+    }
+    // files outside the requested size/date window are processed but not matched
+    let in_range = rules.min_size.map_or(true, |min| size >= min)
+        && rules.max_size.map_or(true, |max| size <= max)
+        && rules
+            .modified_after
+            .map_or(true, |after| modified_dt.is_some_and(|m| m >= after))
+        && rules
+            .modified_before
+            .map_or(true, |before| modified_dt.is_some_and(|m| m <= before));
+    if !in_range {
+        return Outcome::OutOfRange;
+    }
+    let modified = modified_dt.map(|dt| dt.to_rfc3339());
+    let key = p.to_string_lossy().to_string();
+    let fingerprint = (size, modified.clone());
+    if rules.previous_manifest.get(&key) == Some(&fingerprint) {
+        return Outcome::Unchanged { key, fingerprint };
+    }
+    let started = Instant::now();
+    let mut meta = media_meta(p, modality, size, modified);
+    if let Some(meta) = meta.as_mut().filter(|_| rules.hash_content) {
+        let known = rules
+            .known_hashes
+            .get(&key)
+            .filter(|(at, _)| *at == fingerprint)
+            .map(|(_, hash)| hash.clone());
+        match known.map_or_else(|| content_hash::hash_file(p), Ok) {
+            Ok(hash) => meta.hash = Some(hash),
+            // unhashed files are still listed, just never deduplicated
+            Err(err) => log::warn!("could not hash {}: {}", meta.path, err),
+        }
+    }
+    Outcome::Matched {
+        key,
+        fingerprint,
+        modality,
+        meta: meta.map(Box::new),
+        elapsed: started.elapsed(),
+    }
+}
+
+/// Walks on one thread and inspects files on `concurrency` others. Results
+/// arrive in completion order, so walk order is only kept with a single
+/// worker. Every queue is bounded: a consumer that stops reading, e.g. while
+/// paused or throttled, soon stops the walk too, and dropping the receiver
+/// winds all threads down.
+pub fn spawn<I>(walker: I, rules: FileRules, concurrency: usize) -> async_mpsc::Receiver<Outcome>
+where
+    I: Iterator<Item = walkdir::Result<walkdir::DirEntry>> + Send + 'static,
+{
+    let capacity = concurrency * QUEUE_PER_WORKER;
+    let (out_tx, out_rx) = async_mpsc::channel(capacity);
+    let (file_tx, file_rx) = mpsc::sync_channel::<walkdir::DirEntry>(capacity);
+    let file_rx = Arc::new(Mutex::new(file_rx));
+    let rules = Arc::new(rules);
+    let seen_files: Arc<Mutex<HashSet<VisitKey>>> = Arc::new(Mutex::new(HashSet::new()));
+    for _ in 0..concurrency {
+        let file_rx = file_rx.clone();
+        let out_tx = out_tx.clone();
+        let rules = rules.clone();
+        let seen_files = seen_files.clone();
+        std::thread::spawn(move || loop {
+            // held only while waiting for the next entry
+            let next = file_rx.lock().unwrap().recv();
+            let Ok(entry) = next else {
+                break;
+            };
+            if out_tx
+                .blocking_send(inspect(&entry, &rules, &seen_files))
+                .is_err()
+            {
+                break;
+            }
+        });
+    }
+    std::thread::spawn(move || {
+        for entry in walker {
+            if out_tx.is_closed() {
+                break;
+            }
+            let sent = match entry {
+                Ok(e) if e.file_type().is_file() => file_tx.send(e).is_ok(),
+                Ok(_) => true,
+                Err(err) => out_tx.blocking_send(Outcome::WalkError(err)).is_ok(),
+            };
+            if !sent {
+                break;
+            }
+        }
+    });
+    out_rx
+}
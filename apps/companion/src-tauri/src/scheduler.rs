@@ -0,0 +1,435 @@
+use crate::incremental::scan_changes;
+use crate::watcher::{sync_or_hold, WatchedFolder};
+use crate::{delete_indexed, settings, MediaMeta, ScanOptions, SyncPayloadItem};
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone, Timelike};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::Emitter;
+
+static SCHEDULE_WAKE: Lazy<tokio::sync::Notify> = Lazy::new(tokio::sync::Notify::new);
+static NEXT_RUN: Lazy<Mutex<Option<DateTime<Local>>>> = Lazy::new(|| Mutex::new(None));
+// Sleeping in short steps notices a wall clock that jumped, e.g. after the
+// machine was suspended past a due time.
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+// How soon a run held back by power or activity is tried again.
+const CONDITION_RETRY_MINUTES: i64 = 5;
+// Long enough for any date a cron expression can name, Feb 29 included.
+const CRON_SEARCH_DAYS: usize = 8 * 366;
+
+/// When background syncs of the watched folders run. Set exactly one of
+/// `interval_minutes` and `cron`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SyncSchedule {
+    /// Minutes between runs, counted from launch or the end of the last run.
+    #[serde(default)]
+    pub interval_minutes: Option<u64>,
+    /// Five fields in local time: minute, hour, day of month, month, day of
+    /// week (0 or 7 is Sunday). Fields take `*`, numbers, ranges, lists and
+    /// steps, e.g. "30 2 * * *" or "0 */4 * * 1-5".
+    #[serde(default)]
+    pub cron: Option<String>,
+    /// Hold runs while on battery; defaults to on.
+    #[serde(default)]
+    pub require_ac_power: Option<bool>,
+    /// Hold runs until there has been no keyboard or mouse input for this
+    /// long.
+    #[serde(default)]
+    pub min_idle_secs: Option<u64>,
+}
+
+struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    // cron matches either day field when both are restricted
+    either_day: bool,
+}
+
+fn cron_value(s: &str, field: &str) -> Result<u32, String> {
+    s.parse()
+        .map_err(|_| format!("cron {}: {:?} is not a number", field, s))
+}
+
+fn cron_field(spec: &str, field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for part in spec.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, cron_value(step, field)?),
+            None => (part, 1),
+        };
+        let (lo, hi) = if range == "*" {
+            (min, max)
+        } else if let Some((lo, hi)) = range.split_once('-') {
+            (cron_value(lo, field)?, cron_value(hi, field)?)
+        } else {
+            // "5/15" means every 15 starting at 5
+            let lo = cron_value(range, field)?;
+            (lo, if part.contains('/') { max } else { lo })
+        };
+        if step == 0 || lo < min || hi > max || lo > hi {
+            return Err(format!("cron {}: {:?} is out of range", field, part));
+        }
+        for v in (lo..=hi).step_by(step as usize) {
+            mask |= 1 << v;
+        }
+    }
+    Ok(mask)
+}
+
+impl Cron {
+    fn parse(expr: &str) -> Result<Cron, String> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err("cron needs five fields: minute hour day month weekday".into());
+        };
+        let weekdays = cron_field(weekday, "weekday", 0, 7)?;
+        Ok(Cron {
+            minutes: cron_field(minute, "minute", 0, 59)?,
+            hours: cron_field(hour, "hour", 0, 23)?,
+            days: cron_field(day, "day", 1, 31)?,
+            months: cron_field(month, "month", 1, 12)?,
+            // 7 is another name for Sunday
+            weekdays: (weekdays | weekdays >> 7) & 0x7f,
+            either_day: day != "*" && weekday != "*",
+        })
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        if self.either_day {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+
+    /// First matching minute strictly after `after`.
+    fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let after = after.naive_local();
+        let mut date = after.date();
+        let mut from = after.time().num_seconds_from_midnight() / 60 + 1;
+        for _ in 0..CRON_SEARCH_DAYS {
+            if self.day_matches(date) {
+                for minute_of_day in from..24 * 60 {
+                    let (h, m) = (minute_of_day / 60, minute_of_day % 60);
+                    if self.hours & (1 << h) == 0 || self.minutes & (1 << m) == 0 {
+                        continue;
+                    }
+                    // a time skipped by a DST change has no instant; an
+                    // ambiguous one runs at its first occurrence
+                    let naive = date.and_hms_opt(h, m, 0)?;
+                    if let Some(at) = Local.from_local_datetime(&naive).earliest() {
+                        return Some(at);
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+            from = 0;
+        }
+        None
+    }
+}
+
+impl SyncSchedule {
+    fn validate(&self) -> Result<(), String> {
+        match (self.interval_minutes, self.cron.as_deref()) {
+            (Some(0), _) => Err("interval_minutes must be at least 1".into()),
+            (Some(_), None) => Ok(()),
+            (None, Some(expr)) => Cron::parse(expr)?
+                .next_after(Local::now())
+                .map(|_| ())
+                .ok_or_else(|| format!("cron {:?} never matches", expr)),
+            _ => Err("set exactly one of interval_minutes and cron".into()),
+        }
+    }
+
+    fn next_after(&self, last: DateTime<Local>) -> Option<DateTime<Local>> {
+        if let Some(minutes) = self.interval_minutes {
+            return Some(last + chrono::Duration::minutes(minutes as i64));
+        }
+        Cron::parse(self.cron.as_deref()?).ok()?.next_after(last)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn on_ac_power() -> Option<bool> {
+    let supplies = std::fs::read_dir("/sys/class/power_supply").ok()?;
+    let mut battery = false;
+    for dir in supplies.flatten().map(|e| e.path()) {
+        let read = |name: &str| {
+            std::fs::read_to_string(dir.join(name))
+                .map(|s| s.trim().to_string())
+                .unwrap_or_default()
+        };
+        match read("type").as_str() {
+            "Mains" | "USB" if read("online") == "1" => return Some(true),
+            // wireless mice and headsets report batteries of their own
+            "Battery" if read("scope") != "Device" => battery = true,
+            _ => {}
+        }
+    }
+    // a desktop has no battery and always runs on mains
+    Some(!battery)
+}
+
+#[cfg(target_os = "macos")]
+fn on_ac_power() -> Option<bool> {
+    let out = std::process::Command::new("pmset")
+        .args(["-g", "batt"])
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&out.stdout);
+    if text.contains("'AC Power'") {
+        Some(true)
+    } else if text.contains("'Battery Power'") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+// Output of a PowerShell one-liner, run without flashing a console window.
+#[cfg(target_os = "windows")]
+fn powershell(script: &str) -> Option<String> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let out = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+    Some(String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+#[cfg(target_os = "windows")]
+fn on_ac_power() -> Option<bool> {
+    let script = "Add-Type -AssemblyName System.Windows.Forms; [System.Windows.Forms.SystemInformation]::PowerStatus.PowerLineStatus";
+    match powershell(script)?.as_str() {
+        "Online" => Some(true),
+        "Offline" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn on_ac_power() -> Option<bool> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn idle_time() -> Option<Duration> {
+    // X11 only; Wayland compositors don't share input times
+    let out = std::process::Command::new("xprintidle").output().ok()?;
+    let ms = String::from_utf8_lossy(&out.stdout).trim().parse().ok()?;
+    Some(Duration::from_millis(ms))
+}
+
+#[cfg(target_os = "macos")]
+fn idle_time() -> Option<Duration> {
+    let out = std::process::Command::new("ioreg")
+        .args(["-c", "IOHIDSystem", "-d", "4"])
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&out.stdout);
+    let ns = text.lines().find_map(|l| {
+        let (_, value) = l.split_once("\"HIDIdleTime\" =")?;
+        value.trim().parse().ok()
+    })?;
+    Some(Duration::from_nanos(ns))
+}
+
+#[cfg(target_os = "windows")]
+fn idle_time() -> Option<Duration> {
+    let script = r#"Add-Type 'using System; using System.Runtime.InteropServices; public static class Idle { struct Info { public uint Size; public uint Time; } [DllImport("user32.dll")] static extern bool GetLastInputInfo(ref Info i); public static uint Ms() { var i = new Info(); i.Size = 8; GetLastInputInfo(ref i); return (uint)Environment.TickCount - i.Time; } }'; [Idle]::Ms()"#;
+    let ms = powershell(script)?.parse().ok()?;
+    Some(Duration::from_millis(ms))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn idle_time() -> Option<Duration> {
+    None
+}
+
+// Why a due run has to wait, if it does. Conditions the platform can't
+// report don't hold anything back.
+async fn held_back(schedule: &SyncSchedule) -> Option<String> {
+    let need_power = schedule.require_ac_power.unwrap_or(true);
+    let min_idle = schedule.min_idle_secs.map(Duration::from_secs);
+    let (power, idle) = tauri::async_runtime::spawn_blocking(move || {
+        (
+            need_power.then(on_ac_power).flatten(),
+            min_idle.and_then(|_| idle_time()),
+        )
+    })
+    .await
+    .ok()?;
+    if power == Some(false) {
+        return Some("on battery".into());
+    }
+    match (idle, min_idle) {
+        (Some(idle), Some(min)) if idle < min => Some("in use".into()),
+        _ => None,
+    }
+}
+
+fn delta_item(user_id: &str, meta: &MediaMeta) -> SyncPayloadItem {
+    SyncPayloadItem {
+        user_id: user_id.to_string(),
+        modality: meta.modality.clone(),
+        uri: meta.path.clone(),
+        ts: meta.modified.clone(),
+        bytes_b64: None,
+        frames_b64: None,
+        page: None,
+        text: None,
+    }
+}
+
+async fn sync_folder(
+    app: &tauri::AppHandle,
+    folder: &WatchedFolder,
+) -> Result<serde_json::Value, String> {
+    let delta = scan_changes(folder.path.clone(), ScanOptions::default(), app.clone())
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut deleted = 0;
+    if !delta.removed.is_empty() {
+        deleted = delete_indexed(
+            folder.server_url.clone(),
+            delta.removed.clone(),
+            Some(folder.user_id.clone()),
+            None,
+            None,
+            None,
+            None,
+            None,
+            app.clone(),
+        )
+        .await
+        .map_err(|e| e.to_string())?
+        .deleted;
+    }
+    let items: Vec<SyncPayloadItem> = delta
+        .added
+        .iter()
+        .chain(&delta.changed)
+        .map(|meta| delta_item(&folder.user_id, meta))
+        .collect();
+    let mut upserted = 0;
+    if !items.is_empty() {
+        // the catalog already counts these as seen, so a failed upload is
+        // spooled rather than left for the next scan to find
+        upserted = sync_or_hold(app, folder, items)
+            .await
+            .map_err(|failure| failure.error)?;
+    }
+    Ok(serde_json::json!({
+        "path": folder.path,
+        "status": "synced",
+        "added": delta.added.len(),
+        "changed": delta.changed.len(),
+        "removed": delta.removed.len(),
+        "upserted": upserted,
+        "deleted": deleted,
+    }))
+}
+
+async fn run_once(app: &tauri::AppHandle) {
+    let folders = settings::load(app).watched_folders;
+    let _ = app.emit(
+        "scheduled_sync",
+        serde_json::json!({ "status": "started", "folders": folders.len() }),
+    );
+    for folder in &folders {
+        let event = sync_folder(app, folder).await.unwrap_or_else(|err| {
+            log::warn!("scheduled sync of {} failed: {}", folder.path, err);
+            serde_json::json!({ "path": folder.path, "status": "error", "error": err })
+        });
+        let _ = app.emit("scheduled_sync", event);
+    }
+    let _ = app.emit(
+        "scheduled_sync",
+        serde_json::json!({ "status": "finished", "folders": folders.len() }),
+    );
+}
+
+fn set_next_run(next: Option<DateTime<Local>>) {
+    *NEXT_RUN.lock().unwrap_or_else(|e| e.into_inner()) = next;
+}
+
+/// Long-lived task spawned from `setup`. With a saved schedule it sleeps
+/// until the next due time, waits out battery power or recent input, then
+/// scans, filters and syncs every watched folder. Saving a schedule wakes it
+/// so it reschedules; with none it idles until woken.
+pub async fn run_scheduler(app: tauri::AppHandle) {
+    let mut last_run = Local::now();
+    let mut retry_at = None;
+    loop {
+        let schedule = settings::load(&app).sync_schedule;
+        let next = schedule
+            .as_ref()
+            .and_then(|s| retry_at.or_else(|| s.next_after(last_run)));
+        set_next_run(next);
+        let (Some(schedule), Some(next)) = (schedule, next) else {
+            SCHEDULE_WAKE.notified().await;
+            continue;
+        };
+        let wait = (next - Local::now()).to_std().unwrap_or_default();
+        if !wait.is_zero() {
+            tokio::select! {
+                _ = tokio::time::sleep(wait.min(MAX_SLEEP)) => {}
+                _ = SCHEDULE_WAKE.notified() => retry_at = None,
+            }
+            continue;
+        }
+        if let Some(reason) = held_back(&schedule).await {
+            let retry = Local::now() + chrono::Duration::minutes(CONDITION_RETRY_MINUTES);
+            retry_at = Some(retry);
+            let _ = app.emit(
+                "scheduled_sync",
+                serde_json::json!({
+                    "status": "held",
+                    "reason": reason,
+                    "retry_at": retry.to_rfc3339(),
+                }),
+            );
+            continue;
+        }
+        retry_at = None;
+        set_next_run(None);
+        run_once(&app).await;
+        last_run = Local::now();
+    }
+}
+
+/// Saves when watched folders are synced in the background and reschedules
+/// the next run; None turns scheduled syncs off. Progress is reported as
+/// `scheduled_sync` events.
+#[tauri::command]
+pub async fn set_sync_schedule(
+    schedule: Option<SyncSchedule>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    if let Some(schedule) = &schedule {
+        schedule.validate()?;
+    }
+    settings::update(&app, |s| s.sync_schedule = schedule)?;
+    SCHEDULE_WAKE.notify_one();
+    Ok(())
+}
+
+/// When the next scheduled sync is due, as RFC 3339 local time. None while
+/// no schedule is set or a run is in progress.
+#[tauri::command]
+pub async fn get_next_sync_time() -> Result<Option<String>, String> {
+    let next = *NEXT_RUN.lock().unwrap_or_else(|e| e.into_inner());
+    Ok(next.map(|t| t.to_rfc3339()))
+}
@@ -0,0 +1,75 @@
+use crate::{oauth, send_with_retry};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_LIMIT: usize = 12;
+const MAX_LIMIT: usize = 200;
+
+/// One search result, trimmed to what the UI renders.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchHit {
+    pub media_id: String,
+    /// Local path or URI the media was indexed from.
+    #[serde(alias = "uri")]
+    pub path: String,
+    pub score: f32,
+    pub modality: String,
+    /// Server-side thumbnail, when the gateway has one.
+    #[serde(default, alias = "thumb_url")]
+    pub thumbnail: Option<String>,
+    #[serde(default)]
+    pub ts: Option<String>,
+}
+
+/// Queries the gateway's `/search` as the active account. `filters` is passed
+/// through unchanged (modality, time_range, geo).
+#[tauri::command]
+pub async fn search(
+    server_url: String,
+    query: String,
+    limit: Option<usize>,
+    filters: Option<serde_json::Value>,
+    app: tauri::AppHandle,
+) -> Result<Vec<SearchHit>, String> {
+    if server_url.is_empty() {
+        return Err("server_url empty".into());
+    }
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let user_id = oauth::active_user_id(&app).ok_or_else(|| "not authenticated".to_string())?;
+
+    #[derive(Serialize)]
+    struct SearchRequest<'a> {
+        user_id: &'a str,
+        text: &'a str,
+        top_k: usize,
+        filters: serde_json::Value,
+    }
+
+    #[derive(Deserialize)]
+    struct SearchResponse {
+        results: Vec<SearchHit>,
+    }
+
+    let request = SearchRequest {
+        user_id: &user_id,
+        text: query,
+        top_k: limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+        filters: filters.unwrap_or_else(|| serde_json::json!({})),
+    };
+    let url = format!("{}/search", server_url.trim_end_matches('/'));
+    let sent = send_with_retry(&app, None, |client, token| {
+        client.post(&url).bearer_auth(token).json(&request)
+    })
+    .await?;
+    if !sent.resp.status().is_success() {
+        return Err(format!("search failed: {}", sent.resp.status()));
+    }
+    let body = sent
+        .resp
+        .json::<SearchResponse>()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(body.results)
+}
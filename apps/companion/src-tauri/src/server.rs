@@ -0,0 +1,120 @@
+use crate::error::TauraError;
+use crate::{http, oauth, send_authed};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerStatus {
+    /// No HTTP response at all: wrong URL, DNS, connect or TLS failure.
+    Unreachable,
+    /// The server answered, but not to our session (or there is none).
+    Unauthorized,
+    /// Authenticated, but the server failed the check request, e.g. it
+    /// isn't a Taura gateway.
+    ServerError,
+    Ok,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PingResult {
+    pub status: ServerStatus,
+    /// Round trip of the unauthenticated `/health` request.
+    pub latency_ms: Option<u64>,
+    /// Status of the authenticated check, when there was a response.
+    pub http_status: Option<u16>,
+    pub error: Option<String>,
+}
+
+impl PingResult {
+    fn failed(status: ServerStatus, latency_ms: Option<u64>, error: String) -> Self {
+        PingResult {
+            status,
+            latency_ms,
+            http_status: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Checks that `server_url` is reachable and that the active session is
+/// accepted, with an empty `/sync/missing` request. Only a malformed URL is
+/// an error; every other outcome is described by the returned `status` and
+/// `error`.
+#[tauri::command]
+pub async fn ping_server(
+    server_url: String,
+    app: tauri::AppHandle,
+) -> Result<PingResult, TauraError> {
+    let base = server_url.trim().trim_end_matches('/');
+    let parsed = reqwest::Url::parse(base)
+        .map_err(|e| TauraError::invalid_input(format!("invalid server_url: {}", e)))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(TauraError::invalid_input(
+            "server_url must be http:// or https://",
+        ));
+    }
+
+    // any answer, even an error status, proves the URL points at a server
+    let started = Instant::now();
+    if let Err(err) = http::client()
+        .get(format!("{}/health", base))
+        .timeout(PING_TIMEOUT)
+        .send()
+        .await
+    {
+        return Ok(PingResult::failed(
+            ServerStatus::Unreachable,
+            None,
+            err.to_string(),
+        ));
+    }
+    let latency_ms = Some(started.elapsed().as_millis() as u64);
+
+    let Some(user_id) = oauth::active_user_id(&app) else {
+        return Ok(PingResult::failed(
+            ServerStatus::Unauthorized,
+            latency_ms,
+            "not signed in".into(),
+        ));
+    };
+    // an empty list is answered without touching the index
+    let url = format!("{}/sync/missing", base);
+    let body = serde_json::json!({ "user_id": user_id, "items": [] });
+    let resp = match send_authed(&app, |client, token| {
+        client
+            .post(&url)
+            .bearer_auth(token)
+            .json(&body)
+            .timeout(PING_TIMEOUT)
+    })
+    .await
+    {
+        Ok(resp) => resp,
+        Err(err) => {
+            let status = match err {
+                TauraError::NotAuthenticated { .. } => ServerStatus::Unauthorized,
+                TauraError::Network { .. } => ServerStatus::Unreachable,
+                _ => ServerStatus::ServerError,
+            };
+            return Ok(PingResult::failed(status, latency_ms, err.to_string()));
+        }
+    };
+    let status = resp.status();
+    let mut result = PingResult {
+        status: ServerStatus::Ok,
+        latency_ms,
+        http_status: Some(status.as_u16()),
+        error: None,
+    };
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        result.status = ServerStatus::Unauthorized;
+        result.error = Some(format!("server rejected the session: {}", status));
+    } else if !status.is_success() {
+        result.status = ServerStatus::ServerError;
+        result.error = Some(format!("check request failed: {}", status));
+    }
+    Ok(result)
+}
@@ -0,0 +1,260 @@
+use std::{fs, path::PathBuf};
+use tauri::Manager;
+
+const SESSIONS_DIR: &str = "sessions";
+// Single-account storage used before accounts were keyed.
+const LEGACY_FILE: &str = "session.json";
+const LEGACY_ACCOUNT: &str = "session";
+// Stored in the account's entry when the session is split across parts.
+const PARTS_MARKER: &str = "parts:";
+// Windows caps a credential at 1280 UTF-16 units; session JSON is ASCII, so
+// this stays well under it either way.
+const PART_CHARS: usize = 1000;
+// Proxy credentials get their own entry, outside any account.
+const PROXY_ENTRY: &str = "proxy";
+const PROXY_FILE: &str = "proxy_credentials";
+
+// Serialized sessions live in the OS keychain, one entry per account. A
+// session too long for one entry (Windows Credential Manager) is split over
+// numbered part entries. sessions/<account>.json is only used when no
+// keychain is reachable, e.g. on headless Linux.
+
+fn base_dir(app: &tauri::AppHandle) -> PathBuf {
+    app.path()
+        .app_config_dir()
+        .or_else(|_| app.path().app_data_dir())
+        .unwrap_or_else(|_| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
+}
+
+// Account ids are subjects or emails; keep them safe as file names.
+fn account_key(account: &str) -> String {
+    account
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '@' | '.' | '_' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn session_path(app: &tauri::AppHandle, account: &str) -> PathBuf {
+    base_dir(app)
+        .join(SESSIONS_DIR)
+        .join(format!("{}.json", account_key(account)))
+}
+
+fn entry_for(app: &tauri::AppHandle, name: &str) -> Option<keyring::Entry> {
+    keyring::Entry::new(&app.config().identifier, name)
+        .map_err(|e| log::warn!("keychain unavailable: {}", e))
+        .ok()
+}
+
+fn keyring_entry(app: &tauri::AppHandle, account: &str) -> Option<keyring::Entry> {
+    entry_for(app, &format!("session:{}", account_key(account)))
+}
+
+fn part_entry(app: &tauri::AppHandle, account: &str, part: usize) -> Option<keyring::Entry> {
+    entry_for(app, &format!("session:{}:{}", account_key(account), part))
+}
+
+fn part_count(stored: &str) -> Option<usize> {
+    stored.strip_prefix(PARTS_MARKER)?.parse().ok()
+}
+
+// Reassembles a split session; None if any part has gone missing.
+fn read_parts(app: &tauri::AppHandle, account: &str, count: usize) -> Option<String> {
+    let mut data = String::new();
+    for part in 0..count {
+        data.push_str(&part_entry(app, account, part)?.get_password().ok()?);
+    }
+    Some(data)
+}
+
+fn write_parts(
+    app: &tauri::AppHandle,
+    account: &str,
+    entry: &keyring::Entry,
+    data: &str,
+) -> Result<(), keyring::Error> {
+    let chars: Vec<char> = data.chars().collect();
+    let parts: Vec<String> = chars
+        .chunks(PART_CHARS)
+        .map(|c| c.iter().collect())
+        .collect();
+    for (i, part) in parts.iter().enumerate() {
+        part_entry(app, account, i)
+            .ok_or(keyring::Error::NoStorageAccess(
+                "keychain unavailable".into(),
+            ))?
+            .set_password(part)?;
+    }
+    // the marker goes last, so a half-written split is never read
+    entry.set_password(&format!("{}{}", PARTS_MARKER, parts.len()))
+}
+
+fn delete_parts(app: &tauri::AppHandle, account: &str, entry: &keyring::Entry) {
+    let Some(count) = entry.get_password().ok().as_deref().and_then(part_count) else {
+        return;
+    };
+    for part in 0..count {
+        delete_entry(part_entry(app, account, part));
+    }
+}
+
+fn read_file(p: &PathBuf) -> Option<String> {
+    if !p.exists() {
+        return None;
+    }
+    fs::read_to_string(p).ok()
+}
+
+fn write_file(p: &PathBuf, data: &str) -> Result<(), String> {
+    if let Some(parent) = p.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(p, data).map_err(|e| e.to_string())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = fs::metadata(p).map_err(|e| e.to_string())?.permissions();
+        perms.set_mode(0o600);
+        fs::set_permissions(p, perms).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn remove_file(p: &PathBuf) {
+    if p.exists() {
+        let _ = fs::remove_file(p);
+    }
+}
+
+/// Returns the stored session JSON for `account`, moving a fallback file into
+/// the keychain the first time it is seen.
+pub fn load(app: &tauri::AppHandle, account: &str) -> Option<String> {
+    let path = session_path(app, account);
+    let Some(entry) = keyring_entry(app, account) else {
+        return read_file(&path);
+    };
+    match entry.get_password() {
+        Ok(data) => match part_count(&data) {
+            Some(count) => read_parts(app, account, count).or_else(|| {
+                log::warn!("session parts missing from keychain, using session file");
+                read_file(&path)
+            }),
+            None => Some(data),
+        },
+        Err(keyring::Error::NoEntry) => {
+            let data = read_file(&path)?;
+            match save_to_keychain(app, account, &entry, &data) {
+                Ok(()) => remove_file(&path),
+                Err(e) => log::warn!("session not migrated to keychain: {}", e),
+            }
+            Some(data)
+        }
+        Err(e) => {
+            log::warn!("keychain read failed, using session file: {}", e);
+            read_file(&path)
+        }
+    }
+}
+
+fn save_to_keychain(
+    app: &tauri::AppHandle,
+    account: &str,
+    entry: &keyring::Entry,
+    data: &str,
+) -> Result<(), keyring::Error> {
+    // parts from an earlier, longer session would otherwise linger
+    delete_parts(app, account, entry);
+    match entry.set_password(data) {
+        Err(keyring::Error::TooLong(..)) => write_parts(app, account, entry, data),
+        other => other,
+    }
+}
+
+pub fn save(app: &tauri::AppHandle, account: &str, data: &str) -> Result<(), String> {
+    let path = session_path(app, account);
+    if let Some(entry) = keyring_entry(app, account) {
+        match save_to_keychain(app, account, &entry, data) {
+            Ok(()) => {
+                // don't leave an older plaintext copy behind
+                remove_file(&path);
+                return Ok(());
+            }
+            Err(e) => {
+                log::warn!("keychain write failed, using session file: {}", e);
+                // an older session left in the keychain would win over the file on load
+                delete_parts(app, account, &entry);
+                delete_entry(Some(entry));
+            }
+        }
+    }
+    write_file(&path, data)
+}
+
+fn delete_entry(entry: Option<keyring::Entry>) {
+    if let Some(entry) = entry {
+        match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(e) => log::warn!("keychain delete failed: {}", e),
+        }
+    }
+}
+
+pub fn clear(app: &tauri::AppHandle, account: &str) {
+    if let Some(entry) = keyring_entry(app, account) {
+        delete_parts(app, account, &entry);
+    }
+    delete_entry(keyring_entry(app, account));
+    remove_file(&session_path(app, account));
+}
+
+/// `user:pass` for the saved proxy, kept out of settings.json.
+pub fn load_proxy_credentials(app: &tauri::AppHandle) -> Option<String> {
+    entry_for(app, PROXY_ENTRY)
+        .and_then(|entry| entry.get_password().ok())
+        .or_else(|| read_file(&base_dir(app).join(PROXY_FILE)))
+}
+
+/// Stores the proxy credentials like a session, or forgets them for None.
+pub fn save_proxy_credentials(
+    app: &tauri::AppHandle,
+    credentials: Option<&str>,
+) -> Result<(), String> {
+    let path = base_dir(app).join(PROXY_FILE);
+    let entry = entry_for(app, PROXY_ENTRY);
+    let Some(credentials) = credentials else {
+        delete_entry(entry);
+        remove_file(&path);
+        return Ok(());
+    };
+    if let Some(entry) = entry {
+        match entry.set_password(credentials) {
+            Ok(()) => {
+                remove_file(&path);
+                return Ok(());
+            }
+            Err(e) => {
+                log::warn!("keychain write failed, using credentials file: {}", e);
+                delete_entry(Some(entry));
+            }
+        }
+    }
+    write_file(&path, credentials)
+}
+
+/// The session saved before accounts were keyed, if one is still around.
+pub fn load_legacy(app: &tauri::AppHandle) -> Option<String> {
+    entry_for(app, LEGACY_ACCOUNT)
+        .and_then(|entry| entry.get_password().ok())
+        .or_else(|| read_file(&base_dir(app).join(LEGACY_FILE)))
+}
+
+pub fn clear_legacy(app: &tauri::AppHandle) {
+    delete_entry(entry_for(app, LEGACY_ACCOUNT));
+    remove_file(&base_dir(app).join(LEGACY_FILE));
+}
@@ -0,0 +1,215 @@
+use crate::library::LibraryFolder;
+use crate::overlay::OverlayPosition;
+use crate::scheduler::SyncSchedule;
+use crate::watcher::WatchedFolder;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::{fs, path::PathBuf};
+use tauri::Manager;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// Preferences that outlive a restart. Missing fields fall back to defaults.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Settings {
+    #[serde(default)]
+    pub default_throttle_ms: Option<u64>,
+    /// Gateway the sync queue was last flushed to.
+    #[serde(default)]
+    pub sync_server_url: Option<String>,
+    /// Folders synced in the background by `watch_folder`.
+    #[serde(default)]
+    pub watched_folders: Vec<WatchedFolder>,
+    /// Roots walked by `scan_all`.
+    #[serde(default)]
+    pub library_folders: Vec<LibraryFolder>,
+    /// Accelerator for the overlay hotkey; the platform default when unset.
+    #[serde(default)]
+    pub overlay_shortcut: Option<String>,
+    /// Ids of signed-in accounts, in sign-in order.
+    #[serde(default)]
+    pub accounts: Vec<String>,
+    /// Account the app currently acts as.
+    #[serde(default)]
+    pub active_account: Option<String>,
+    /// Proxy for outbound requests; "none" disables proxies, unset uses the
+    /// environment. Its `user:pass@`, if any, is kept in the keychain.
+    #[serde(default)]
+    pub proxy_url: Option<String>,
+    /// Hide the overlay when it loses focus; defaults to on.
+    #[serde(default)]
+    pub overlay_hide_on_blur: Option<bool>,
+    #[serde(default)]
+    pub overlay_position: Option<OverlayPosition>,
+    /// Closing the main window hides it to the tray instead of quitting;
+    /// defaults to off.
+    #[serde(default)]
+    pub close_to_tray: Option<bool>,
+    /// Extensions `scan_folder` indexes, lowercase without the dot; empty
+    /// means every supported one.
+    #[serde(default)]
+    pub media_extensions: Vec<String>,
+    /// Globs pruned from every scan, matched against the path relative to the
+    /// scan root and against the bare file name.
+    #[serde(default)]
+    pub excluded_globs: Vec<String>,
+    /// Further globs per folder, for scans of that folder or anything in it;
+    /// matched relative to the folder.
+    #[serde(default)]
+    pub folder_exclusions: BTreeMap<String, Vec<String>>,
+    /// Folders `get_default_folder` offers, first existing one wins.
+    #[serde(default)]
+    pub default_folders: Vec<String>,
+    /// Folders the webview may read through the fs plugin, granted by
+    /// `grant_folder_access`.
+    #[serde(default)]
+    pub fs_grants: Vec<String>,
+    #[serde(default)]
+    pub upload_limits: UploadLimits,
+    /// Background syncs of the watched folders; off when unset.
+    #[serde(default)]
+    pub sync_schedule: Option<SyncSchedule>,
+}
+
+/// Used by `sync_index`, `sync_missing` and queue flushes for the arguments
+/// a caller leaves out; unset fields keep the built-in defaults.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct UploadLimits {
+    /// Items per upload request.
+    #[serde(default)]
+    pub batch_size: Option<usize>,
+    /// Send small files' bytes along with their entries.
+    #[serde(default)]
+    pub inline_small_files: Option<bool>,
+    /// Largest file sent inline.
+    #[serde(default)]
+    pub inline_max_bytes: Option<u64>,
+    /// Upper bound on one upload request's body, before compression.
+    #[serde(default)]
+    pub chunk_max_bytes: Option<u64>,
+    /// Retries per failed request.
+    #[serde(default)]
+    pub max_retries: Option<usize>,
+    /// Upload speed cap shared by all syncs; uncapped when unset.
+    #[serde(default)]
+    pub max_bytes_per_sec: Option<u64>,
+}
+
+/// Preferences `update_settings` may change; absent fields are left alone.
+/// Accounts, watched folders and the overlay position have their own commands.
+#[derive(Debug, Deserialize, Default)]
+pub struct SettingsPatch {
+    pub default_throttle_ms: Option<u64>,
+    pub media_extensions: Option<Vec<String>>,
+    pub excluded_globs: Option<Vec<String>>,
+    pub default_folders: Option<Vec<String>>,
+    pub overlay_shortcut: Option<String>,
+    /// Empty string goes back to the environment's proxy.
+    pub proxy_url: Option<String>,
+    pub server_url: Option<String>,
+    pub overlay_hide_on_blur: Option<bool>,
+    pub close_to_tray: Option<bool>,
+    /// Replaces the stored limits as a whole.
+    pub upload_limits: Option<UploadLimits>,
+}
+
+fn trimmed_list(values: Vec<String>) -> Vec<String> {
+    values
+        .into_iter()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+impl SettingsPatch {
+    pub fn apply_to(self, settings: &mut Settings) {
+        if let Some(ms) = self.default_throttle_ms {
+            settings.default_throttle_ms = Some(ms);
+        }
+        if let Some(exts) = self.media_extensions {
+            settings.media_extensions = trimmed_list(exts)
+                .into_iter()
+                .map(|e| e.trim_start_matches('.').to_lowercase())
+                .collect();
+        }
+        if let Some(globs) = self.excluded_globs {
+            settings.excluded_globs = trimmed_list(globs);
+        }
+        if let Some(folders) = self.default_folders {
+            settings.default_folders = trimmed_list(folders);
+        }
+        if let Some(shortcut) = self.overlay_shortcut {
+            settings.overlay_shortcut = Some(shortcut);
+        }
+        if let Some(proxy) = self.proxy_url {
+            let proxy = proxy.trim().to_string();
+            settings.proxy_url = (!proxy.is_empty()).then_some(proxy);
+        }
+        if let Some(url) = self.server_url {
+            let url = url.trim().trim_end_matches('/').to_string();
+            settings.sync_server_url = (!url.is_empty()).then_some(url);
+        }
+        if let Some(enabled) = self.overlay_hide_on_blur {
+            settings.overlay_hide_on_blur = Some(enabled);
+        }
+        if let Some(enabled) = self.close_to_tray {
+            settings.close_to_tray = Some(enabled);
+        }
+        if let Some(limits) = self.upload_limits {
+            settings.upload_limits = limits;
+        }
+    }
+}
+
+fn settings_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(SETTINGS_FILE))
+        .map_err(|e| e.to_string())
+}
+
+// Held across `update`'s read-modify-write so concurrent commands don't
+// drop each other's changes.
+static UPDATE_LOCK: Mutex<()> = Mutex::new(());
+
+// Missing means first launch; a file that exists but doesn't parse is an
+// error, so nothing writes defaults over it.
+fn read(app: &tauri::AppHandle) -> Result<Settings, String> {
+    let p = settings_path(app)?;
+    match fs::read(&p) {
+        Ok(data) => serde_json::from_slice(&data)
+            .map_err(|e| format!("{} is unreadable, leaving it alone: {}", p.display(), e)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Settings::default()),
+        Err(err) => Err(format!("{}: {}", p.display(), err)),
+    }
+}
+
+/// The stored settings, or defaults when there are none or they can't be
+/// read.
+pub fn load(app: &tauri::AppHandle) -> Settings {
+    read(app).unwrap_or_else(|err| {
+        log::warn!("settings not loaded: {}", err);
+        Settings::default()
+    })
+}
+
+fn save(app: &tauri::AppHandle, settings: &Settings) -> Result<(), String> {
+    let p = settings_path(app)?;
+    if let Some(parent) = p.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let data = serde_json::to_vec_pretty(settings).map_err(|e| e.to_string())?;
+    let tmp = p.with_extension("json.tmp");
+    fs::write(&tmp, data).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, &p).map_err(|e| e.to_string())
+}
+
+/// Applies a change to the stored settings and writes them back. Fails
+/// without writing when the stored file can't be read.
+pub fn update(app: &tauri::AppHandle, change: impl FnOnce(&mut Settings)) -> Result<(), String> {
+    let _guard = UPDATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut settings = read(app)?;
+    change(&mut settings);
+    save(app, &settings)
+}
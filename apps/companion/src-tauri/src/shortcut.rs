@@ -0,0 +1,98 @@
+use crate::settings;
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
+
+// The overlay combo currently held by this app, if registration succeeded.
+static CURRENT: Lazy<Mutex<Option<Shortcut>>> = Lazy::new(|| Mutex::new(None));
+
+fn default_accelerator() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "command+shift+k"
+    } else {
+        "ctrl+shift+k"
+    }
+}
+
+fn parse(accelerator: &str) -> Result<Shortcut, String> {
+    accelerator
+        .trim()
+        .parse::<Shortcut>()
+        .map_err(|e| format!("invalid shortcut \"{}\": {}", accelerator, e))
+}
+
+fn register(app: &tauri::AppHandle, shortcut: Shortcut) -> Result<(), String> {
+    app.global_shortcut().register(shortcut).map_err(|err| {
+        let msg = err.to_string();
+        if msg.contains("already registered") || msg.contains("Unable to register") {
+            format!("{} is already in use by another app", shortcut)
+        } else {
+            msg
+        }
+    })
+}
+
+/// Registers the saved overlay shortcut, or the platform default. Failure
+/// only logs; the overlay stays reachable from the UI.
+pub fn register_saved(app: &tauri::AppHandle) {
+    let accelerator = settings::load(app)
+        .overlay_shortcut
+        .unwrap_or_else(|| default_accelerator().to_string());
+    match parse(&accelerator).and_then(|s| register(app, s).map(|_| s)) {
+        Ok(shortcut) => {
+            if let Ok(mut current) = CURRENT.lock() {
+                *current = Some(shortcut);
+            }
+        }
+        Err(err) => log::warn!(
+            "Global shortcut {} not registered ({}); overlay toggle remains available via UI",
+            accelerator,
+            err
+        ),
+    }
+}
+
+/// The overlay shortcut in effect, normalised (e.g. "shift+control+KeyK").
+/// When none could be registered, the saved or default one that was tried.
+#[tauri::command]
+pub async fn get_overlay_shortcut(app: tauri::AppHandle) -> Result<String, String> {
+    if let Some(current) = *CURRENT.lock().map_err(|_| "lock poisoned")? {
+        return Ok(current.into_string());
+    }
+    Ok(settings::load(&app)
+        .overlay_shortcut
+        .unwrap_or_else(|| default_accelerator().to_string()))
+}
+
+/// Swaps the overlay shortcut for `shortcut` (e.g. "ctrl+alt+space") and
+/// saves it. If the new combo can't be registered the previous one is kept.
+#[tauri::command]
+pub async fn set_overlay_shortcut(
+    shortcut: String,
+    app: tauri::AppHandle,
+) -> Result<String, String> {
+    let next = parse(&shortcut)?;
+    {
+        let mut current = CURRENT.lock().map_err(|_| "lock poisoned")?;
+        if *current != Some(next) {
+            if let Some(prev) = *current {
+                app.global_shortcut()
+                    .unregister(prev)
+                    .map_err(|e| e.to_string())?;
+            }
+            if let Err(err) = register(&app, next) {
+                // put the old combo back so the overlay stays reachable
+                if let Some(prev) = *current {
+                    if register(&app, prev).is_err() {
+                        *current = None;
+                    }
+                }
+                return Err(err);
+            }
+            *current = Some(next);
+        }
+    }
+    let saved = next.into_string();
+    settings::update(&app, |s| s.overlay_shortcut = Some(saved.clone()))?;
+    Ok(saved)
+}
@@ -0,0 +1,423 @@
+use crate::error::TauraError;
+use crate::{
+    byte_chunks, http, prepare_items, settings, sync_batch, BatchExtras, SyncErrorItem,
+    SyncPayload, SyncPayloadItem, SyncResult, DEFAULT_CHUNK_MAX_BYTES, DEFAULT_SYNC_BATCH,
+};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{Emitter, Manager};
+
+const SPOOL_FILE: &str = "sync_queue.ndjson";
+// Items taken out of the spool for an upload; survives a crash mid-flush.
+const INFLIGHT_FILE: &str = "sync_queue.inflight.ndjson";
+// How many in-flight items have been sent or set aside, so an interrupted
+// flush resumes after them instead of from the start.
+const CURSOR_FILE: &str = "sync_queue.cursor";
+// In-flight batches that failed; they rejoin the spool when the flush ends.
+const FAILED_FILE: &str = "sync_queue.failed.ndjson";
+// Items set aside for good: refused by the server, or failed too often.
+const DEAD_LETTER_FILE: &str = "sync_queue.dead.ndjson";
+// Flushes an item may fail with a retryable error before it is set aside.
+const MAX_ATTEMPTS: u32 = 5;
+
+// Set by `pause_sync`; uploads stop at the next batch boundary.
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+// Guards spool file swaps; held only for file operations, never for uploads.
+static SPOOL_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+// Only one flush drains the in-flight file at a time.
+static FLUSH_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+// One spool line: the item as given to the sync, and what it still needs.
+#[derive(Serialize, Deserialize, Clone)]
+struct QueuedItem {
+    #[serde(flatten)]
+    item: SyncPayloadItem,
+    /// Flushes that failed to send it so far.
+    #[serde(default)]
+    attempts: u32,
+    #[serde(default)]
+    extras: BatchExtras,
+}
+
+#[derive(Serialize)]
+pub struct FlushResult {
+    #[serde(flatten)]
+    result: SyncResult,
+    sent: usize,
+    remaining: usize,
+    /// Items this flush moved to the dead-letter file.
+    dead_lettered: usize,
+    /// The flush stopped early for `pause_sync`; `resume_sync` continues it.
+    paused: bool,
+}
+
+#[derive(Serialize)]
+pub struct QueueStatus {
+    /// Items still to upload, wherever they sit in the journal.
+    pending: usize,
+    /// Items of the interrupted or running flush already handled.
+    in_flight_done: usize,
+    paused: bool,
+    flushing: bool,
+    server_url: Option<String>,
+    /// Items set aside in `sync_queue.dead.ndjson`, which no flush sends.
+    dead_letter: usize,
+}
+
+pub fn is_paused() -> bool {
+    PAUSED.load(Ordering::SeqCst)
+}
+
+// Announced as `sync_paused` so the tray and the UI agree on the state.
+fn set_paused(app: &tauri::AppHandle, paused: bool) {
+    PAUSED.store(paused, Ordering::SeqCst);
+    let _ = app.emit("sync_paused", paused);
+}
+
+fn spool_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path().app_data_dir().map_err(|e| e.to_string())
+}
+
+fn read_items(path: &PathBuf) -> Vec<QueuedItem> {
+    let Ok(data) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    data.lines()
+        .filter(|l| !l.trim().is_empty())
+        .filter_map(|l| match serde_json::from_str(l) {
+            Ok(item) => Some(item),
+            Err(err) => {
+                log::warn!("dropping unreadable sync queue line: {}", err);
+                None
+            }
+        })
+        .collect()
+}
+
+fn encode_items(items: &[QueuedItem]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    for item in items {
+        serde_json::to_writer(&mut out, item).map_err(|e| e.to_string())?;
+        out.push(b'\n');
+    }
+    Ok(out)
+}
+
+fn write_items(path: &PathBuf, items: &[QueuedItem]) -> Result<(), String> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, encode_items(items)?).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+fn append_items(path: &PathBuf, items: &[QueuedItem]) -> Result<(), String> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| e.to_string())?;
+    file.write_all(&encode_items(items)?)
+        .map_err(|e| e.to_string())
+}
+
+fn read_cursor(path: &PathBuf) -> usize {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+fn write_cursor(path: &PathBuf, done: usize) -> Result<(), String> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, done.to_string()).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+/// Number of items waiting in the spool, including any left in flight.
+pub fn queued_len(app: &tauri::AppHandle) -> usize {
+    let Ok(dir) = spool_dir(app) else {
+        return 0;
+    };
+    let inflight = read_items(&dir.join(INFLIGHT_FILE)).len();
+    let done = read_cursor(&dir.join(CURSOR_FILE)).min(inflight);
+    read_items(&dir.join(SPOOL_FILE)).len()
+        + (inflight - done)
+        + read_items(&dir.join(FAILED_FILE)).len()
+}
+
+async fn spool(
+    app: &tauri::AppHandle,
+    items: &[SyncPayloadItem],
+    extras: BatchExtras,
+) -> Result<(), String> {
+    let dir = spool_dir(app)?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let queued: Vec<QueuedItem> = items
+        .iter()
+        .map(|item| QueuedItem {
+            item: item.clone(),
+            attempts: 0,
+            extras,
+        })
+        .collect();
+    let _guard = SPOOL_LOCK.lock().await;
+    append_items(&dir.join(SPOOL_FILE), &queued)
+}
+
+/// Appends items to the on-disk spool and returns the new queue length.
+#[tauri::command]
+pub async fn queue_sync(payload: SyncPayload, app: tauri::AppHandle) -> Result<usize, String> {
+    spool(&app, &payload.items, BatchExtras::default()).await?;
+    Ok(queued_len(&app))
+}
+
+/// Spools items a direct sync didn't get to, remembering where they were
+/// headed so `resume_sync` can send them without being told. `extras` is
+/// applied again when they are flushed.
+pub async fn hold_for_resume(
+    app: &tauri::AppHandle,
+    server_url: &str,
+    items: &[SyncPayloadItem],
+    extras: BatchExtras,
+) -> Result<(), String> {
+    settings::update(app, |s| s.sync_server_url = Some(server_url.to_string()))?;
+    spool(app, items, extras).await
+}
+
+// `prepare_items` for each item with the extras it was queued with, off the
+// async runtime.
+async fn prepare_queued(
+    batch: &[QueuedItem],
+) -> Result<(Vec<SyncPayloadItem>, Vec<SyncErrorItem>), String> {
+    let batch = batch.to_vec();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut errors = Vec::new();
+        let items = batch
+            .into_iter()
+            .map(|queued| {
+                let mut item = [queued.item];
+                errors.extend(prepare_items(&mut item, queued.extras));
+                let [item] = item;
+                item
+            })
+            .collect();
+        (items, errors)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+// Sets aside items of a chunk that failed: back to the spool while a retry
+// may help, to the dead-letter file once it can't. Returns how many were
+// dead-lettered.
+fn set_aside(
+    dir: &std::path::Path,
+    chunk: &[QueuedItem],
+    error: &TauraError,
+) -> Result<usize, String> {
+    let (mut retry, mut dead) = (Vec::new(), Vec::new());
+    for queued in chunk {
+        let mut queued = queued.clone();
+        match error {
+            // signing in again fixes it; not the item's fault
+            TauraError::NotAuthenticated { .. } => retry.push(queued),
+            _ if error.is_retryable() => {
+                queued.attempts += 1;
+                if queued.attempts < MAX_ATTEMPTS {
+                    retry.push(queued);
+                } else {
+                    dead.push(queued);
+                }
+            }
+            _ => dead.push(queued),
+        }
+    }
+    if !retry.is_empty() {
+        append_items(&dir.join(FAILED_FILE), &retry)?;
+    }
+    if !dead.is_empty() {
+        log::warn!(
+            "{} queued items set aside in {}: {}",
+            dead.len(),
+            DEAD_LETTER_FILE,
+            error
+        );
+        append_items(&dir.join(DEAD_LETTER_FILE), &dead)?;
+    }
+    Ok(dead.len())
+}
+
+/// Uploads the spool in batches, prepared with the extras they were queued
+/// with. Batches that fail with a connection error, 429 or 5xx go back to
+/// the front of the spool for the next flush, items queued meanwhile after
+/// them; after `MAX_ATTEMPTS` such failures, or on any other error, items
+/// move to the dead-letter file instead. Progress is journaled per batch, so
+/// a flush cut short by a crash, sleep or `pause_sync` picks up at the first
+/// unsent batch.
+#[tauri::command]
+pub async fn flush_sync_queue(
+    server_url: String,
+    batch_size: Option<usize>,
+    app: tauri::AppHandle,
+) -> Result<FlushResult, String> {
+    if server_url.is_empty() {
+        return Err("server_url empty".into());
+    }
+    // remembered so background flushes know where to send
+    settings::update(&app, |s| s.sync_server_url = Some(server_url.clone()))?;
+    let _flushing = FLUSH_LOCK.lock().await;
+    let dir = spool_dir(&app)?;
+    let spool = dir.join(SPOOL_FILE);
+    let inflight = dir.join(INFLIGHT_FILE);
+    let cursor = dir.join(CURSOR_FILE);
+    let failed_file = dir.join(FAILED_FILE);
+    if is_paused() {
+        return Ok(FlushResult {
+            result: SyncResult::empty(),
+            sent: 0,
+            remaining: queued_len(&app),
+            dead_lettered: 0,
+            paused: true,
+        });
+    }
+    {
+        let _guard = SPOOL_LOCK.lock().await;
+        // a leftover in-flight file is from an interrupted flush; send it first
+        if !inflight.exists() && spool.exists() {
+            fs::rename(&spool, &inflight).map_err(|e| e.to_string())?;
+        }
+    }
+    let items = read_items(&inflight);
+    let start = read_cursor(&cursor).min(items.len());
+
+    let url = format!("{}/sync/stream", server_url.trim_end_matches('/'));
+    let limits = settings::load(&app).upload_limits;
+    let batch_size = batch_size
+        .or(limits.batch_size)
+        .unwrap_or(DEFAULT_SYNC_BATCH)
+        .max(1);
+    let chunk_max_bytes = limits
+        .chunk_max_bytes
+        .unwrap_or(DEFAULT_CHUNK_MAX_BYTES)
+        .max(1);
+    let opts = http::RequestOptions::with_defaults(http::STREAM_TIMEOUT);
+    let total = items.len();
+    let mut result = SyncResult::empty();
+    let mut sent = 0;
+    let mut dead_lettered = 0;
+    let mut paused = false;
+    for (index, batch) in items[start..].chunks(batch_size).enumerate() {
+        if is_paused() {
+            paused = true;
+            break;
+        }
+        let offset = start + index * batch_size;
+        let (prepared, read_errors) = prepare_queued(batch).await?;
+        if !read_errors.is_empty() {
+            result
+                .read_errors
+                .get_or_insert_with(Vec::new)
+                .extend(read_errors);
+        }
+        for (range, _) in byte_chunks(&prepared, chunk_max_bytes) {
+            let sending = &prepared[range.clone()];
+            let first = offset + range.start;
+            match sync_batch(&app, &url, sending, first, total, None, false, &opts).await {
+                Ok(part) => {
+                    sent += sending.len();
+                    result.absorb(part);
+                }
+                Err(err) => {
+                    log::warn!("queued sync batch {} failed: {}", index, err);
+                    // spooled as queued, without the prepared bytes
+                    dead_lettered += set_aside(&dir, &batch[range], &err)?;
+                }
+            }
+        }
+        write_cursor(&cursor, offset + batch.len())?;
+    }
+
+    // a paused flush keeps its journal as is for `resume_sync`
+    if !paused {
+        let _guard = SPOOL_LOCK.lock().await;
+        let mut remaining = read_items(&failed_file);
+        remaining.extend(read_items(&spool));
+        if remaining.is_empty() {
+            let _ = fs::remove_file(&spool);
+        } else {
+            write_items(&spool, &remaining)?;
+        }
+        // cursor first: a stale cursor must never apply to the next in-flight file
+        let _ = fs::remove_file(&cursor);
+        let _ = fs::remove_file(&failed_file);
+        let _ = fs::remove_file(&inflight);
+    }
+    Ok(FlushResult {
+        result,
+        sent,
+        remaining: queued_len(&app),
+        dead_lettered,
+        paused,
+    })
+}
+
+/// Stops uploads at the next batch boundary: queue flushes keep their place
+/// in the journal and `sync_index` spools what it hasn't sent. Stays paused
+/// until `resume_sync` or the next launch.
+#[tauri::command]
+pub async fn pause_sync(app: tauri::AppHandle) -> Result<QueueStatus, String> {
+    set_paused(&app, true);
+    sync_queue_status(app).await
+}
+
+/// Lifts a pause and sends the queue from where it stopped. Without
+/// `server_url` the last server synced to is used.
+#[tauri::command]
+pub async fn resume_sync(
+    server_url: Option<String>,
+    batch_size: Option<usize>,
+    app: tauri::AppHandle,
+) -> Result<FlushResult, String> {
+    set_paused(&app, false);
+    let server_url = server_url
+        .filter(|u| !u.trim().is_empty())
+        .or_else(|| settings::load(&app).sync_server_url)
+        .ok_or("no server to resume syncing to")?;
+    flush_sync_queue(server_url, batch_size, app).await
+}
+
+#[tauri::command]
+pub async fn sync_queue_status(app: tauri::AppHandle) -> Result<QueueStatus, String> {
+    let dir = spool_dir(&app)?;
+    let inflight = read_items(&dir.join(INFLIGHT_FILE)).len();
+    Ok(QueueStatus {
+        pending: queued_len(&app),
+        in_flight_done: read_cursor(&dir.join(CURSOR_FILE)).min(inflight),
+        paused: is_paused(),
+        flushing: FLUSH_LOCK.try_lock().is_err(),
+        server_url: settings::load(&app).sync_server_url,
+        dead_letter: read_items(&dir.join(DEAD_LETTER_FILE)).len(),
+    })
+}
+
+/// Best-effort flush used by background tasks; a no-op while paused or
+/// without a known server or pending items.
+pub async fn flush_in_background(app: &tauri::AppHandle) {
+    if is_paused() || queued_len(app) == 0 {
+        return;
+    }
+    let Some(server_url) = settings::load(app).sync_server_url else {
+        return;
+    };
+    match flush_sync_queue(server_url, None, app.clone()).await {
+        Ok(res) if res.remaining > 0 => {
+            log::warn!("background flush left {} items queued", res.remaining)
+        }
+        Ok(_) => {}
+        Err(err) => log::warn!("background flush failed: {}", err),
+    }
+}
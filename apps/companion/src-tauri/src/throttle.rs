@@ -0,0 +1,114 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const WINDOW: Duration = Duration::from_secs(3);
+const MAX_SLEEP_MS: f64 = 1000.0;
+// the fixed throttle sleeps its `throttle_ms` once per this much work
+const WORK_SLICE: Duration = Duration::from_millis(100);
+// per-file work this many times slower than the best seen means the disk or
+// CPU is contended, so we back off regardless of the rate target
+const LOAD_FACTOR: f64 = 2.5;
+
+/// Fixed throttle as a duty cycle: `throttle_ms` of sleep for every 100ms
+/// spent working, so the scan's share of CPU and disk stays the same whether
+/// files are tiny or slow to read.
+pub struct DutyCycle {
+    sleep_per_slice: Duration,
+    work_started: Instant,
+}
+
+impl DutyCycle {
+    pub fn new(throttle_ms: u64) -> Self {
+        DutyCycle {
+            sleep_per_slice: Duration::from_millis(throttle_ms),
+            work_started: Instant::now(),
+        }
+    }
+
+    /// The pause owed for the work since the last one; zero until a full
+    /// slice has been worked, so cheap files don't cause tiny sleeps.
+    pub fn due(&self) -> Duration {
+        let work = self.work_started.elapsed();
+        if work < WORK_SLICE {
+            return Duration::ZERO;
+        }
+        let owed = self
+            .sleep_per_slice
+            .mul_f64(work.as_secs_f64() / WORK_SLICE.as_secs_f64());
+        owed.min(Duration::from_millis(MAX_SLEEP_MS as u64))
+    }
+
+    /// Starts counting work again, after a sleep or a user pause.
+    pub fn restart(&mut self) {
+        self.work_started = Instant::now();
+    }
+}
+
+/// Picks the sleep between scan batches so throughput stays near
+/// `target_fps` files per second, measured over a sliding window.
+pub struct AdaptiveThrottle {
+    target_fps: f64,
+    sleep_ms: f64,
+    samples: VecDeque<(Instant, usize)>,
+    batch_started: Instant,
+    best_work_per_file: Option<Duration>,
+}
+
+impl AdaptiveThrottle {
+    pub fn new(target_fps: f64, initial_ms: u64) -> Self {
+        AdaptiveThrottle {
+            target_fps: target_fps.max(1.0),
+            sleep_ms: initial_ms as f64,
+            samples: VecDeque::new(),
+            batch_started: Instant::now(),
+            best_work_per_file: None,
+        }
+    }
+
+    pub fn current_ms(&self) -> u64 {
+        self.sleep_ms.round() as u64
+    }
+
+    /// Records that `processed` files are done after a batch of `batch`
+    /// files and returns how long to sleep before the next batch.
+    pub fn next_sleep(&mut self, processed: usize, batch: usize) -> Duration {
+        let now = Instant::now();
+        let work = now.duration_since(self.batch_started);
+        self.samples.push_back((now, processed));
+        while self
+            .samples
+            .front()
+            .is_some_and(|(t, _)| now.duration_since(*t) > WINDOW)
+        {
+            self.samples.pop_front();
+        }
+
+        let per_file = work / batch.max(1) as u32;
+        let best = *self.best_work_per_file.get_or_insert(per_file);
+        if per_file < best {
+            self.best_work_per_file = Some(per_file);
+        }
+        let loaded = per_file.as_secs_f64() > best.as_secs_f64() * LOAD_FACTOR;
+
+        if let (Some((t0, p0)), Some((t1, p1))) = (self.samples.front(), self.samples.back()) {
+            let span = t1.duration_since(*t0).as_secs_f64();
+            if span > 0.0 {
+                let rate = (p1 - p0) as f64 / span;
+                if loaded || rate > self.target_fps * 1.1 {
+                    self.sleep_ms = (self.sleep_ms * 1.25 + 1.0).min(MAX_SLEEP_MS);
+                } else if rate < self.target_fps * 0.9 {
+                    self.sleep_ms *= 0.8;
+                    if self.sleep_ms < 0.5 {
+                        self.sleep_ms = 0.0;
+                    }
+                }
+            }
+        }
+        Duration::from_millis(self.current_ms())
+    }
+
+    /// Marks the start of the next batch, after any sleep has finished.
+    pub fn batch_started(&mut self) {
+        self.batch_started = Instant::now();
+    }
+}
@@ -0,0 +1,349 @@
+use crate::{animation, cache, exif, ffmpeg, heic, media_modality, mp4, raw};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Write};
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_THUMBNAIL_EDGE: u32 = 256;
+// previews only; anything bigger belongs to the original
+const MAX_THUMBNAIL_EDGE: u32 = 1024;
+const THUMBNAIL_QUALITY: u8 = 80;
+// the server embeds uploads, so they keep more detail than a preview
+const UPLOAD_QUALITY: u8 = 90;
+// a video's preview comes from a tenth of the way in, past any fade from
+// black, but no later than this
+const VIDEO_PREVIEW_MAX_SECS: f64 = 10.0;
+// `generate_thumbnails` decodes this many files at once
+const MAX_THUMBNAIL_WORKERS: usize = 4;
+
+/// Encoding of cached previews. WebP is written lossless, the only WebP the
+/// image crate encodes, so it keeps alpha but is larger than JPEG.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ThumbnailFormat {
+    Jpeg,
+    Webp,
+}
+
+impl ThumbnailFormat {
+    pub fn parse(raw: Option<&str>) -> Result<Self, String> {
+        match raw.map(str::trim).filter(|s| !s.is_empty()) {
+            None | Some("jpeg") | Some("jpg") => Ok(Self::Jpeg),
+            Some("webp") => Ok(Self::Webp),
+            Some(other) => Err(format!(
+                "unknown thumbnail format \"{}\" (expected jpeg or webp)",
+                other
+            )),
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+        }
+    }
+}
+
+/// Decodes an image upright and downscales it so its longest edge fits
+/// `max_edge`.
+pub fn decode_thumbnail(path: &Path, max_edge: u32) -> Result<DynamicImage, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    Ok(fit(decode_image(path, &bytes, max_edge)?, max_edge))
+}
+
+/// Stored pixel size, read from the header without decoding. HEIC is left
+/// out: libheif reports it already rotated. So is RAW, whose header describes
+/// the sensor data rather than the picture.
+pub fn dimensions(path: &Path) -> Option<(u32, u32)> {
+    if heic::is_heif(path) || raw::is_raw(path) {
+        return None;
+    }
+    ImageReader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+fn decode_upright(bytes: &[u8]) -> Result<DynamicImage, String> {
+    let mut decoder = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?
+        .into_decoder()
+        .map_err(|e| e.to_string())?;
+    // a missing or unreadable tag just means the pixels are stored upright
+    let orientation = decoder.orientation().ok();
+    let mut img = DynamicImage::from_decoder(decoder).map_err(|e| e.to_string())?;
+    if let Some(orientation) = orientation {
+        img.apply_orientation(orientation);
+    }
+    Ok(img)
+}
+
+// Decodes the embedded preview. Cameras store it like the sensor data, so the
+// RAW's own orientation tag says how to turn it.
+fn decode_raw(bytes: &[u8]) -> Result<DynamicImage, String> {
+    let preview = raw::preview_jpeg(bytes).ok_or("RAW file has no embedded preview")?;
+    let mut img = image::load_from_memory_with_format(preview, ImageFormat::Jpeg)
+        .map_err(|e| e.to_string())?;
+    let orientation = exif::orientation_in(bytes)
+        .and_then(|o| Orientation::from_exif(o as u8))
+        .or_else(|| Orientation::from_exif(exif::orientation_in(preview)? as u8));
+    if let Some(orientation) = orientation {
+        img.apply_orientation(orientation);
+    }
+    Ok(img)
+}
+
+fn write_image(img: DynamicImage, out: &mut File, format: ThumbnailFormat) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(out);
+    match format {
+        ThumbnailFormat::Jpeg => {
+            let encoder = JpegEncoder::new_with_quality(&mut writer, THUMBNAIL_QUALITY);
+            // JPEG has no alpha channel
+            img.into_rgb8().write_with_encoder(encoder)
+        }
+        ThumbnailFormat::Webp => img
+            .into_rgba8()
+            .write_with_encoder(WebPEncoder::new_lossless(&mut writer)),
+    }
+    .map_err(std::io::Error::other)?;
+    writer.flush()
+}
+
+pub fn clamp_edge(max_edge: Option<u32>) -> u32 {
+    max_edge
+        .unwrap_or(DEFAULT_THUMBNAIL_EDGE)
+        .clamp(16, MAX_THUMBNAIL_EDGE)
+}
+
+/// Writes an upright JPEG preview of `path` into `cache_dir` and returns its
+/// path. See `cache_preview`.
+pub fn cache_thumbnail(path: &Path, cache_dir: &Path, max_edge: u32) -> Result<PathBuf, String> {
+    cache_preview(path, cache_dir, max_edge, ThumbnailFormat::Jpeg)
+}
+
+fn digest_name(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .take(16)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn fit(img: DynamicImage, max_edge: u32) -> DynamicImage {
+    if img.width() <= max_edge && img.height() <= max_edge {
+        img
+    } else {
+        img.thumbnail(max_edge, max_edge)
+    }
+}
+
+// Fits `img` into `max_edge` and writes it to a temp file beside the target
+// before renaming, so a half-written file is never reused and two writers of
+// the same preview don't share one.
+fn store(
+    img: DynamicImage,
+    out: &Path,
+    max_edge: u32,
+    format: ThumbnailFormat,
+) -> Result<(), String> {
+    let img = fit(img, max_edge);
+    let dir = out.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    // removed on drop unless persisted
+    let mut tmp = tempfile::NamedTempFile::new_in(dir).map_err(|e| e.to_string())?;
+    write_image(img, tmp.as_file_mut(), format).map_err(|e| e.to_string())?;
+    tmp.persist(out).map_err(|e| e.error.to_string())?;
+    Ok(())
+}
+
+// Videos are keyed by path, size and mtime rather than content: hashing a
+// whole video for a preview would cost more than decoding the frame.
+fn cache_video_preview(
+    path: &Path,
+    cache_dir: &Path,
+    max_edge: u32,
+    format: ThumbnailFormat,
+) -> Result<PathBuf, String> {
+    let md = std::fs::metadata(path).map_err(|e| e.to_string())?;
+    let mtime = md
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos());
+    let key = format!("{}\n{}\n{}", path.to_string_lossy(), md.len(), mtime);
+    let out = cache_dir.join(format!(
+        "{}_{}.{}",
+        digest_name(key.as_bytes()),
+        max_edge,
+        format.extension()
+    ));
+    if out.exists() {
+        return Ok(out);
+    }
+    let at = mp4::read_video_meta(path)
+        .and_then(|m| m.duration_secs)
+        .map_or(0.0, |d| (d / 10.0).min(VIDEO_PREVIEW_MAX_SECS));
+    // a seek past the last keyframe of a short clip finds nothing
+    let frame =
+        ffmpeg::keyframe(path, at, max_edge).or_else(|_| ffmpeg::keyframe(path, 0.0, max_edge))?;
+    store(frame, &out, max_edge, format)?;
+    Ok(out)
+}
+
+/// Writes an upright preview of an image or video into `cache_dir` and
+/// returns its path. Images are keyed by content hash and edge size, so
+/// renamed or copied originals reuse the same preview and an existing one is
+/// never redone. Video frames come from ffmpeg.
+pub fn cache_preview(
+    path: &Path,
+    cache_dir: &Path,
+    max_edge: u32,
+    format: ThumbnailFormat,
+) -> Result<PathBuf, String> {
+    if media_modality(path, false) == Some("video") {
+        return cache_video_preview(path, cache_dir, max_edge, format);
+    }
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    let out = cache_dir.join(format!(
+        "{}_{}.{}",
+        digest_name(&bytes),
+        max_edge,
+        format.extension()
+    ));
+    if out.exists() {
+        return Ok(out);
+    }
+    let img = decode_image(path, &bytes, max_edge)?;
+    store(img, &out, max_edge, format)?;
+    Ok(out)
+}
+
+// Upright pixels of any still or animated image the scan indexes.
+fn decode_image(path: &Path, bytes: &[u8], max_edge: u32) -> Result<DynamicImage, String> {
+    if heic::is_heif(path) {
+        heic::decode(bytes, max_edge)
+    } else if raw::is_raw(path) {
+        decode_raw(bytes)
+    } else if animation::is_animated_bytes(bytes) {
+        animation::representative_frame(bytes)
+    } else {
+        decode_upright(bytes)
+    }
+}
+
+/// An upright JPEG of the image at `path` fitted into `max_edge`, to upload
+/// in place of a larger original.
+pub fn downscaled_jpeg(path: &Path, max_edge: u32) -> Result<Vec<u8>, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    let img = fit(decode_image(path, &bytes, max_edge)?, max_edge);
+    let mut out = Vec::new();
+    img.into_rgb8()
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut out, UPLOAD_QUALITY))
+        .map_err(|e| e.to_string())?;
+    Ok(out)
+}
+
+pub fn cache_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    cache::subdir(app, cache::THUMBNAILS)
+}
+
+/// Generates (or reuses) a cached preview for one image or video and returns
+/// its path.
+#[tauri::command]
+pub async fn generate_thumbnail(
+    path: String,
+    max_edge: Option<u32>,
+    format: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<String, String> {
+    let dir = cache_dir(&app)?;
+    let edge = clamp_edge(max_edge);
+    let format = ThumbnailFormat::parse(format.as_deref())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        cache_preview(Path::new(&path), &dir, edge, format).map(|p| p.to_string_lossy().to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[derive(Serialize)]
+pub struct ThumbnailResult {
+    pub path: String,
+    pub thumbnail: Option<String>,
+    pub error: Option<String>,
+}
+
+/// `generate_thumbnail` for many files, e.g. a page of search results.
+/// Results come back in the order asked; a file that can't be previewed
+/// gets an `error` instead of failing the rest.
+#[tauri::command]
+pub async fn generate_thumbnails(
+    paths: Vec<String>,
+    max_edge: Option<u32>,
+    format: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<Vec<ThumbnailResult>, String> {
+    let dir = cache_dir(&app)?;
+    let edge = clamp_edge(max_edge);
+    let format = ThumbnailFormat::parse(format.as_deref())?;
+    if paths.is_empty() {
+        return Ok(Vec::new());
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let per_worker = paths.len().div_ceil(MAX_THUMBNAIL_WORKERS);
+        std::thread::scope(|s| {
+            let workers: Vec<_> = paths
+                .chunks(per_worker)
+                .map(|chunk| {
+                    let dir = &dir;
+                    let worker = s.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(
+                                |path| match cache_preview(Path::new(path), dir, edge, format) {
+                                    Ok(out) => ThumbnailResult {
+                                        path: path.clone(),
+                                        thumbnail: Some(out.to_string_lossy().to_string()),
+                                        error: None,
+                                    },
+                                    Err(err) => ThumbnailResult {
+                                        path: path.clone(),
+                                        thumbnail: None,
+                                        error: Some(err),
+                                    },
+                                },
+                            )
+                            .collect::<Vec<_>>()
+                    });
+                    (chunk, worker)
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|(chunk, worker)| {
+                    // a decoder that panicked takes its whole chunk with it
+                    worker.join().unwrap_or_else(|_| {
+                        chunk
+                            .iter()
+                            .map(|path| ThumbnailResult {
+                                path: path.clone(),
+                                thumbnail: None,
+                                error: Some("thumbnail worker panicked".into()),
+                            })
+                            .collect()
+                    })
+                })
+                .collect()
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
@@ -0,0 +1,312 @@
+use crate::http;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{DigitallySignedStruct, SignatureScheme};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use x509_parser::extensions::GeneralName;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn without_credentials(url: &reqwest::Url) -> String {
+    let mut url = url.clone();
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    url.to_string()
+}
+
+#[derive(Serialize)]
+pub struct CertSummary {
+    pub subject: String,
+    pub issuer: String,
+    pub not_before: String,
+    pub not_after: String,
+    pub sans: Vec<String>,
+    pub expired: bool,
+    pub not_yet_valid: bool,
+}
+
+#[derive(Serialize)]
+pub struct TlsReport {
+    pub host: String,
+    pub port: u16,
+    pub protocol: Option<String>,
+    pub trusted: bool,
+    pub trust_error: Option<String>,
+    pub chain: Vec<CertSummary>,
+    /// The proxy the connection was tunnelled through, without credentials.
+    pub proxy: Option<String>,
+    /// Set when a proxy is configured but couldn't be used, so the chain
+    /// came from a direct connection instead.
+    pub proxy_skipped: Option<String>,
+}
+
+// Accepts any chain so the handshake completes; the captured certificates are
+// checked separately against the real roots afterwards.
+#[derive(Debug)]
+struct CapturingVerifier {
+    provider: Arc<CryptoProvider>,
+    captured: Mutex<Vec<CertificateDer<'static>>>,
+}
+
+impl ServerCertVerifier for CapturingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if let Ok(mut guard) = self.captured.lock() {
+            guard.clear();
+            guard.push(end_entity.clone().into_owned());
+            guard.extend(intermediates.iter().map(|c| c.clone().into_owned()));
+        }
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+fn summarize(der: &CertificateDer<'_>, now: i64) -> Option<CertSummary> {
+    let (_, cert) = X509Certificate::from_der(der.as_ref()).ok()?;
+    let validity = cert.validity();
+    let sans = cert
+        .subject_alternative_name()
+        .ok()
+        .flatten()
+        .map(|ext| {
+            ext.value
+                .general_names
+                .iter()
+                .map(|name| match name {
+                    GeneralName::DNSName(dns) => dns.to_string(),
+                    GeneralName::IPAddress(raw) => match raw.len() {
+                        4 => std::net::Ipv4Addr::new(raw[0], raw[1], raw[2], raw[3]).to_string(),
+                        16 => {
+                            let mut octets = [0u8; 16];
+                            octets.copy_from_slice(raw);
+                            std::net::Ipv6Addr::from(octets).to_string()
+                        }
+                        _ => format!("{:?}", raw),
+                    },
+                    other => other.to_string(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let fmt_time = |t: &x509_parser::time::ASN1Time| {
+        chrono::DateTime::from_timestamp(t.timestamp(), 0)
+            .map(|dt| dt.to_rfc3339())
+            .unwrap_or_else(|| t.to_string())
+    };
+    Some(CertSummary {
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        not_before: fmt_time(&validity.not_before),
+        not_after: fmt_time(&validity.not_after),
+        sans,
+        expired: validity.not_after.timestamp() < now,
+        not_yet_valid: validity.not_before.timestamp() > now,
+    })
+}
+
+// Opens a tunnel to `host:port` with HTTP CONNECT, the way reqwest reaches
+// https servers through an http proxy.
+async fn connect_via_proxy(
+    proxy: &reqwest::Url,
+    host: &str,
+    port: u16,
+) -> Result<tokio::net::TcpStream, String> {
+    use base64::Engine;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    let proxy_host = proxy.host_str().ok_or("proxy_url has no host")?;
+    let proxy_port = proxy.port_or_known_default().unwrap_or(80);
+    let mut tcp = tokio::net::TcpStream::connect((proxy_host, proxy_port))
+        .await
+        .map_err(|e| {
+            format!(
+                "connection to proxy {}:{} failed: {}",
+                proxy_host, proxy_port, e
+            )
+        })?;
+    let target = if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    };
+    let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", target);
+    if !proxy.username().is_empty() {
+        let user = urlencoding::decode(proxy.username()).map_err(|e| e.to_string())?;
+        let pass =
+            urlencoding::decode(proxy.password().unwrap_or("")).map_err(|e| e.to_string())?;
+        let credentials =
+            base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, pass));
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", credentials));
+    }
+    request.push_str("\r\n");
+    tcp.write_all(request.as_bytes())
+        .await
+        .map_err(|e| format!("proxy CONNECT failed: {}", e))?;
+    // byte by byte, so nothing past the headers is taken from the TLS stream
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() > 8 * 1024 {
+            return Err("proxy CONNECT response too long".to_string());
+        }
+        let byte = tcp
+            .read_u8()
+            .await
+            .map_err(|e| format!("proxy CONNECT failed: {}", e))?;
+        head.push(byte);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let status = head.lines().next().unwrap_or_default();
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(format!("proxy refused CONNECT: {}", status.trim()));
+    }
+    Ok(tcp)
+}
+
+/// Connects to the server the way the HTTP client would and reports the
+/// certificate chain it presents. Goes through the saved proxy when that is
+/// an http one; other proxies are noted in `proxy_skipped`.
+#[tauri::command]
+pub async fn inspect_tls(server_url: String, app: tauri::AppHandle) -> Result<TlsReport, String> {
+    let url = reqwest::Url::parse(server_url.trim()).map_err(|e| e.to_string())?;
+    if url.scheme() != "https" {
+        return Err(format!("{} is not an https url", url));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| "server_url has no host".to_string())?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let port = url.port_or_known_default().unwrap_or(443);
+    let server_name = ServerName::try_from(host.clone()).map_err(|e| e.to_string())?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let verifier = Arc::new(CapturingVerifier {
+        provider: provider.clone(),
+        captured: Mutex::new(Vec::new()),
+    });
+    let config = rustls::ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .dangerous()
+        .with_custom_certificate_verifier(verifier.clone())
+        .with_no_client_auth();
+    let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+
+    let proxy_url = http::saved_proxy(&app)
+        .map(|p| reqwest::Url::parse(&p).map_err(|e| format!("invalid proxy_url: {}", e)))
+        .transpose()?;
+    let (proxy, proxy_skipped) = match &proxy_url {
+        Some(url) if url.scheme() == "http" => (Some(without_credentials(url)), None),
+        Some(url) => (
+            None,
+            Some(format!(
+                "{} proxies aren't supported here; connected directly",
+                url.scheme()
+            )),
+        ),
+        None => (None, None),
+    };
+    let connect = async {
+        match proxy_url.as_ref().filter(|_| proxy.is_some()) {
+            Some(url) => connect_via_proxy(url, &host, port).await,
+            None => tokio::net::TcpStream::connect((host.as_str(), port))
+                .await
+                .map_err(|e| format!("connection to {}:{} failed: {}", host, port, e)),
+        }
+    };
+    let tcp = tokio::time::timeout(CONNECT_TIMEOUT, connect)
+        .await
+        .map_err(|_| format!("connection to {}:{} timed out", host, port))??;
+    let tls = tokio::time::timeout(CONNECT_TIMEOUT, connector.connect(server_name.clone(), tcp))
+        .await
+        .map_err(|_| "TLS handshake timed out".to_string())?
+        .map_err(|e| format!("TLS handshake failed: {}", e))?;
+    let protocol = tls
+        .get_ref()
+        .1
+        .protocol_version()
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    let chain = verifier
+        .captured
+        .lock()
+        .map_err(|_| "lock poisoned")?
+        .clone();
+    let (end_entity, intermediates) = chain
+        .split_first()
+        .ok_or_else(|| "server presented no certificates".to_string())?;
+
+    let mut roots = rustls::RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let trust_error =
+        match WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider).build() {
+            Ok(webpki) => webpki
+                .verify_server_cert(
+                    end_entity,
+                    intermediates,
+                    &server_name,
+                    &[],
+                    UnixTime::now(),
+                )
+                .err()
+                .map(|e| e.to_string()),
+            Err(e) => Some(e.to_string()),
+        };
+
+    let now = chrono::Utc::now().timestamp();
+    Ok(TlsReport {
+        host,
+        port,
+        protocol,
+        trusted: trust_error.is_none(),
+        trust_error,
+        chain: chain.iter().filter_map(|c| summarize(c, now)).collect(),
+        proxy,
+        proxy_skipped,
+    })
+}
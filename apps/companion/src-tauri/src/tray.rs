@@ -0,0 +1,173 @@
+use crate::{show_main_window, show_overlay, sync_queue, toggle_overlay};
+use serde_json::Value;
+use tauri::menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
+use tauri::{Emitter, Listener};
+
+const TRAY_ID: &str = "main";
+const IDLE_TOOLTIP: &str = "Taura";
+
+fn set_tooltip(app: &tauri::AppHandle, status: Option<String>) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let text = match status {
+        Some(status) => format!("{} \u{2014} {}", IDLE_TOOLTIP, status),
+        None => IDLE_TOOLTIP.to_string(),
+    };
+    if let Err(err) = tray.set_tooltip(Some(text)) {
+        log::warn!("tray: tooltip not updated: {}", err);
+    }
+}
+
+fn count(payload: &Value, key: &str) -> u64 {
+    payload.get(key).and_then(Value::as_u64).unwrap_or(0)
+}
+
+fn flag(payload: &Value, key: &str) -> bool {
+    payload.get(key).and_then(Value::as_bool).unwrap_or(false)
+}
+
+fn scan_status(payload: &Value) -> String {
+    if flag(payload, "done") {
+        format!("scan finished, {} media files", count(payload, "matched"))
+    } else if flag(payload, "paused") {
+        "scan paused".to_string()
+    } else {
+        format!("scanning, {} files checked", count(payload, "processed"))
+    }
+}
+
+fn sync_status(payload: &Value) -> String {
+    let (sent, total) = (count(payload, "sent"), count(payload, "total"));
+    if sent >= total {
+        format!("synced {} items", total)
+    } else {
+        format!("syncing {} of {}", sent, total)
+    }
+}
+
+// The tooltip follows the events the UI already gets, so every scan and
+// sync shows up no matter who started it.
+fn follow_progress(app: &tauri::AppHandle, pause_item: CheckMenuItem<tauri::Wry>) {
+    let handle = app.clone();
+    app.listen_any("scan_progress", move |event| {
+        if let Ok(payload) = serde_json::from_str::<Value>(event.payload()) {
+            set_tooltip(&handle, Some(scan_status(&payload)));
+        }
+    });
+    let handle = app.clone();
+    app.listen_any("sync_progress", move |event| {
+        // a paused sync keeps saying so until it resumes
+        if sync_queue::is_paused() {
+            return;
+        }
+        if let Ok(payload) = serde_json::from_str::<Value>(event.payload()) {
+            set_tooltip(&handle, Some(sync_status(&payload)));
+        }
+    });
+    let handle = app.clone();
+    app.listen_any("sync_paused", move |event| {
+        let paused = event.payload() == "true";
+        if let Err(err) = pause_item.set_checked(paused) {
+            log::warn!("tray: pause item not updated: {}", err);
+        }
+        set_tooltip(&handle, paused.then(|| "sync paused".to_string()));
+    });
+}
+
+fn on_menu(app: &tauri::AppHandle, event: MenuEvent) {
+    let handle = app.clone();
+    match event.id().as_ref() {
+        "overlay" => {
+            tauri::async_runtime::spawn(async move {
+                if let Err(err) = show_overlay(handle).await {
+                    log::warn!("tray: overlay not shown: {}", err);
+                }
+            });
+        }
+        "main" => {
+            tauri::async_runtime::spawn(async move {
+                let _ = show_main_window(handle).await;
+            });
+        }
+        "scan" => {
+            // the UI owns the scan-and-sync run
+            let _ = app.emit("scan-requested", ());
+        }
+        "sync" => {
+            // uploads spooled while offline or paused go now, without a rescan
+            tauri::async_runtime::spawn(async move {
+                sync_queue::flush_in_background(&handle).await;
+            });
+        }
+        "pause" => {
+            tauri::async_runtime::spawn(async move {
+                if sync_queue::is_paused() {
+                    if let Err(err) = sync_queue::resume_sync(None, None, handle).await {
+                        log::warn!("tray: sync not resumed: {}", err);
+                    }
+                } else {
+                    let _ = sync_queue::pause_sync(handle).await;
+                }
+            });
+        }
+        "quit" => app.exit(0),
+        _ => {}
+    }
+}
+
+fn on_tray(tray: &TrayIcon, event: TrayIconEvent) {
+    if let TrayIconEvent::Click {
+        button: MouseButton::Left,
+        button_state: MouseButtonState::Up,
+        ..
+    } = event
+    {
+        let handle = tray.app_handle().clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(err) = toggle_overlay(handle).await {
+                log::warn!("tray: overlay not toggled: {}", err);
+            }
+        });
+    }
+}
+
+/// Adds the tray icon: left-click toggles the overlay, the menu covers the
+/// rest. It stays reachable when the global shortcut couldn't be registered.
+/// The tooltip shows the running scan or sync.
+pub fn create(app: &tauri::AppHandle) -> tauri::Result<()> {
+    let pause_item = CheckMenuItem::with_id(
+        app,
+        "pause",
+        "Pause sync",
+        true,
+        sync_queue::is_paused(),
+        None::<&str>,
+    )?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &MenuItem::with_id(app, "overlay", "Open search overlay", true, None::<&str>)?,
+            &MenuItem::with_id(app, "main", "Show main window", true, None::<&str>)?,
+            &PredefinedMenuItem::separator(app)?,
+            &MenuItem::with_id(app, "scan", "Scan now", true, None::<&str>)?,
+            &MenuItem::with_id(app, "sync", "Sync now", true, None::<&str>)?,
+            &pause_item,
+            &PredefinedMenuItem::separator(app)?,
+            &MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?,
+        ],
+    )?;
+    let mut tray = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip(IDLE_TOOLTIP)
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(on_menu)
+        .on_tray_icon_event(on_tray);
+    if let Some(icon) = app.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    tray.build(app)?;
+    follow_progress(app, pause_item);
+    Ok(())
+}
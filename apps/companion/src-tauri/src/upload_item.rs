@@ -0,0 +1,95 @@
+use crate::{local_path, media_modality, settings, tag_active_user, thumbnail};
+use crate::{ndjson_line_len, SyncPayloadItem, DEFAULT_CHUNK_MAX_BYTES};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use std::path::Path;
+
+// Smaller targets would leave nothing worth embedding.
+const MIN_UPLOAD_EDGE: u32 = 16;
+
+fn over_limit(len: u64, max_bytes: u64) -> String {
+    format!(
+        "{} bytes is over the {} byte request limit; let sync_index read it",
+        len, max_bytes
+    )
+}
+
+// The bytes to upload: a downscaled JPEG when the image is larger than
+// `max_edge`, the file as it is otherwise. Files whose base64 alone can't fit
+// a request are refused before they are read.
+fn upload_bytes(
+    path: &Path,
+    modality: &str,
+    max_edge: Option<u32>,
+    max_bytes: u64,
+) -> Result<Vec<u8>, String> {
+    if let Some(edge) = max_edge.map(|e| e.max(MIN_UPLOAD_EDGE)) {
+        // HEIC and RAW report no size up front, so with an edge they always
+        // get converted
+        let fits = thumbnail::dimensions(path).is_some_and(|(w, h)| w.max(h) <= edge);
+        if modality == "image" && !fits {
+            return thumbnail::downscaled_jpeg(path, edge);
+        }
+    }
+    let size = std::fs::metadata(path).map_err(|e| e.to_string())?.len();
+    let encoded = usize::try_from(size)
+        .ok()
+        .and_then(|size| base64::encoded_len(size, true))
+        .map_or(u64::MAX, |len| len as u64);
+    if encoded > max_bytes {
+        return Err(over_limit(encoded, max_bytes));
+    }
+    std::fs::read(path).map_err(|e| e.to_string())
+}
+
+/// Reads a media file into a `SyncPayloadItem` with `bytes_b64` filled, so
+/// the webview never holds the file or its base64. Images larger than
+/// `max_edge` are sent as an upright JPEG fitted into it (HEIC and RAW are
+/// always converted when it is set); anything else goes unchanged. Either
+/// way the item's NDJSON line must fit one upload request
+/// (`chunk_max_bytes`). `user_id` defaults to the active account.
+#[tauri::command]
+pub async fn prepare_upload_item(
+    path: String,
+    max_edge: Option<u32>,
+    user_id: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<SyncPayloadItem, String> {
+    let max_bytes = settings::load(&app)
+        .upload_limits
+        .chunk_max_bytes
+        .unwrap_or(DEFAULT_CHUNK_MAX_BYTES);
+    let path = path.trim().to_string();
+    let mut item = tauri::async_runtime::spawn_blocking(move || {
+        let file = local_path(&path);
+        if !file.is_file() {
+            return Err("not a file".to_string());
+        }
+        let modality = media_modality(&file, true).ok_or("not a supported media file")?;
+        let bytes = upload_bytes(&file, modality, max_edge, max_bytes)?;
+        let ts = std::fs::metadata(&file)
+            .and_then(|md| md.modified())
+            .ok()
+            .map(|mt| DateTime::<Utc>::from(mt).to_rfc3339());
+        let item = SyncPayloadItem {
+            user_id: user_id.unwrap_or_default(),
+            modality: modality.to_string(),
+            uri: path,
+            ts,
+            bytes_b64: Some(base64::engine::general_purpose::STANDARD.encode(bytes)),
+            frames_b64: None,
+            page: None,
+            text: None,
+        };
+        // the line the request carries, base64 and JSON included
+        let line = ndjson_line_len(&item);
+        if line > max_bytes {
+            return Err(over_limit(line, max_bytes));
+        }
+        Ok(item)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    tag_active_user(&app, std::slice::from_mut(&mut item));
+    Ok(item)
+}
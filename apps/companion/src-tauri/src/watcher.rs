@@ -0,0 +1,342 @@
+use crate::{
+    delete_indexed, filter_indexed, media_modality, settings, sync_index, sync_queue, BatchExtras,
+    SyncPayload, SyncPayloadItem,
+};
+use chrono::{DateTime, Utc};
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tauri::Emitter;
+
+// Editors and camera imports write in bursts; one sync per quiet window.
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+// Dropping a debouncer stops its watcher and ends the sync task behind it.
+static WATCHERS: Lazy<Mutex<HashMap<String, Debouncer<RecommendedWatcher>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// A folder kept in sync in the background, persisted across restarts.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WatchedFolder {
+    pub path: String,
+    pub server_url: String,
+    pub user_id: String,
+}
+
+// Media paths from a batch that no longer exist. A removed folder shows up
+// as a single path without a media extension, so its contents aren't caught.
+fn removed_uris(paths: &BTreeSet<PathBuf>) -> Vec<String> {
+    paths
+        .iter()
+        .filter(|p| !p.exists() && media_modality(p, false).is_some())
+        .filter_map(|p| p.to_str().map(|s| s.to_string()))
+        .collect()
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ChangeKind {
+    Created,
+    Modified,
+    Deleted,
+}
+
+#[derive(Serialize)]
+struct MediaChange {
+    uri: String,
+    kind: ChangeKind,
+}
+
+// A file born since the batch started settling is new; older ones were
+// edited. Without birth times (some Linux filesystems) everything counts as
+// modified.
+fn change_kind(md: &std::fs::Metadata) -> ChangeKind {
+    let window = DEBOUNCE * 3;
+    let born_recently = md.created().ok().is_some_and(|born| {
+        SystemTime::now()
+            .duration_since(born)
+            .map_or(true, |age| age <= window)
+    });
+    if born_recently {
+        ChangeKind::Created
+    } else {
+        ChangeKind::Modified
+    }
+}
+
+fn changed_items(
+    user_id: &str,
+    paths: &BTreeSet<PathBuf>,
+) -> (Vec<SyncPayloadItem>, Vec<MediaChange>) {
+    let mut changes = Vec::new();
+    let items = paths
+        .iter()
+        .filter(|p| p.is_file())
+        .filter_map(|p| {
+            let modality = media_modality(p, false)?;
+            let uri = p.to_str()?.to_string();
+            let md = std::fs::metadata(p).ok();
+            let ts = md
+                .as_ref()
+                .and_then(|md| md.modified().ok())
+                .map(|mt| DateTime::<Utc>::from(mt).to_rfc3339());
+            changes.push(MediaChange {
+                uri: uri.clone(),
+                kind: md.as_ref().map_or(ChangeKind::Modified, change_kind),
+            });
+            Some(SyncPayloadItem {
+                user_id: user_id.to_string(),
+                modality: modality.to_string(),
+                uri,
+                ts,
+                bytes_b64: None,
+                frames_b64: None,
+                page: None,
+                text: None,
+            })
+        })
+        .collect();
+    (items, changes)
+}
+
+/// Why `sync_or_hold` didn't sync, and whether its items were spooled.
+pub struct HeldSync {
+    pub error: String,
+    pub queued: bool,
+}
+
+/// Syncs whichever of `items` the server is missing and returns how many
+/// were upserted. Nothing looks at a file again until it changes, so when
+/// that fails the items are spooled for `resume_sync`, headed for `folder`'s
+/// server.
+pub async fn sync_or_hold(
+    app: &tauri::AppHandle,
+    folder: &WatchedFolder,
+    items: Vec<SyncPayloadItem>,
+) -> Result<usize, HeldSync> {
+    let pending = items.clone();
+    let result = async {
+        let missing = filter_indexed(
+            folder.server_url.clone(),
+            SyncPayload { items },
+            None,
+            None,
+            None,
+            None,
+            None,
+            app.clone(),
+        )
+        .await?;
+        sync_index(
+            folder.server_url.clone(),
+            SyncPayload { items: missing },
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            app.clone(),
+        )
+        .await
+    }
+    .await;
+    match result {
+        Ok(res) => Ok(res.upserted),
+        Err(err) => {
+            let queued = sync_queue::hold_for_resume(
+                app,
+                &folder.server_url,
+                &pending,
+                BatchExtras::default(),
+            )
+            .await;
+            if let Err(queue_err) = &queued {
+                log::warn!(
+                    "could not queue {} files of {}: {}",
+                    pending.len(),
+                    folder.path,
+                    queue_err
+                );
+            }
+            Err(HeldSync {
+                error: err.to_string(),
+                queued: queued.is_ok(),
+            })
+        }
+    }
+}
+
+async fn sync_changed(app: &tauri::AppHandle, folder: &WatchedFolder, paths: BTreeSet<PathBuf>) {
+    let user_id = folder.user_id.clone();
+    let ((items, mut changes), removed) = tokio::task::spawn_blocking(move || {
+        (changed_items(&user_id, &paths), removed_uris(&paths))
+    })
+    .await
+    .unwrap_or_default();
+    changes.extend(removed.iter().map(|uri| MediaChange {
+        uri: uri.clone(),
+        kind: ChangeKind::Deleted,
+    }));
+    if !changes.is_empty() {
+        let _ = app.emit(
+            "media_changed",
+            serde_json::json!({ "path": folder.path, "changes": changes }),
+        );
+    }
+    if !removed.is_empty() {
+        delete_removed(app, folder, removed).await;
+    }
+    if items.is_empty() {
+        return;
+    }
+    let files = items.len();
+    let _ = app.emit(
+        "watch_event",
+        serde_json::json!({ "path": folder.path, "files": files, "status": "syncing" }),
+    );
+    let event = match sync_or_hold(app, folder, items).await {
+        Ok(upserted) => serde_json::json!({
+            "path": folder.path,
+            "files": files,
+            "status": "synced",
+            "upserted": upserted,
+        }),
+        Err(failure) => {
+            log::warn!("watch sync for {} failed: {}", folder.path, failure.error);
+            serde_json::json!({
+                "path": folder.path,
+                "files": files,
+                "status": if failure.queued { "queued" } else { "error" },
+                "error": failure.error,
+            })
+        }
+    };
+    let _ = app.emit("watch_event", event);
+}
+
+async fn delete_removed(app: &tauri::AppHandle, folder: &WatchedFolder, uris: Vec<String>) {
+    let files = uris.len();
+    let result = delete_indexed(
+        folder.server_url.clone(),
+        uris,
+        Some(folder.user_id.clone()),
+        None,
+        None,
+        None,
+        None,
+        None,
+        app.clone(),
+    )
+    .await;
+    let event = match result {
+        Ok(res) => serde_json::json!({
+            "path": folder.path,
+            "files": files,
+            "status": "deleted",
+            "deleted": res.deleted,
+        }),
+        Err(err) => {
+            log::warn!("watch delete for {} failed: {}", folder.path, err);
+            serde_json::json!({
+                "path": folder.path,
+                "files": files,
+                "status": "error",
+                "error": err.to_string(),
+            })
+        }
+    };
+    let _ = app.emit("watch_event", event);
+}
+
+fn start(app: &tauri::AppHandle, folder: WatchedFolder) -> Result<(), String> {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<BTreeSet<PathBuf>>();
+    let mut debouncer = new_debouncer(DEBOUNCE, move |res: DebounceEventResult| match res {
+        Ok(events) => {
+            let _ = tx.send(events.into_iter().map(|e| e.path).collect());
+        }
+        Err(err) => log::warn!("folder watch error: {}", err),
+    })
+    .map_err(|e| e.to_string())?;
+    debouncer
+        .watcher()
+        .watch(Path::new(&folder.path), RecursiveMode::Recursive)
+        .map_err(|e| e.to_string())?;
+    {
+        let mut watchers = WATCHERS.lock().map_err(|_| "lock poisoned")?;
+        // replacing an existing watcher drops it, which stops it
+        watchers.insert(folder.path.clone(), debouncer);
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        // batches are synced one at a time, in the order they settled
+        while let Some(paths) = rx.recv().await {
+            sync_changed(&app, &folder, paths).await;
+        }
+    });
+    Ok(())
+}
+
+/// Re-creates watchers for folders saved by earlier sessions. Folders that
+/// can't be watched right now (e.g. an unplugged drive) stay saved.
+pub fn restore(app: &tauri::AppHandle) {
+    for folder in settings::load(app).watched_folders {
+        let path = folder.path.clone();
+        if let Err(err) = start(app, folder) {
+            log::warn!("could not watch {}: {}", path, err);
+        }
+    }
+}
+
+/// Watches `path` recursively, syncs new or modified media files to
+/// `server_url` and deletes removed ones from it. Each settled batch of
+/// changes is announced as `media_changed`; files whose sync fails are put
+/// in the sync queue. The folder is remembered and watched again on next
+/// launch.
+#[tauri::command]
+pub async fn watch_folder(
+    path: String,
+    server_url: String,
+    user_id: String,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    if server_url.is_empty() {
+        return Err("server_url empty".into());
+    }
+    if user_id.is_empty() {
+        return Err("user_id empty".into());
+    }
+    if !Path::new(&path).is_dir() {
+        return Err("not a folder".into());
+    }
+    let folder = WatchedFolder {
+        path,
+        server_url,
+        user_id,
+    };
+    start(&app, folder.clone())?;
+    settings::update(&app, |s| {
+        s.watched_folders.retain(|f| f.path != folder.path);
+        s.watched_folders.push(folder);
+    })
+}
+
+#[tauri::command]
+pub async fn unwatch_folder(path: String, app: tauri::AppHandle) -> Result<(), String> {
+    {
+        let mut watchers = WATCHERS.lock().map_err(|_| "lock poisoned")?;
+        watchers.remove(&path);
+    }
+    settings::update(&app, |s| s.watched_folders.retain(|f| f.path != path))
+}
@@ -17,6 +17,138 @@ export interface SearchResultItem {
   source?: string | null
 }
 
+/** Result shape returned by the companion's `search` command. */
+export interface SearchHit {
+  media_id: string
+  path: string
+  score: number
+  modality: string
+  thumbnail?: string | null
+  ts?: string | null
+}
+
+/** One entry from `generate_thumbnails`, in the order the paths were given. */
+export interface ThumbnailResult {
+  path: string
+  /** Cached preview under the app cache dir; null when `error` is set. */
+  thumbnail?: string | null
+  error?: string | null
+}
+
+/**
+ * Error object rejected by companion commands that report structured errors
+ * (scan_folder, sync_index, filter_indexed and the sign-in/session commands).
+ * Branch on `kind`; `message` is only for display and may be reworded.
+ */
+export interface TauraError {
+  kind: 'not_authenticated' | 'network' | 'server' | 'invalid_input' | 'cancelled' | 'io' | 'other'
+  message: string
+  /** HTTP status, only for `server`. */
+  status?: number
+}
+
+export function isTauraError(e: unknown): e is TauraError {
+  return typeof e === 'object' && e !== null && typeof (e as any).kind === 'string' && typeof (e as any).message === 'string'
+}
+
+/** Display text for anything a command can reject with. */
+export function errorMessage(e: unknown): string {
+  if (isTauraError(e)) return e.message
+  if (e instanceof Error) return e.message
+  return String(e)
+}
+
+/** Result of the companion's `ping_server` command. */
+export interface PingResult {
+  status: 'unreachable' | 'unauthorized' | 'server_error' | 'ok'
+  latency_ms?: number | null
+  http_status?: number | null
+  error?: string | null
+}
+
+/** Server-side embedding backlog, from `get_embed_status` and `embed_status` events. */
+export interface EmbedStatus {
+  queue_depth: number
+  embedded?: number | null
+  failed: { uri: string; error: string }[]
+}
+
+/** Result of `describe_session`; never includes tokens. */
+export interface SessionInfo {
+  account_id: string
+  email?: string | null
+  name?: string | null
+  picture?: string | null
+  provider: string
+  expires_at?: number | null
+  scopes: string[]
+  has_refresh_token: boolean
+}
+
+/** Result of `build_auth_url`; pass `handle` and the pasted redirect to `complete_auth`. */
+export interface AuthUrl {
+  url: string
+  state: string
+  handle: string
+}
+
+/** Defaults for sync arguments a caller leaves out; unset keeps the built-in ones. */
+export interface UploadLimits {
+  batch_size?: number | null
+  inline_small_files?: boolean | null
+  inline_max_bytes?: number | null
+  /** Upper bound on one upload request's body before compression; 10 MB when unset. */
+  chunk_max_bytes?: number | null
+  max_retries?: number | null
+  /** Upload speed cap shared by all syncs, in bytes per second; uncapped when unset. */
+  max_bytes_per_sec?: number | null
+}
+
+/** Argument of `set_sync_schedule`; set exactly one of `interval_minutes` and `cron`. */
+export interface SyncSchedule {
+  interval_minutes?: number | null
+  /** Five-field cron expression in local time, e.g. "30 2 * * *". */
+  cron?: string | null
+  /** Hold runs while on battery; on when unset. */
+  require_ac_power?: boolean | null
+  min_idle_secs?: number | null
+}
+
+/** Result of `get_settings` and `update_settings`. */
+export interface AppSettings {
+  default_throttle_ms?: number | null
+  sync_server_url?: string | null
+  watched_folders: { path: string; server_url: string; user_id: string }[]
+  library_folders: { path: string; added_at: string }[]
+  overlay_shortcut?: string | null
+  proxy_url?: string | null
+  overlay_hide_on_blur?: boolean | null
+  close_to_tray?: boolean | null
+  media_extensions: string[]
+  excluded_globs: string[]
+  /** Extra globs per folder, set with `set_exclusions`. */
+  folder_exclusions: Record<string, string[]>
+  default_folders: string[]
+  upload_limits: UploadLimits
+  sync_schedule?: SyncSchedule | null
+}
+
+/** `patch` for `update_settings`; absent fields are left alone. */
+export interface SettingsPatch {
+  default_throttle_ms?: number
+  media_extensions?: string[]
+  excluded_globs?: string[]
+  default_folders?: string[]
+  overlay_shortcut?: string
+  /** Empty string goes back to the environment's proxy. */
+  proxy_url?: string
+  server_url?: string
+  overlay_hide_on_blur?: boolean
+  close_to_tray?: boolean
+  /** Replaces the stored limits as a whole. */
+  upload_limits?: UploadLimits
+}
+
 export interface SearchResponse {
   results: SearchResultItem[]
 }
@@ -1,8 +1,8 @@
 import { listen } from '@tauri-apps/api/event'
 import { invoke } from '@tauri-apps/api/core'
-import { readFile } from '@tauri-apps/plugin-fs'
 import { useCallback, useSyncExternalStore } from 'react'
 import { getConfig, subscribeConfig } from './state/config'
+import { errorMessage } from './api'
 
 // ---- Types ----
 export type IndexerPhase = 'idle' | 'scanning' | 'uploading' | 'error'
@@ -22,9 +22,56 @@ export interface SyncResult {
   embed_queue_depth?: number
   embed_errors?: SyncErrorItem[]
   read_errors?: SyncErrorItem[]
+  failed_batches?: SyncBatchError[]
+  dry_run?: DryRunSummary
+  /** Set by `sync_missing`: items skipped because the server already had them. */
+  already_indexed?: number
+  /** Set by `sync_index`: items left in the sync queue for `resume_sync`. */
+  queued?: number
+  /** Uploads resent after a 429, 5xx or connection error before going through. */
+  retries?: number
+}
+
+/** Result of `sync_queue_status`, `pause_sync` and part of `resume_sync`'s. */
+export interface SyncQueueStatus {
+  pending: number
+  in_flight_done: number
+  paused: boolean
+  flushing: boolean
+  server_url?: string | null
+  /** Items set aside after being refused or failing too many flushes; never resent. */
+  dead_letter: number
+}
+
+/** `videoFrames` option of `sync_index`/`sync_missing`; needs ffmpeg on PATH or in `TAURA_FFMPEG`. */
+export interface FrameSampling {
+  every_secs?: number
+  max_frames?: number
+  max_edge?: number
+}
+
+/** `pdfPages` option of `sync_index`/`sync_missing`; page images need poppler's `pdftoppm`. */
+export interface PageSplit {
+  max_pages?: number
+  max_edge?: number
+  render?: boolean
+  text?: boolean
+}
+
+export interface DryRunSummary {
+  would_upload: number
+  estimated_bytes: number
+  already_indexed: number
+}
+
+export interface SyncBatchError {
+  index: number
+  items: number
+  error: string
 }
 
 export interface ScanProgressEvent {
+  scan_id?: string
   path: string
   processed: number
   total: number
@@ -33,6 +80,175 @@ export interface ScanProgressEvent {
   cancelled?: boolean
 }
 
+/** Emitted as `media_changed` for each settled batch of changes in a watched folder. */
+export interface MediaChangedEvent {
+  path: string
+  changes: { uri: string; kind: 'created' | 'modified' | 'deleted' }[]
+}
+
+/** Emitted as `sync_progress` while `sync_index` hands lines to the connection. */
+export interface SyncProgressEvent {
+  sent: number
+  total: number
+}
+
+/**
+ * Emitted as `sync_chunk` after each chunk `sync_index` finishes. Counts
+ * what the server confirmed, so it trails `sync_progress`.
+ */
+export interface SyncChunkEvent {
+  confirmed: number
+  total: number
+  bytes_sent: number
+  upserted: number
+  chunk: number
+  failed_chunks: number
+}
+
+/** Emitted as `scan_delta` by `incremental_scan`, in chunks of up to 500 changes. */
+export interface ScanDeltaEvent {
+  scan_id: string
+  path: string
+  changes: { uri: string; kind: 'added' | 'changed' | 'removed' }[]
+}
+
+/** Scanned file as returned by `scan_folder` and `incremental_scan`. */
+export interface ScannedMedia {
+  path: string
+  modality: string
+  size: number
+  modified?: string | null
+  [field: string]: unknown
+}
+
+/** Result of `incremental_scan`: differences from the local catalog. */
+export interface ScanDelta {
+  scan_id: string
+  added: ScannedMedia[]
+  changed: ScannedMedia[]
+  removed: string[]
+  unchanged: number
+  /** False after a cancelled or timed-out walk; `removed` is empty then. */
+  complete: boolean
+  errors: string[]
+}
+
+/** Result of `stop_scan`; `stopped` is false when no scan was running. */
+export interface StopScanResult {
+  stopped: boolean
+  scan_ids: string[]
+}
+
+export type CatalogSyncStatus = 'pending' | 'synced' | 'failed'
+
+/** Result of `get_index_stats`, counted from the local catalog. */
+export interface IndexStats {
+  total: number
+  total_bytes: number
+  by_modality: Record<string, number>
+  by_status: Partial<Record<CatalogSyncStatus, number>>
+  last_scanned_at?: string | null
+}
+
+/** One file in the local catalog, as returned by `query_index`. */
+export interface IndexEntry {
+  path: string
+  size: number
+  mtime?: string | null
+  modality: string
+  hash?: string | null
+  sync_status: CatalogSyncStatus
+  synced_at?: string | null
+  scanned_at: string
+}
+
+/** Result of `query_index`; `total` counts matches before paging. */
+export interface IndexPage {
+  items: IndexEntry[]
+  total: number
+}
+
+/** One group from `find_duplicates`; every path holds the same contents. */
+export interface DuplicateGroup {
+  hash: string
+  size: number
+  paths: string[]
+  reclaimable_bytes: number
+}
+
+/** Emitted once as `scan_summary` after a scan's final progress event. */
+export interface ScanSummaryEvent {
+  scan_id: string
+  path: string
+  duration_ms: number
+  walk_ms: number
+  metadata_ms: number
+  thumbnail_ms: number
+  throttle_sleep_ms: number
+  paused_ms: number
+  processed: number
+  matched: number
+  match_rate: number
+  total_bytes: number
+  error_count: number
+  cancelled: boolean
+  timed_out: boolean
+}
+
+export interface WatchEvent {
+  path: string
+  files: number
+  status: 'syncing' | 'synced' | 'deleted' | 'error'
+  upserted?: number
+  deleted?: number
+  error?: string
+}
+
+/** `scheduled_sync` payload: one per run start and end, one per watched folder, or a held run. */
+export interface ScheduledSyncEvent {
+  status: 'started' | 'synced' | 'error' | 'finished' | 'held'
+  path?: string
+  folders?: number
+  added?: number
+  changed?: number
+  removed?: number
+  upserted?: number
+  deleted?: number
+  error?: string
+  /** Why a due run waits: "on battery" or "in use". */
+  reason?: string
+  retry_at?: string
+}
+
+export interface LibraryFolder {
+  path: string
+  added_at: string
+  /** False while the folder is missing, e.g. on an unplugged drive. */
+  available: boolean
+}
+
+export interface LibraryFolderProgress {
+  path: string
+  /** The folder's own scan, `<library scan_id>:<n>`. */
+  scan_id: string
+  state: 'pending' | 'scanning' | 'done' | 'cancelled' | 'error'
+  processed: number
+  matched: number
+  error_count: number
+  total_bytes: number
+}
+
+/** `library_scan_progress` payload: totals over every library root so far. */
+export interface LibraryScanProgressEvent {
+  scan_id: string
+  processed: number
+  matched: number
+  error_count: number
+  total_bytes: number
+  folders: LibraryFolderProgress[]
+  done: boolean
+}
+
 export interface IndexerState {
   rootPath: string | null
   phase: IndexerPhase
@@ -99,7 +315,7 @@ window.__TAURA_INDEXER = indexerStore
 // ---- Config ----
 const SCAN_INTERVAL_MIN = Number(localStorage.getItem('taura.scan.interval.min') || '30') // minutes
 const DEFAULT_THROTTLE_MS = 40 // built-in gentle default
-const MAX_INLINE_FILE_BYTES = 8 * 1024 * 1024 // 8MB safety to avoid ballooning JSON
+const INLINE_MAX_EDGE = 2048 // larger images are downscaled natively before inlining
 const STREAM_CHUNK_SIZE = 8
 const STREAM_REQUEUE_LIMIT = 2
 const STREAM_RETRY_DELAY_MS = 1200
@@ -113,6 +329,10 @@ type UploadPayloadItem = {
   lat?: number | null
   lon?: number | null
   bytes_b64?: string
+  frames_b64?: string[]
+  /** Set on items split from a PDF by `pdfPages`. */
+  page?: number
+  text?: string
 }
 
 type UploadAggregate = {
@@ -232,7 +452,11 @@ subscribeConfig(() => {
 export async function initIndexer() {
   // restore root path if persisted
   const savedRoot = localStorage.getItem('taura.root')
-  if (savedRoot) indexerStore.patch({ rootPath: savedRoot })
+  if (savedRoot) {
+    indexerStore.patch({ rootPath: savedRoot })
+    // roots picked before grants existed need one to stay readable
+    await grantRootAccess(savedRoot)
+  }
 
   // Initialize backend default throttle
   if (!localStorage.getItem('taura.scan.throttle.ms')) {
@@ -270,6 +494,9 @@ export async function initIndexer() {
     })
   })
 
+  // "Scan now" from the tray menu
+  await listen('scan-requested', () => { void startFullScan() })
+
   if (RESCAN_ON_START && savedRoot) {
     void startFullScan()
   }
@@ -286,9 +513,19 @@ export async function initIndexer() {
   }, 60 * 1000)
 }
 
+// The fs plugin only reads folders granted by the backend; grants persist.
+async function grantRootAccess(p: string) {
+  try {
+    await invoke('grant_folder_access', { path: p })
+  } catch (e) {
+    console.warn('failed to grant read access to', p, e)
+  }
+}
+
 export async function setRootPath(p: string) {
   const st = indexerStore.get()
   if (st.rootPath === p) return
+  await grantRootAccess(p)
   if (scanning) {
     pendingRoot = p
     indexerStore.patch({ pendingRoot: p })
@@ -312,31 +549,25 @@ export async function startFullScan() {
   indexerStore.patch({ phase: 'scanning', scan: { processed: 0, total: 0, matched: 0, startedAt: Date.now() } })
   try {
     const throttlePref = Number(localStorage.getItem('taura.scan.throttle.ms') || String(DEFAULT_THROTTLE_MS))
-    const res: any = await invoke('scan_folder', { path: st.rootPath, maxSamples: 50000, throttleMs: throttlePref })
-    // res.items contains enumerated media; batch upload
-    await batchUpload(res.items as any[])
+    const res: any = await invoke('scan_folder', { path: st.rootPath, maxSamples: 50000, throttleMs: throttlePref, hashContent: true })
+    // res.items contains enumerated media; copies of an earlier file's contents are uploaded once
+    await batchUpload((res.items as any[]).filter(item => !item.duplicate_of))
   } catch (e: any) {
-    indexerStore.patch({ phase: 'error', error: String(e) })
+    indexerStore.patch({ phase: 'error', error: errorMessage(e) })
   } finally {
     scanning = false
     if (!uploading) indexerStore.patchNested(s => { if (s.phase === 'scanning') s.phase = 'idle' })
   }
 }
 
-export async function stopScan() {
-  if (!scanning) return
-  try { await invoke('stop_scan') } catch {}
-}
-
-function toBase64(data: Uint8Array): string {
-  if (data.length === 0) return ''
-  let binary = ''
-  const chunkSize = 0x8000
-  for (let i = 0; i < data.length; i += chunkSize) {
-    const chunk = data.subarray(i, Math.min(i + chunkSize, data.length))
-    binary += String.fromCharCode(...chunk)
+export async function stopScan(): Promise<boolean> {
+  if (!scanning) return false
+  try {
+    const res = await invoke<StopScanResult>('stop_scan')
+    return res.stopped
+  } catch {
+    return false
   }
-  return btoa(binary)
 }
 
 async function batchUpload(items: any[]) {
@@ -443,7 +674,7 @@ async function uploadWorkerLoop(): Promise<void> {
             await delay(STREAM_RETRY_DELAY_MS)
             continue
           }
-          aggregateTotals.embedErrors.push({ uri: 'batch', error: err instanceof Error ? err.message : String(err) })
+          aggregateTotals.embedErrors.push({ uri: 'batch', error: errorMessage(err) })
           aggregateTotals.embeddedFailed += payloadItems.length
         }
       }
@@ -572,19 +803,15 @@ async function prepareStreamPayload(items: any[]): Promise<{ payloadItems: Uploa
 
     if (shouldInlineBytes && (modality === 'image' || modality === 'pdf_page')) {
       try {
-        const raw = await readFile(uri)
-        const bytes = raw instanceof Uint8Array ? raw : Uint8Array.from(raw as unknown as number[])
-        if (bytes.length === 0) {
+        // read, downscaled and encoded in Rust so the file never sits in the webview
+        const prepared = await invoke<UploadPayloadItem>('prepare_upload_item', { path: uri, maxEdge: INLINE_MAX_EDGE, userId })
+        if (!prepared.bytes_b64) {
           localReadErrors.push({ uri, error: 'file empty' })
           continue
         }
-        if (bytes.length > MAX_INLINE_FILE_BYTES) {
-          localReadErrors.push({ uri, error: `file too large (${(bytes.length / (1024 * 1024)).toFixed(1)}MB)` })
-          continue
-        }
-        base.bytes_b64 = toBase64(bytes)
-      } catch (err: any) {
-        localReadErrors.push({ uri, error: err?.message || 'read failed' })
+        base.bytes_b64 = prepared.bytes_b64
+      } catch (err) {
+        localReadErrors.push({ uri, error: errorMessage(err) })
         continue
       }
     }
@@ -74,7 +74,7 @@ function Overlay() {
 
     const handleKey = (e: KeyboardEvent) => {
       if (e.key === 'Escape') {
-        invoke('toggle_overlay').catch(() => {})
+        invoke('hide_overlay').catch(() => {})
         return
       }
       if (e.key === 'Tab') {
@@ -1,7 +1,7 @@
 import React, { createContext, useContext, useEffect, useMemo, useRef, useState } from 'react'
 import { invoke } from '@tauri-apps/api/core'
 import { updateConfig, getConfig, getApiBase } from './config'
-import { fetchStats } from '../api'
+import { errorMessage, fetchStats } from '../api'
 import type { StatsResponse } from '../api'
 import { initIndexer } from '../indexer'
 
@@ -17,6 +17,16 @@ export interface Session {
   sub?: string | null
   client_id?: string | null
   client_secret?: string | null
+  provider?: string | null
+}
+
+export interface AccountSummary {
+  account_id: string
+  email?: string | null
+  name?: string | null
+  picture?: string | null
+  provider?: string | null
+  active: boolean
 }
 
 interface InternalState {
@@ -65,7 +75,7 @@ export const AuthProvider: React.FC<{ children: React.ReactNode }> = ({ children
           setState({ session: null, loading: false, stats: null })
         }
       } catch (e: any) {
-        if (!cancelled) setState({ session: null, loading: false, error: String(e) })
+        if (!cancelled) setState({ session: null, loading: false, error: errorMessage(e) })
       }
     })()
     return () => { cancelled = true }
@@ -105,6 +115,9 @@ export const AuthProvider: React.FC<{ children: React.ReactNode }> = ({ children
     try {
       const secret = (import.meta as any).env?.VITE_TAURA_GOOGLE_CLIENT_SECRET || (window as any).TAURA_GOOGLE_CLIENT_SECRET
       const cfg: any = secret ? { clientId, clientSecret: secret } : { clientId }
+      // Web-type clients need the pre-registered loopback port
+      const redirectPort = Number((import.meta as any).env?.VITE_TAURA_GOOGLE_REDIRECT_PORT)
+      if (redirectPort > 0) cfg.redirectPort = redirectPort
       const res = await invoke<{ session: Session }>('google_auth_start', { cfg })
       const sess = res.session
       syncConfig(sess)
@@ -121,7 +134,7 @@ export const AuthProvider: React.FC<{ children: React.ReactNode }> = ({ children
       initIndexer().catch(() => {})
       return sess
     } catch (e: any) {
-      setState(s => ({ ...s, loading: false, error: String(e) }))
+      setState(s => ({ ...s, loading: false, error: errorMessage(e) }))
       throw e
     }
   }
@@ -142,7 +155,7 @@ export const AuthProvider: React.FC<{ children: React.ReactNode }> = ({ children
       setState(s => ({ ...s, session: fresh }))
       return fresh
     } catch (e) {
-      setState(s => ({ ...s, error: String(e) }))
+      setState(s => ({ ...s, error: errorMessage(e) }))
       return state.session
     }
   }
@@ -26,6 +26,10 @@ CREATE TABLE IF NOT EXISTS media (
 -- Ensure a user cannot have duplicate URI entries
 CREATE UNIQUE INDEX IF NOT EXISTS idx_media_user_uri ON media(user_id, uri);
 
+-- Pages split from a PDF by the companion: 1-based page number and the page's text
+ALTER TABLE media ADD COLUMN IF NOT EXISTS page INT;
+ALTER TABLE media ADD COLUMN IF NOT EXISTS page_text TEXT;
+
 -- Drop helper view so we can recreate tables when dimensions change
 DROP VIEW IF EXISTS media_with_vec;
 
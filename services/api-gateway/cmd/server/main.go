@@ -78,8 +78,10 @@ func main() {
 	app.Post("/search", handlers.PostSearch)
 	app.Post("/sync", handlers.PostSync)
 	app.Post("/sync/missing", handlers.PostSyncMissing)
+	app.Post("/sync/delete", handlers.PostSyncDelete)
 	app.Post("/sync/stream", handlers.PostSyncStream)
 	app.Get("/stats", handlers.GetStats)
+	app.Get("/embed/status", handlers.GetEmbedStatus)
 	app.Post("/users/upsert", handlers.PostUpsertUser)
 	app.Post("/auth/google", handlers.PostAuthGoogle)
 
@@ -5,6 +5,7 @@ import (
 	"errors"
 	"fmt"
 	"log"
+	"math"
 	"strings"
 	"sync"
 	"time"
@@ -16,6 +17,8 @@ type imageTask struct {
 	mediaID  string
 	uri      string
 	bytes    []byte
+	frames   [][]byte // set for videos instead of bytes; averaged into one vector
+	text     string   // set for PDF pages sent without an image; embedded as text
 	attempts int
 }
 
@@ -94,6 +97,31 @@ func EnqueueImage(mediaID, uri string, bytes []byte) error {
 	return processorInst.enqueue(task)
 }
 
+// EnqueueVideo queues a video's sampled frames; the stored vector is their normalised mean.
+func EnqueueVideo(mediaID, uri string, frames [][]byte) error {
+	if processorInst == nil {
+		return errors.New("embed queue not initialised")
+	}
+	if len(frames) == 0 {
+		return errors.New("no frames")
+	}
+	task := imageTask{mediaID: mediaID, uri: uri, frames: frames}
+	return processorInst.enqueue(task)
+}
+
+// EnqueueText queues a PDF page's text for pages that arrive without a rendered image. Text and
+// image vectors share one space, so the page is found by the same queries.
+func EnqueueText(mediaID, uri, text string) error {
+	if processorInst == nil {
+		return errors.New("embed queue not initialised")
+	}
+	if strings.TrimSpace(text) == "" {
+		return errors.New("empty text")
+	}
+	task := imageTask{mediaID: mediaID, uri: uri, text: text}
+	return processorInst.enqueue(task)
+}
+
 func QueueDepth() int {
 	if processorInst == nil {
 		return 0
@@ -164,9 +192,31 @@ func (p *imageProcessor) processBatch(batch []imageTask) {
 	if len(batch) == 0 {
 		return
 	}
-	payload := make([][]byte, len(batch))
+	images := make([]imageTask, 0, len(batch))
+	var texts []imageTask
+	for _, task := range batch {
+		if task.text != "" {
+			texts = append(texts, task)
+		} else {
+			images = append(images, task)
+		}
+	}
+	p.processTextBatch(texts)
+	if len(images) == 0 {
+		return
+	}
+	batch = images
+	// videos contribute one image per frame; spans map tasks back to their vectors
+	payload := make([][]byte, 0, len(batch))
+	spans := make([][2]int, len(batch))
 	for i, task := range batch {
-		payload[i] = task.bytes
+		start := len(payload)
+		if len(task.frames) > 0 {
+			payload = append(payload, task.frames...)
+		} else {
+			payload = append(payload, task.bytes)
+		}
+		spans[i] = [2]int{start, len(payload)}
 	}
 	log.Printf("embed queue dispatch batch=%d current_depth=%d", len(batch), len(p.tasks))
 
@@ -185,14 +235,16 @@ func (p *imageProcessor) processBatch(batch []imageTask) {
 	success := 0
 	failures := 0
 	for i, task := range batch {
-		var vec []float32
-		if i < len(vecs) {
-			vec = vecs[i]
-		}
+		var parts [][]float32
 		var perErr string
-		if errs != nil && i < len(errs) {
-			perErr = errs[i]
+		for j := spans[i][0]; j < spans[i][1]; j++ {
+			if j < len(vecs) && len(vecs[j]) > 0 {
+				parts = append(parts, vecs[j])
+			} else if errs != nil && j < len(errs) && perErr == "" {
+				perErr = errs[j]
+			}
 		}
+		vec := meanVector(parts)
 		if len(vec) == 0 {
 			if perErr == "" {
 				perErr = "empty vector"
@@ -215,6 +267,72 @@ func (p *imageProcessor) processBatch(batch []imageTask) {
 	}
 }
 
+func (p *imageProcessor) processTextBatch(batch []imageTask) {
+	if len(batch) == 0 {
+		return
+	}
+	texts := make([]string, len(batch))
+	for i, task := range batch {
+		texts[i] = task.text
+	}
+	log.Printf("embed queue dispatch text batch=%d current_depth=%d", len(batch), len(p.tasks))
+
+	ctx, cancel := context.WithTimeout(context.Background(), time.Duration(envInt("EMBEDDER_QUEUE_REQUEST_TIMEOUT_SECONDS", 60))*time.Second)
+	defer cancel()
+
+	vecs, err := TextBatch(ctx, texts)
+	if err != nil {
+		log.Printf("embed queue text batch failed count=%d err=%v", len(batch), err)
+		for _, task := range batch {
+			p.handleFailure(task, err.Error())
+		}
+		return
+	}
+	for i, task := range batch {
+		if i >= len(vecs) || len(vecs[i]) == 0 {
+			p.handleFailure(task, "empty vector")
+			continue
+		}
+		if err := p.persistEmbedding(ctx, task.mediaID, vecs[i]); err != nil {
+			p.handleFailure(task, err.Error())
+		}
+	}
+}
+
+// meanVector averages same-length vectors and scales the result to unit length, so a video's
+// frames rank against images like a single image would. A lone vector is returned as is.
+func meanVector(vecs [][]float32) []float32 {
+	if len(vecs) == 0 {
+		return nil
+	}
+	if len(vecs) == 1 {
+		return vecs[0]
+	}
+	dim := len(vecs[0])
+	sum := make([]float64, dim)
+	for _, vec := range vecs {
+		if len(vec) != dim {
+			return nil
+		}
+		for i, f := range vec {
+			sum[i] += float64(f)
+		}
+	}
+	var norm float64
+	for _, f := range sum {
+		norm += f * f
+	}
+	norm = math.Sqrt(norm)
+	if norm == 0 {
+		return nil
+	}
+	out := make([]float32, dim)
+	for i, f := range sum {
+		out[i] = float32(f / norm)
+	}
+	return out
+}
+
 func (p *imageProcessor) persistEmbedding(ctx context.Context, mediaID string, vec []float32) error {
 	if mediaID == "" {
 		return errors.New("missing media id")
@@ -0,0 +1,51 @@
+package handlers
+
+import (
+	"context"
+	"github.com/TAURAAI/taura/api-gateway/internal/db"
+	"github.com/TAURAAI/taura/api-gateway/internal/embed"
+	"github.com/gofiber/fiber/v2"
+	"log"
+	"strings"
+)
+
+type EmbedFailure struct {
+	URI   string `json:"uri"`
+	Error string `json:"error"`
+}
+
+// EmbedStatusResponse is the embedding backlog as the companion polls it.
+// QueueDepth counts the whole queue, which isn't split by user.
+type EmbedStatusResponse struct {
+	QueueDepth int                `json:"queue_depth"`
+	Embedded   int64              `json:"embedded"`
+	Failed     []EmbedFailure     `json:"failed"`
+	Embedder   embed.HealthStatus `json:"embedder"`
+}
+
+func GetEmbedStatus(c *fiber.Ctx) error {
+	database, ok := c.Locals("db").(*db.Database)
+	if !ok || database == nil {
+		return fiber.NewError(fiber.StatusInternalServerError, "db missing")
+	}
+
+	ctx := context.Background()
+	userUUID, ok := resolveUserID(ctx, database, strings.TrimSpace(c.Query("user_id")))
+	if !ok {
+		return fiber.NewError(fiber.StatusBadRequest, "invalid user")
+	}
+
+	var embedded int64
+	if err := database.Pool.QueryRow(ctx, `SELECT COUNT(*) FROM media_vecs mv JOIN media m ON m.id = mv.media_id WHERE m.user_id=$1 AND m.deleted=false`, userUUID).Scan(&embedded); err != nil {
+		log.Printf("embed status count error user=%s err=%v", userUUID, err)
+		return fiber.NewError(fiber.StatusInternalServerError, "embedded count error")
+	}
+
+	// failed embeds are only logged, not kept per item
+	return c.JSON(EmbedStatusResponse{
+		QueueDepth: embed.QueueDepth(),
+		Embedded:   embedded,
+		Failed:     []EmbedFailure{},
+		Embedder:   embed.HealthSnapshot(),
+	})
+}
@@ -3,17 +3,20 @@ package handlers
 import (
 	"bufio"
 	"bytes"
+	"compress/gzip"
 	"context"
 	"database/sql"
 	"encoding/base64"
 	"encoding/json"
 	"errors"
+	"fmt"
 	"github.com/TAURAAI/taura/api-gateway/internal/db"
 	"github.com/TAURAAI/taura/api-gateway/internal/embed"
 	"github.com/gofiber/fiber/v2"
 	"github.com/google/uuid"
 	"github.com/jackc/pgconn"
 	"github.com/jackc/pgx/v5"
+	"io"
 	"log"
 	"os"
 	"strings"
@@ -25,15 +28,18 @@ import (
 // ON CONFLICT DO UPDATE and never escalate to 23505 spam. We only log unexpected errors.
 
 type MediaUpsert struct {
-	UserID   string   `json:"user_id"`
-	Modality string   `json:"modality"`
-	URI      string   `json:"uri"`
-	TS       *string  `json:"ts"`
-	Lat      *float64 `json:"lat"`
-	Lon      *float64 `json:"lon"`
-	Album    *string  `json:"album"`
-	Source   *string  `json:"source"`
-	BytesB64 *string  `json:"bytes_b64,omitempty"`
+	UserID    string   `json:"user_id"`
+	Modality  string   `json:"modality"`
+	URI       string   `json:"uri"`
+	TS        *string  `json:"ts"`
+	Lat       *float64 `json:"lat"`
+	Lon       *float64 `json:"lon"`
+	Album     *string  `json:"album"`
+	Source    *string  `json:"source"`
+	BytesB64  *string  `json:"bytes_b64,omitempty"`
+	FramesB64 []string `json:"frames_b64,omitempty"` // video frames, embedded as one vector
+	Page      *int     `json:"page,omitempty"`       // 1-based, on pages split from a PDF
+	Text      *string  `json:"text,omitempty"`       // text embedded in a PDF page
 }
 
 type SyncRequest struct {
@@ -54,6 +60,15 @@ type SyncMissingResponse struct {
 	Missing []string `json:"missing"`
 }
 
+type SyncDeleteRequest struct {
+	UserID string   `json:"user_id"`
+	URIs   []string `json:"uris"`
+}
+
+type SyncDeleteResponse struct {
+	Deleted []string `json:"deleted"`
+}
+
 type failureDetail struct {
 	URI   string `json:"uri"`
 	Error string `json:"error"`
@@ -91,14 +106,19 @@ var syncVersionOnce sync.Once
 
 const maxInlinePayloadBytes = 25 * 1024 * 1024
 
-const mediaUpsertStatement = `INSERT INTO media (user_id, modality, uri, ts, album, source, lat, lon)
-	VALUES ($1,$2,$3,$4,$5,$6,$7,$8)
+// The companion samples at most 32 frames per video.
+const maxVideoFrames = 32
+
+const mediaUpsertStatement = `INSERT INTO media (user_id, modality, uri, ts, album, source, lat, lon, page, page_text)
+	VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10)
 	ON CONFLICT (user_id, uri) DO UPDATE SET
 	  ts = COALESCE(EXCLUDED.ts, media.ts),
 	  album = COALESCE(EXCLUDED.album, media.album),
 	  source = COALESCE(EXCLUDED.source, media.source),
 	  lat = COALESCE(EXCLUDED.lat, media.lat),
-	  lon = COALESCE(EXCLUDED.lon, media.lon)
+	  lon = COALESCE(EXCLUDED.lon, media.lon),
+	  page = COALESCE(EXCLUDED.page, media.page),
+	  page_text = COALESCE(EXCLUDED.page_text, media.page_text)
 	RETURNING id, (xmax = 0) AS inserted`
 
 const ensureUserStatement = `WITH ins AS (
@@ -139,7 +159,7 @@ func parseTimestamp(ts *string) *time.Time {
 func upsertMedia(ctx context.Context, database *db.Database, userUUID string, item MediaUpsert) (string, bool, error) {
 	var mediaID string
 	var inserted bool
-	err := database.Pool.QueryRow(ctx, mediaUpsertStatement, userUUID, item.Modality, item.URI, parseTimestamp(item.TS), item.Album, item.Source, item.Lat, item.Lon).Scan(&mediaID, &inserted)
+	err := database.Pool.QueryRow(ctx, mediaUpsertStatement, userUUID, item.Modality, item.URI, parseTimestamp(item.TS), item.Album, item.Source, item.Lat, item.Lon, item.Page, item.Text).Scan(&mediaID, &inserted)
 	if err != nil {
 		return "", false, err
 	}
@@ -317,6 +337,35 @@ func resolveInlineBytes(item MediaUpsert) ([]byte, []failureDetail) {
 	return nil, nil
 }
 
+// resolveFrames decodes a video's sampled frames. Frames beyond maxVideoFrames are ignored and
+// the decoded total must stay within maxInlinePayloadBytes, like a single inline payload.
+func resolveFrames(item MediaUpsert) ([][]byte, []failureDetail) {
+	encoded := item.FramesB64
+	if len(encoded) > maxVideoFrames {
+		encoded = encoded[:maxVideoFrames]
+	}
+	frames := make([][]byte, 0, len(encoded))
+	total := 0
+	for i, frame := range encoded {
+		decoded, err := base64.StdEncoding.DecodeString(frame)
+		if err != nil {
+			return nil, []failureDetail{{URI: item.URI, Error: fmt.Sprintf("decode frame %d: %v", i, err)}}
+		}
+		if len(decoded) == 0 {
+			continue
+		}
+		total += len(decoded)
+		if total > maxInlinePayloadBytes {
+			return nil, []failureDetail{{URI: item.URI, Error: "video frames exceed 25MB"}}
+		}
+		frames = append(frames, decoded)
+	}
+	if len(frames) == 0 {
+		return nil, []failureDetail{{URI: item.URI, Error: "video frames empty"}}
+	}
+	return frames, nil
+}
+
 func processSyncItem(ctx context.Context, database *db.Database, item MediaUpsert) itemResult {
 	res := itemResult{}
 	if item.UserID == "" || item.URI == "" || item.Modality == "" {
@@ -359,7 +408,8 @@ func processSyncItem(ctx context.Context, database *db.Database, item MediaUpser
 	}
 	incomingTS := parseTimestamp(item.TS)
 	lower := strings.ToLower(item.Modality)
-	if lower != "image" && lower != "pdf_page" {
+	isVideo := lower == "video" && len(item.FramesB64) > 0
+	if lower != "image" && lower != "pdf_page" && !isVideo {
 		return res
 	}
 	exists, err := mediaEmbeddingExists(ctx, database, mediaID)
@@ -370,11 +420,37 @@ func processSyncItem(ctx context.Context, database *db.Database, item MediaUpser
 			return res
 		}
 	}
+	if isVideo {
+		frames, frameFailures := resolveFrames(item)
+		if len(frameFailures) > 0 {
+			res.readFailures = append(res.readFailures, frameFailures...)
+			return res
+		}
+		res.requested = 1
+		if err := embed.EnqueueVideo(mediaID, item.URI, frames); err != nil {
+			res.embedFailures = append(res.embedFailures, failureDetail{URI: item.URI, Error: err.Error()})
+			log.Printf("/sync enqueue failed uri=%s err=%v", item.URI, err)
+			return res
+		}
+		res.queued = 1
+		log.Printf("/sync enqueued uri=%s media_id=%s frames=%d queue_depth=%d", item.URI, mediaID, len(frames), embed.QueueDepth())
+		return res
+	}
 	inline, readFailures := resolveInlineBytes(item)
 	if len(readFailures) > 0 {
 		res.readFailures = append(res.readFailures, readFailures...)
 	}
 	if len(inline) == 0 {
+		// a page sent without its image still has its text to go by
+		if item.Page != nil && item.Text != nil && strings.TrimSpace(*item.Text) != "" {
+			res.requested = 1
+			if err := embed.EnqueueText(mediaID, item.URI, *item.Text); err != nil {
+				res.embedFailures = append(res.embedFailures, failureDetail{URI: item.URI, Error: err.Error()})
+				log.Printf("/sync enqueue failed uri=%s err=%v", item.URI, err)
+				return res
+			}
+			res.queued = 1
+		}
 		return res
 	}
 	res.requested = 1
@@ -482,6 +558,64 @@ func PostSyncMissing(c *fiber.Ctx) error {
 	return c.JSON(SyncMissingResponse{Missing: missing})
 }
 
+func deleteMedia(ctx context.Context, database *db.Database, userID string, uris []string) ([]string, error) {
+	trimmed := make([]string, 0, len(uris))
+	for _, uri := range uris {
+		if t := strings.TrimSpace(uri); t != "" {
+			trimmed = append(trimmed, t)
+		}
+	}
+	if len(trimmed) == 0 {
+		return []string{}, nil
+	}
+	// media_vecs rows go with their media via ON DELETE CASCADE; a PDF's URI also takes the
+	// pages split from it, stored as <uri>#page=<n>
+	rows, err := database.Pool.Query(ctx, `DELETE FROM media WHERE user_id=$1
+	  AND (uri = ANY($2) OR (modality = 'pdf_page' AND split_part(uri, '#page=', 1) = ANY($2)))
+	  RETURNING uri`, userID, trimmed)
+	if err != nil {
+		return nil, err
+	}
+	defer rows.Close()
+	deleted := make([]string, 0, len(trimmed))
+	for rows.Next() {
+		var uri string
+		if err := rows.Scan(&uri); err != nil {
+			return nil, err
+		}
+		deleted = append(deleted, uri)
+	}
+	return deleted, rows.Err()
+}
+
+func PostSyncDelete(c *fiber.Ctx) error {
+	var req SyncDeleteRequest
+	if err := c.BodyParser(&req); err != nil {
+		return fiber.NewError(fiber.StatusBadRequest, err.Error())
+	}
+	database, ok := c.Locals("db").(*db.Database)
+	if !ok || database == nil {
+		return fiber.NewError(fiber.StatusInternalServerError, "db missing")
+	}
+	if req.UserID == "" || len(req.URIs) == 0 {
+		return c.JSON(SyncDeleteResponse{Deleted: []string{}})
+	}
+
+	ctx := context.Background()
+	userUUID, ok := resolveUserID(ctx, database, req.UserID)
+	if !ok {
+		return fiber.NewError(fiber.StatusBadRequest, "invalid user")
+	}
+
+	deleted, err := deleteMedia(ctx, database, userUUID, req.URIs)
+	if err != nil {
+		log.Printf("/sync/delete failed user_id=%s err=%v", userUUID, err)
+		return fiber.NewError(fiber.StatusInternalServerError, "delete failed")
+	}
+
+	return c.JSON(SyncDeleteResponse{Deleted: deleted})
+}
+
 func PostSyncStream(c *fiber.Ctx) error {
 	database, ok := c.Locals("db").(*db.Database)
 	if !ok || database == nil {
@@ -491,9 +625,18 @@ func PostSyncStream(c *fiber.Ctx) error {
 	ctx := context.Background()
 	stats := syncAccumulator{}
 
-	reader := c.Context().RequestBodyStream()
+	var reader io.Reader = c.Context().RequestBodyStream()
 	if reader == nil {
-		reader = bytes.NewReader(c.Body())
+		// raw body: c.Body() would already inflate gzip and we decode it below
+		reader = bytes.NewReader(c.Request().Body())
+	}
+	if strings.EqualFold(c.Get(fiber.HeaderContentEncoding), "gzip") {
+		gz, err := gzip.NewReader(reader)
+		if err != nil {
+			return fiber.NewError(fiber.StatusBadRequest, "invalid gzip body: "+err.Error())
+		}
+		defer gz.Close()
+		reader = gz
 	}
 
 	scanner := bufio.NewScanner(reader)
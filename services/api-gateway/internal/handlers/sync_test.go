@@ -130,3 +130,97 @@ func TestProcessSyncItemReembedsWhenTimestampChanges(t *testing.T) {
 		t.Fatalf("unexpected failure uri: %s", res.embedFailures[0].URI)
 	}
 }
+
+func TestProcessSyncItemEmbedsVideoFrames(t *testing.T) {
+	ctx := context.Background()
+	originalUpsert := performUpsertMedia
+	originalCheck := mediaEmbeddingExists
+	originalLookup := lookupExistingMediaTimestamp
+	defer func() {
+		performUpsertMedia = originalUpsert
+		mediaEmbeddingExists = originalCheck
+		lookupExistingMediaTimestamp = originalLookup
+	}()
+
+	performUpsertMedia = func(ctx context.Context, database *db.Database, userUUID string, item MediaUpsert) (string, bool, error) {
+		return "media-456", true, nil
+	}
+	lookupExistingMediaTimestamp = func(ctx context.Context, database *db.Database, userUUID string, uri string) (*time.Time, error) {
+		return nil, nil
+	}
+	mediaEmbeddingExists = func(ctx context.Context, database *db.Database, mediaID string) (bool, error) {
+		return false, nil
+	}
+
+	item := MediaUpsert{
+		UserID:    uuid.NewString(),
+		Modality:  "video",
+		URI:       "file:///clip.mp4",
+		FramesB64: []string{"aGVsbG8=", "d29ybGQ="},
+	}
+
+	res := processSyncItem(ctx, &db.Database{}, item)
+
+	// the embed queue isn't running in tests, so the frames reach it and fail there
+	if res.requested != 1 {
+		t.Fatalf("expected requested=1 got %d", res.requested)
+	}
+	if len(res.embedFailures) != 1 {
+		t.Fatalf("expected 1 embed failure got %d", len(res.embedFailures))
+	}
+	if len(res.readFailures) != 0 {
+		t.Fatalf("expected no read failures got %v", res.readFailures)
+	}
+
+	item.FramesB64 = []string{"not base64!"}
+	res = processSyncItem(ctx, &db.Database{}, item)
+	if res.requested != 0 || len(res.readFailures) != 1 {
+		t.Fatalf("expected an undecodable frame to be a read failure, got requested=%d read_failures=%v", res.requested, res.readFailures)
+	}
+}
+
+func TestProcessSyncItemEmbedsPageTextWithoutImage(t *testing.T) {
+	ctx := context.Background()
+	originalUpsert := performUpsertMedia
+	originalCheck := mediaEmbeddingExists
+	originalLookup := lookupExistingMediaTimestamp
+	defer func() {
+		performUpsertMedia = originalUpsert
+		mediaEmbeddingExists = originalCheck
+		lookupExistingMediaTimestamp = originalLookup
+	}()
+
+	var stored MediaUpsert
+	performUpsertMedia = func(ctx context.Context, database *db.Database, userUUID string, item MediaUpsert) (string, bool, error) {
+		stored = item
+		return "media-789", true, nil
+	}
+	lookupExistingMediaTimestamp = func(ctx context.Context, database *db.Database, userUUID string, uri string) (*time.Time, error) {
+		return nil, nil
+	}
+	mediaEmbeddingExists = func(ctx context.Context, database *db.Database, mediaID string) (bool, error) {
+		return false, nil
+	}
+
+	page := 3
+	text := "quarterly report"
+	item := MediaUpsert{
+		UserID:   uuid.NewString(),
+		Modality: "pdf_page",
+		URI:      "file:///report.pdf#page=3",
+		Page:     &page,
+		Text:     &text,
+	}
+
+	res := processSyncItem(ctx, &db.Database{}, item)
+
+	if stored.Page == nil || *stored.Page != 3 || stored.Text == nil || *stored.Text != text {
+		t.Fatalf("expected page and text to reach the upsert, got page=%v text=%v", stored.Page, stored.Text)
+	}
+	if res.requested != 1 {
+		t.Fatalf("expected requested=1 got %d", res.requested)
+	}
+	if len(res.embedFailures) != 1 {
+		t.Fatalf("expected 1 embed failure got %d", len(res.embedFailures))
+	}
+}